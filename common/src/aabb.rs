@@ -0,0 +1,139 @@
+//! 轴对齐包围盒
+
+use crate::ray::Ray;
+use crate::{Point3, Vector3};
+
+/// Axis-aligned bounding box. A box with `min > max` on some axis is
+/// empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    /// The box containing nothing; the identity of [`Aabb::union`].
+    pub const EMPTY: Aabb = Aabb {
+        min: Point3 {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+            z: f64::INFINITY,
+        },
+        max: Point3 {
+            x: f64::NEG_INFINITY,
+            y: f64::NEG_INFINITY,
+            z: f64::NEG_INFINITY,
+        },
+    };
+
+    /// The box spanned by two opposite corners in any order.
+    pub fn new(a: &Point3, b: &Point3) -> Self {
+        Aabb {
+            min: Point3::new([a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)]),
+            max: Point3::new([a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)]),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Aabb {
+            min: Point3::new([
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ]),
+            max: Point3::new([
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ]),
+        }
+    }
+
+    pub fn union_point(&self, p: &Point3) -> Self {
+        self.union(&Aabb { min: *p, max: *p })
+    }
+
+    pub fn contains(&self, p: &Point3) -> bool {
+        (0..3).all(|axis| self.min[axis] <= p[axis] && p[axis] <= self.max[axis])
+    }
+
+    pub fn diagonal(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// 0 for an empty box.
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Whether `ray` passes through the box between `t_min` and `t_max`.
+    pub fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        // Slab test: intersect the intervals in which the ray is between
+        // the two planes of each axis. Infinite `1 / d` of rays parallel to
+        // an axis work out.
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point3, vec3};
+
+    #[test]
+    fn test_union_and_measures() {
+        let a = Aabb::new(&point3![1, 1, 1], &point3![0, 0, 0]);
+        assert_eq!(a.min, point3![0, 0, 0]);
+        assert_eq!(Aabb::EMPTY.union(&a), a);
+        assert!(Aabb::EMPTY.is_empty() && !a.is_empty());
+        assert_eq!(Aabb::EMPTY.surface_area(), 0.0);
+
+        let b = a.union_point(&point3![2, 0.5, 0.5]);
+        assert_eq!(b.max, point3![2, 1, 1]);
+        assert_eq!(b.surface_area(), 10.0);
+        assert_eq!(b.centroid(), point3![1, 0.5, 0.5]);
+        assert!(b.contains(&point3![1.5, 0.5, 0.5]) && !a.contains(&point3![1.5, 0.5, 0.5]));
+    }
+
+    #[test]
+    fn test_hit() {
+        let b = Aabb::new(&point3![-1, -1, -1], &point3![1, 1, 1]);
+        let ray = |o: Point3, d: Vector3| Ray::new(&o, &d);
+        assert!(b.hit(&ray(point3![0, 0, 5], vec3![0, 0, -1]), 0.0, f64::INFINITY));
+        assert!(!b.hit(&ray(point3![0, 0, 5], vec3![0, 0, 1]), 0.0, f64::INFINITY));
+        assert!(!b.hit(&ray(point3![0, 0, 5], vec3![0, 0, -1]), 0.0, 3.0));
+        // Parallel to two axes, outside and inside the slabs.
+        assert!(!b.hit(&ray(point3![2, 0, 5], vec3![0, 0, -1]), 0.0, f64::INFINITY));
+        assert!(b.hit(
+            &ray(point3![0.5, 0.5, 0], vec3![1, 0, 0]),
+            0.0,
+            f64::INFINITY
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! 关键帧动画
+
+use crate::camera::CameraSetup;
+use crate::transform::Transform;
+use crate::{Point3, Vector3};
+use std::ops::{Add, Mul, Sub};
+
+/// How a [`Track`] moves from one key to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Straight from key to key, with a kink at every key.
+    Linear,
+    /// Catmull-Rom spline: passes through the keys with a smooth velocity.
+    Spline,
+}
+
+pub const INTERPOLATION_NAMES: [&str; 2] = ["linear", "spline"];
+
+pub fn interpolation_from_name(name: &str) -> Result<Interpolation, String> {
+    match name {
+        "linear" => Ok(Interpolation::Linear),
+        "spline" => Ok(Interpolation::Spline),
+        _ => Err(format!(
+            "unknown interpolation: {} (expected one of {})",
+            name,
+            INTERPOLATION_NAMES.join(", ")
+        )),
+    }
+}
+
+/// A value changing over time, given by its value at key times in seconds.
+/// It holds the first and the last value before and after the keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    /// Sorted by time, no two at the same time.
+    keys: Vec<(f64, T)>,
+    pub interpolation: Interpolation,
+}
+
+impl<T, D> Track<T>
+where
+    T: Copy + Add<D, Output = T> + Sub<Output = D>,
+    D: Copy + Add<Output = D> + Mul<f64, Output = D>,
+{
+    /// Panics without keys. Of keys at the same time the last one is kept.
+    pub fn new(interpolation: Interpolation, keys: Vec<(f64, T)>) -> Self {
+        assert!(!keys.is_empty(), "a track needs at least one key");
+        let mut track = Track {
+            keys: Vec::with_capacity(keys.len()),
+            interpolation,
+        };
+        for (time, value) in keys {
+            track = track.with_key(time, value);
+        }
+        track
+    }
+
+    /// The same value at all times.
+    pub fn constant(value: T) -> Self {
+        Track {
+            keys: vec![(0.0, value)],
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    /// Add a key, replacing any at the same time.
+    pub fn with_key(mut self, time: f64, value: T) -> Self {
+        let i = self.keys.partition_point(|&(t, _)| t < time);
+        match self.keys.get_mut(i) {
+            Some(key) if key.0 == time => key.1 = value,
+            _ => self.keys.insert(i, (time, value)),
+        }
+        self
+    }
+
+    pub fn keys(&self) -> &[(f64, T)] {
+        &self.keys
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let keys = &self.keys;
+        let i = keys.partition_point(|&(t, _)| t <= time);
+        if i == 0 {
+            return keys[0].1;
+        }
+        if i == keys.len() {
+            return keys[i - 1].1;
+        }
+        let ((t0, p0), (t1, p1)) = (keys[i - 1], keys[i]);
+        let h = t1 - t0;
+        let x = (time - t0) / h;
+        match self.interpolation {
+            Interpolation::Linear => p0 + (p1 - p0) * x,
+            Interpolation::Spline => {
+                // Cubic Hermite with the tangents of Catmull-Rom, scaled to
+                // the length of the interval since the keys need not be
+                // evenly spaced.
+                let m0 = self.tangent(i - 1) * h;
+                let m1 = self.tangent(i) * h;
+                let (x2, x3) = (x * x, x * x * x);
+                // The weights of p0 and p1 add up to 1, so this is a
+                // weighted sum of points too.
+                p0 + ((p1 - p0) * (3.0 * x2 - 2.0 * x3) + m0 * (x3 - 2.0 * x2 + x) + m1 * (x3 - x2))
+            }
+        }
+    }
+
+    /// Velocity at key `i`: from the previous to the next key, one-sided at
+    /// the ends.
+    fn tangent(&self, i: usize) -> D {
+        let (a, b) = (i.saturating_sub(1), (i + 1).min(self.keys.len() - 1));
+        let ((ta, pa), (tb, pb)) = (self.keys[a], self.keys[b]);
+        (pb - pa) * (1.0 / (tb - ta))
+    }
+}
+
+/// Keyframed placement and lens of a camera.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraAnimation {
+    pub lookfrom: Track<Point3>,
+    pub lookat: Track<Point3>,
+    /// Vertical field of view in degrees; kept as set up if not given.
+    pub vfov: Option<Track<f64>>,
+    /// Distance in focus; the distance to `lookat` if not given.
+    pub focus_dist: Option<Track<f64>>,
+}
+
+impl CameraAnimation {
+    pub fn new(lookfrom: Track<Point3>, lookat: Track<Point3>) -> Self {
+        CameraAnimation {
+            lookfrom,
+            lookat,
+            vfov: None,
+            focus_dist: None,
+        }
+    }
+
+    /// `setup` with the animated parameters at `time`.
+    pub fn setup_at(&self, setup: &CameraSetup, time: f64) -> CameraSetup {
+        let lookfrom = self.lookfrom.at(time);
+        let lookat = self.lookat.at(time);
+        CameraSetup {
+            lookfrom,
+            lookat,
+            vfov: self.vfov.as_ref().map_or(setup.vfov, |vfov| vfov.at(time)),
+            focus_dist: match &self.focus_dist {
+                Some(focus_dist) => focus_dist.at(time),
+                None => (lookfrom - lookat).length(),
+            },
+            ..setup.clone()
+        }
+    }
+}
+
+/// Parse camera keys: one per line with the time in seconds, `lookfrom`,
+/// `lookat` and optionally the vertical field of view and the focus
+/// distance, separated by whitespace. All keys have the same columns. `#`
+/// starts a comment.
+pub fn parse_camera_keys(
+    text: &str,
+    interpolation: Interpolation,
+) -> Result<CameraAnimation, String> {
+    let mut rows = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values = line
+            .split_whitespace()
+            .map(|v| v.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        if !(7..=9).contains(&values.len()) {
+            return Err(format!("line {}: expected 7 to 9 columns", number + 1));
+        }
+        if rows
+            .first()
+            .is_some_and(|first: &Vec<f64>| first.len() != values.len())
+        {
+            return Err(format!(
+                "line {}: expected as many columns as the first key",
+                number + 1
+            ));
+        }
+        rows.push(values);
+    }
+    if rows.is_empty() {
+        return Err("no camera keys".to_string());
+    }
+    let track = |f: &dyn Fn(&[f64]) -> Point3| {
+        Track::new(interpolation, rows.iter().map(|r| (r[0], f(r))).collect())
+    };
+    let scalar = |column: usize| {
+        (rows[0].len() > column).then(|| {
+            Track::new(
+                interpolation,
+                rows.iter().map(|r| (r[0], r[column])).collect(),
+            )
+        })
+    };
+    Ok(CameraAnimation {
+        lookfrom: track(&|r| Point3::new([r[1], r[2], r[3]])),
+        lookat: track(&|r| Point3::new([r[4], r[5], r[6]])),
+        vfov: scalar(7),
+        focus_dist: scalar(8),
+    })
+}
+
+/// Keyframed transform of an object: scaled, then turned about the y axis,
+/// then moved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectAnimation {
+    pub translation: Track<Vector3>,
+    /// Rotation about the y axis in degrees.
+    pub rotation_y: Track<f64>,
+    pub scale: Track<f64>,
+}
+
+impl ObjectAnimation {
+    pub fn transform_at(&self, time: f64) -> Transform {
+        let scale = self.scale.at(time);
+        Transform::translate(&self.translation.at(time))
+            * Transform::rotate_y(self.rotation_y.at(time))
+            * Transform::scale(scale, scale, scale)
+    }
+}
+
+impl Default for ObjectAnimation {
+    /// Staying in place.
+    fn default() -> Self {
+        ObjectAnimation {
+            translation: Track::constant(Vector3::new0()),
+            rotation_y: Track::constant(0.0),
+            scale: Track::constant(1.0),
+        }
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Bokeh;
+    use crate::{point3, vec3};
+
+    #[test]
+    fn test_linear_track() {
+        let track = Track::new(Interpolation::Linear, vec![(2.0, 10.0), (0.0, 0.0)]);
+        assert_eq!(track.keys()[0], (0.0, 0.0));
+        assert_eq!(track.at(-1.0), 0.0);
+        assert_eq!(track.at(0.5), 2.5);
+        assert_eq!(track.at(2.0), 10.0);
+        assert_eq!(track.at(3.0), 10.0);
+        let track = track.with_key(2.0, 4.0);
+        assert_eq!(track.keys().len(), 2);
+        assert_eq!(track.at(1.0), 2.0);
+    }
+
+    #[test]
+    fn test_spline_track() {
+        let keys: Vec<(f64, f64)> = vec![(0.0, 0.0), (1.0, 1.0), (3.0, 0.0), (4.0, 2.0)];
+        let track = Track::new(Interpolation::Spline, keys.clone());
+        for (time, value) in keys {
+            assert_eq!(track.at(time), value);
+        }
+        // Smooth across a key, unlike linear interpolation.
+        let slope = |track: &Track<f64>, t: f64| (track.at(t + 1e-6) - track.at(t - 1e-6)) / 2e-6;
+        let before = (track.at(1.0) - track.at(1.0 - 1e-6)) / 1e-6;
+        let after = (track.at(1.0 + 1e-6) - track.at(1.0)) / 1e-6;
+        assert!((before - after).abs() < 1e-4, "{} {}", before, after);
+        assert!(slope(&track, 1.0).abs() < 1e-4);
+
+        // Evenly moving keys give even motion.
+        let track = Track::new(
+            Interpolation::Spline,
+            (0..4).map(|i| (i as f64, vec3![i, 0, 0])).collect(),
+        );
+        assert!((track.at(1.25) - vec3![1.25, 0, 0]).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_camera_keys() {
+        let text = "# time lookfrom lookat vfov\n\
+                    0  0 0 4  0 0 0  40\n\
+                    2  0 0 8  0 0 0  20  # zoom in\n";
+        let animation = parse_camera_keys(text, Interpolation::Linear).unwrap();
+        let setup = CameraSetup {
+            lookfrom: point3![0, 0, 1],
+            lookat: point3![0, 0, 0],
+            vup: vec3![0, 1, 0],
+            vfov: 90.0,
+            aspect_ratio: 1.0,
+            aperture: 0.1,
+            focus_dist: 1.0,
+            bokeh: Bokeh::default(),
+            shift: (0.0, 0.0),
+            tilt: (0.0, 0.0),
+        };
+        let setup = animation.setup_at(&setup, 1.0);
+        assert_eq!(setup.lookfrom, point3![0, 0, 6]);
+        assert_eq!(setup.vfov, 30.0);
+        assert_eq!(setup.focus_dist, 6.0);
+        assert_eq!(setup.aperture, 0.1);
+
+        assert!(parse_camera_keys("", Interpolation::Linear).is_err());
+        assert!(parse_camera_keys("0 0 0 1 0 0", Interpolation::Linear).is_err());
+        assert!(
+            parse_camera_keys("0 0 0 1 0 0 0 40\n1 0 0 1 0 0 0", Interpolation::Linear).is_err()
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! 二进制读写 (小端)
+
+use std::io::{self, Read, Write};
+
+pub fn write_u64(os: &mut dyn Write, value: u64) -> io::Result<()> {
+    os.write_all(&value.to_le_bytes())
+}
+
+pub fn write_f64(os: &mut dyn Write, value: f64) -> io::Result<()> {
+    os.write_all(&value.to_le_bytes())
+}
+
+pub fn read_u64(is: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    is.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub fn read_f64(is: &mut dyn Read) -> io::Result<f64> {
+    Ok(f64::from_bits(read_u64(is)?))
+}
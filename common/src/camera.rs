@@ -1,20 +1,265 @@
+//! 相机
+
+mod aperture;
+mod orthographic;
+mod panoramic;
+mod physical;
+mod realistic;
+mod stereo;
+
+pub use aperture::{shape_from_name, ApertureMask, ApertureShape, Bokeh};
+pub use orthographic::OrthographicCamera;
+pub use panoramic::{CubeMapCamera, EquirectangularCamera, FisheyeCamera, FisheyeProjection};
+pub use physical::{PhysicalCamera, PhysicalSettings};
+pub use realistic::{parse_lens, LensElement, RealisticCamera, DOUBLE_GAUSS_50MM};
+pub use stereo::{Convergence, StereoCamera, StereoLayout, StereoRig};
+
 use crate::ray::Ray;
-use crate::rtweekend::{degrees_to_radians, random_in_unit_disk};
-use crate::vec3::Vec3;
-use crate::Point3;
+use crate::rtweekend::degrees_to_radians;
+use crate::sampler::Sampler;
+use crate::vec3::Vector3;
+use crate::{Color, Point3};
+use std::fs;
+
+/// Maps positions on the film to rays into the scene.
+pub trait Camera: Send + Sync {
+    /// The ray through `(s, t)`, where `(0, 0)` is the lower left and
+    /// `(1, 1)` the upper right corner of the film; `None` where the film
+    /// sees nothing, e.g. outside the image circle of a fisheye.
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<Ray>;
+
+    /// Like [`Camera::get_ray`], with the factor per color channel by which
+    /// the camera scales the radiance along the ray.
+    fn get_weighted_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<(Ray, Color)> {
+        self.get_ray(s, t, sampler)
+            .map(|ray| (ray, Color::new([1.0, 1.0, 1.0])))
+    }
 
-pub struct Camera {
+    /// Factor from the radiance arriving at the camera to the value
+    /// recorded on the film.
+    fn exposure(&self) -> f64 {
+        1.0
+    }
+
+    /// Width of the view `distance` in front of the camera, the unit of its
+    /// horizontal lens shift; `None` if it cannot shift its lens.
+    fn view_width(&self, _distance: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Orthonormal basis of a camera at `lookfrom` looking at `lookat`: `u`
+/// points right, `v` up and `w` backwards, away from `lookat`.
+pub fn look_at_basis(
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vector3,
+) -> (Vector3, Vector3, Vector3) {
+    let w = (lookfrom - lookat).unit_vector();
+    let u = vup.cross(&w).unit_vector();
+    let v = w.cross(&u);
+    (u, v, w)
+}
+
+/// How the render binaries place the camera; the projection is chosen by
+/// name with [`from_name`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraSetup {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vector3,
+    /// Vertical field of view in degrees.
+    pub vfov: f64,
+    /// Width / height.
+    pub aspect_ratio: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    /// Shape of the defocus blur of the thin lens cameras.
+    pub bokeh: Bokeh,
+    /// Lens shift and focal plane tilt and swing of the thin lens cameras,
+    /// see [`PerspectiveCamera::with_shift`] and
+    /// [`PerspectiveCamera::with_tilt`].
+    pub shift: (f64, f64),
+    pub tilt: (f64, f64),
+}
+
+/// Names accepted by [`from_name`].
+pub const CAMERA_NAMES: [&str; 9] = [
+    "perspective",
+    "physical",
+    "realistic",
+    "orthographic",
+    "equirectangular",
+    "equirectangular-stereo",
+    "fisheye",
+    "fisheye-equisolid",
+    "cubemap",
+];
+
+/// Create a camera from `name[:parameter...]`:
+///
+/// - `perspective`: thin lens with the field of view and aperture of `setup`.
+/// - `physical[:focal_length[:f_number[:shutter[:iso[:sensor_width]]]]]`:
+///   thin lens set up like a real camera, in millimeters and seconds, with
+///   the defaults of [`PhysicalSettings`]; focuses at `focus_dist`.
+/// - `realistic[:FILE]`: traces rays through the lens prescription in
+///   `FILE`, see [`parse_lens`], or a double Gauss 50 mm f/2; focuses at
+///   `focus_dist` with a 36 mm wide film.
+/// - `orthographic[:view_height]`: parallel projection; by default the view
+///   is as high as the field of view is at `lookat`.
+/// - `equirectangular`: 360° x 180° panorama, for an aspect ratio of 2.
+/// - `equirectangular-stereo[:eye_distance]`: a panorama per eye stacked
+///   over/under, left on top, for an aspect ratio of 1; the eyes are 0.064
+///   apart by default.
+/// - `fisheye[:fov]`, `fisheye-equisolid[:fov]`: circular fisheye with the
+///   equidistant or equisolid projection and a field of view of 180° by
+///   default.
+/// - `cubemap`: six 90° faces side by side, for an aspect ratio of 6; see
+///   [`CubeMapCamera`].
+pub fn from_name(spec: &str, setup: &CameraSetup) -> Result<Box<dyn Camera>, String> {
+    if let Some(lens) = spec.strip_prefix("realistic") {
+        let elements = match lens.strip_prefix(':') {
+            Some(path) => {
+                let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                parse_lens(&text).map_err(|e| format!("{}: {}", path, e))?
+            }
+            None if lens.is_empty() => parse_lens(DOUBLE_GAUSS_50MM)?,
+            None => return Err(format!("unknown camera: {}", spec)),
+        };
+        return Ok(Box::new(RealisticCamera::new(
+            setup.lookfrom,
+            setup.lookat,
+            setup.vup,
+            elements,
+            PhysicalSettings::default().sensor_width,
+            setup.aspect_ratio,
+            setup.focus_dist,
+        )?));
+    }
+    let mut parts = spec.split(':');
+    let name = parts.next().unwrap_or_default();
+    let parameters = parts
+        .map(|value| match value.parse::<f64>() {
+            Ok(p) if p > 0.0 => Ok(p),
+            Ok(_) => Err(format!("camera parameter must be positive: {}", value)),
+            Err(e) => Err(format!("invalid camera parameter {:?}: {}", value, e)),
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    let (lookfrom, lookat, vup) = (setup.lookfrom, setup.lookat, setup.vup);
+
+    let camera: Box<dyn Camera> = match (name, parameters.as_slice()) {
+        ("perspective", []) => Box::new(
+            PerspectiveCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                setup.vfov,
+                setup.aspect_ratio,
+                setup.aperture,
+                setup.focus_dist,
+            )
+            .with_bokeh(setup.bokeh.clone())
+            .with_shift(setup.shift.0, setup.shift.1)
+            .with_tilt(setup.tilt.0, setup.tilt.1),
+        ),
+        ("physical", p) if p.len() <= 5 => {
+            let mut settings = PhysicalSettings::default();
+            let fields = [
+                &mut settings.focal_length,
+                &mut settings.f_number,
+                &mut settings.shutter_time,
+                &mut settings.iso,
+                &mut settings.sensor_width,
+            ];
+            for (field, &value) in fields.into_iter().zip(p) {
+                *field = value;
+            }
+            let mut camera = PhysicalCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                setup.aspect_ratio,
+                setup.focus_dist,
+                settings,
+                setup.bokeh.clone(),
+            );
+            camera.camera = camera
+                .camera
+                .with_shift(setup.shift.0, setup.shift.1)
+                .with_tilt(setup.tilt.0, setup.tilt.1);
+            Box::new(camera)
+        }
+        ("orthographic", [] | [_]) => {
+            let view_height = parameters.first().copied().unwrap_or_else(|| {
+                let distance = (lookfrom - lookat).length();
+                2.0 * distance * (degrees_to_radians(setup.vfov) / 2.0).tan()
+            });
+            Box::new(OrthographicCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                setup.aspect_ratio * view_height,
+                view_height,
+            ))
+        }
+        ("equirectangular", []) => Box::new(EquirectangularCamera::new(lookfrom, lookat, vup)),
+        ("equirectangular-stereo", [] | [_]) => Box::new(
+            EquirectangularCamera::new(lookfrom, lookat, vup)
+                .with_stereo(parameters.first().copied().unwrap_or(0.064)),
+        ),
+        ("fisheye" | "fisheye-equisolid", [] | [_]) => {
+            let projection = match name {
+                "fisheye" => FisheyeProjection::Equidistant,
+                _ => FisheyeProjection::Equisolid,
+            };
+            let fov = parameters.first().copied().unwrap_or(180.0);
+            if fov > 360.0 {
+                return Err(format!("fisheye field of view above 360°: {}", fov));
+            }
+            Box::new(FisheyeCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                projection,
+                fov,
+                setup.aspect_ratio,
+            ))
+        }
+        ("cubemap", []) => Box::new(CubeMapCamera::new(lookfrom, lookat, vup)),
+        (name, _) if CAMERA_NAMES.contains(&name) => {
+            return Err(format!("too many parameters for camera {}", name))
+        }
+        _ => {
+            return Err(format!(
+                "unknown camera: {} (expected one of {})",
+                name,
+                CAMERA_NAMES.join(", ")
+            ))
+        }
+    };
+    Ok(camera)
+}
+
+/// Pinhole or thin lens camera with perspective projection.
+pub struct PerspectiveCamera {
     pub origin: Point3,
     pub lower_left_corner: Point3,
-    pub horizontal: Vec3,
-    pub vertical: Vec3,
-    pub u: Vec3, // horizontal
-    pub v: Vec3, // vertical
-    pub w: Vec3, // depth
+    pub horizontal: Vector3,
+    pub vertical: Vector3,
+    pub u: Vector3, // horizontal
+    pub v: Vector3, // vertical
+    pub w: Vector3, // depth
     pub lens_radius: f64,
+    /// Width / height.
+    pub aspect_ratio: f64,
+    pub bokeh: Bokeh,
+    /// Lens shift in image widths and heights.
+    pub shift: (f64, f64),
+    /// Point on and normal of the plane in focus when it is tilted.
+    pub focal_plane: Option<(Point3, Vector3)>,
 }
 
-impl Camera {
+impl PerspectiveCamera {
     /// lookfrom: the position where we place the camera
     /// lookat: the point we look at
     /// vup: view up vector
@@ -23,7 +268,7 @@ impl Camera {
     pub fn new(
         lookfrom: Point3,
         lookat: Point3,
-        vup: Vec3,
+        vup: Vector3,
         vfov: f64,
         aspect_ratio: f64,
         aperture: f64,
@@ -34,9 +279,7 @@ impl Camera {
         let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
 
-        let w = (lookfrom - lookat).unit_vector();
-        let u = vup.cross(&w).unit_vector();
-        let v = w.cross(&u);
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
 
         let origin = lookfrom;
         let horizontal = focus_dist * viewport_width * u;
@@ -44,8 +287,8 @@ impl Camera {
         let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w;
 
         let lens_radius = aperture / 2.0;
-        Camera {
-            origin: origin.clone(),
+        PerspectiveCamera {
+            origin,
             lower_left_corner,
             horizontal,
             vertical,
@@ -53,17 +296,138 @@ impl Camera {
             v,
             w,
             lens_radius,
+            aspect_ratio,
+            bokeh: Bokeh::default(),
+            shift: (0.0, 0.0),
+            focal_plane: None,
+        }
+    }
+
+    pub fn with_bokeh(mut self, bokeh: Bokeh) -> Self {
+        self.bokeh = bokeh;
+        self
+    }
+
+    /// Shift the lens parallel to the film by `x` image widths to the right
+    /// and `y` image heights up. The view moves without turning the camera,
+    /// so lines parallel to the film stay parallel, e.g. the verticals of a
+    /// building seen from below with a level camera.
+    pub fn with_shift(mut self, x: f64, y: f64) -> Self {
+        let (dx, dy) = (x - self.shift.0, y - self.shift.1);
+        self.lower_left_corner += dx * self.horizontal + dy * self.vertical;
+        self.shift = (x, y);
+        self
+    }
+
+    /// Tilt the plane in focus (Scheimpflug) about the horizontal axis of
+    /// the camera by `tilt` degrees, bringing its lower part nearer, and
+    /// swing it about the vertical axis by `swing` degrees, bringing its
+    /// right part nearer. It still passes through the focus distance on
+    /// the axis. The view itself does not change.
+    pub fn with_tilt(mut self, tilt: f64, swing: f64) -> Self {
+        if tilt == 0.0 && swing == 0.0 {
+            self.focal_plane = None;
+            return self;
+        }
+        let (tilt, swing) = (degrees_to_radians(tilt), degrees_to_radians(swing));
+        let normal =
+            tilt.cos() * (swing.cos() * self.w - swing.sin() * self.u) + tilt.sin() * self.v;
+        let focus_dist = (self.origin - self.lower_left_corner).dot(&self.w);
+        self.focal_plane = Some((self.origin - focus_dist * self.w, normal));
+        self
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let diagonal = self.aspect_ratio.hypot(1.0);
+        // Relative to the optical axis, which a shift moves.
+        let film = (
+            (2.0 * (s + self.shift.0) - 1.0) * self.aspect_ratio / diagonal,
+            (2.0 * (t + self.shift.1) - 1.0) / diagonal,
+        );
+        let lens = self.bokeh.sample_lens(sampler.get_2d(), film);
+        let (x, y) = lens?;
+        let offset = self.u * (self.lens_radius * x) + self.v * (self.lens_radius * y);
+        let mut target = self.lower_left_corner + s * self.horizontal + t * self.vertical;
+        if let Some((point, normal)) = self.focal_plane {
+            // Where the ray through the lens center meets the tilted plane;
+            // at infinity if it runs parallel to the plane or away from it.
+            let chief = target - self.origin;
+            let denominator = chief.dot(&normal);
+            let distance = (point - self.origin).dot(&normal);
+            if denominator * distance <= 0.0 {
+                return Some(Ray::new(&(self.origin + offset), &chief));
+            }
+            target = self.origin + distance / denominator * chief;
         }
+        Some(Ray::new(
+            &(self.origin + offset),
+            &(target - self.origin - offset),
+        ))
+    }
+
+    fn view_width(&self, distance: f64) -> Option<f64> {
+        let focus_dist = (self.origin - self.lower_left_corner).dot(&self.w);
+        Some(self.horizontal.length() / focus_dist * distance)
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::RandomSampler;
+    use crate::{point3, vec3};
+
+    fn camera(aperture: f64) -> PerspectiveCamera {
+        PerspectiveCamera::new(
+            point3![0, 0, 0],
+            point3![0, 0, -1],
+            vec3![0, 1, 0],
+            90.0,
+            1.0,
+            aperture,
+            2.0,
+        )
+    }
+
+    #[test]
+    fn test_shift_keeps_direction_of_view() {
+        let camera = camera(0.0).with_shift(0.0, 0.25);
+        let mut sampler = RandomSampler::new(0);
+        // The view moved up by a quarter of its height, 4 at distance 2.
+        let center = camera.get_ray(0.5, 0.5, &mut sampler).unwrap();
+        assert!((center.direction - vec3![0, 1, -2]).length() < 1e-12);
+        let bottom = camera.get_ray(0.5, 0.0, &mut sampler).unwrap();
+        assert!((bottom.direction - vec3![0, -1, -2]).length() < 1e-12);
+
+        // Shifting back restores the camera.
+        let camera = camera.with_shift(0.0, 0.0);
+        let center = camera.get_ray(0.5, 0.5, &mut sampler).unwrap();
+        assert!((center.direction - vec3![0, 0, -2]).length() < 1e-12);
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
-        Ray {
-            origin: self.origin + offset,
-            direction: self.lower_left_corner + s * self.horizontal + t * self.vertical
-                - self.origin
-                - offset,
+    #[test]
+    fn test_tilted_focal_plane() {
+        let camera = camera(0.5).with_tilt(30.0, 0.0);
+        let mut sampler = RandomSampler::new(0);
+        // All rays of a pixel meet on the plane in focus, which is nearer
+        // at the bottom and farther at the top.
+        for (t, nearer) in [(0.0, true), (0.5, false), (1.0, false)] {
+            let rays: Vec<Ray> = (0..16)
+                .map(|_| camera.get_ray(0.3, t, &mut sampler).unwrap())
+                .collect();
+            let (point, normal) = camera.focal_plane.unwrap();
+            let hit = |ray: &Ray| {
+                let k = (point - ray.origin).dot(&normal) / ray.direction.dot(&normal);
+                ray.at(k)
+            };
+            let first = hit(&rays[0]);
+            for ray in &rays {
+                assert!((hit(ray) - first).length() < 1e-9);
+            }
+            assert_eq!(-first.z < 2.0 - 1e-9, nearer, "{} {:?}", t, first);
         }
     }
 }
@@ -0,0 +1,247 @@
+use crate::color::luminance;
+use crate::pfm::read_pfm;
+use crate::sampling::{sample_regular_polygon, sample_unit_disk};
+use crate::Image;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+/// Outline of the opening of a lens, which out-of-focus highlights take.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ApertureShape {
+    #[default]
+    Disk,
+    /// Diaphragm of `blades` straight blades, the first corner at
+    /// `rotation` radians from the horizontal.
+    Polygon {
+        blades: usize,
+        rotation: f64,
+    },
+    Mask(Arc<ApertureMask>),
+}
+
+/// Aperture given by an image: brighter pixels let more light through. The
+/// image covers the square around the unit disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureMask {
+    pub width: usize,
+    pub height: usize,
+    /// Cumulative transmission of the rows, top first, ending in 1.
+    row_cdf: Vec<f64>,
+    /// Cumulative transmission within every row, each ending in 1.
+    column_cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// The transmission of every pixel is its luminance; `None` if no pixel
+    /// lets light through.
+    pub fn new(image: &Image) -> Option<Self> {
+        let (width, height) = (image.width, image.height);
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut column_cdf = Vec::with_capacity(width * height);
+        let mut total = 0.0;
+        for row in image.pixels.chunks_exact(width) {
+            let mut sum = 0.0;
+            for pixel in row {
+                sum += luminance(pixel).max(0.0);
+                column_cdf.push(sum);
+            }
+            let start = column_cdf.len() - width;
+            for c in &mut column_cdf[start..] {
+                *c = if sum > 0.0 { *c / sum } else { 1.0 };
+            }
+            total += sum;
+            row_cdf.push(total);
+        }
+        if total <= 0.0 {
+            return None;
+        }
+        for c in &mut row_cdf {
+            *c /= total;
+        }
+        Some(ApertureMask {
+            width,
+            height,
+            row_cdf,
+            column_cdf,
+        })
+    }
+
+    /// Point in `[-1, 1]²` distributed like the transmission.
+    pub fn sample(&self, u: (f64, f64)) -> (f64, f64) {
+        let (row, v) = sample_cdf(&self.row_cdf, u.1);
+        let columns = &self.column_cdf[row * self.width..(row + 1) * self.width];
+        let (column, w) = sample_cdf(columns, u.0);
+        let x = (column as f64 + w) / self.width as f64;
+        let y = (row as f64 + v) / self.height as f64;
+        (2.0 * x - 1.0, 1.0 - 2.0 * y)
+    }
+}
+
+/// The bin of `cdf` that `u` falls in and where in the bin, from 0 to 1.
+fn sample_cdf(cdf: &[f64], u: f64) -> (usize, f64) {
+    let i = cdf.partition_point(|&c| c <= u).min(cdf.len() - 1);
+    let lo = if i == 0 { 0.0 } else { cdf[i - 1] };
+    let fraction = if cdf[i] > lo {
+        (u - lo) / (cdf[i] - lo)
+    } else {
+        0.5
+    };
+    (i, fraction.clamp(0.0, 1.0))
+}
+
+/// Parse `disk`, `polygon:BLADES[:ROTATION]` with the rotation in degrees,
+/// or the path of a PFM mask.
+pub fn shape_from_name(spec: &str) -> Result<ApertureShape, String> {
+    if spec.ends_with(".pfm") {
+        let file = File::open(spec).map_err(|e| format!("{}: {}", spec, e))?;
+        let pfm = read_pfm(&mut BufReader::new(file)).map_err(|e| format!("{}: {}", spec, e))?;
+        let image = Image::from_data(pfm.width, pfm.height, pfm.components, &pfm.data);
+        let mask = ApertureMask::new(&image).ok_or(format!("{} is black", spec))?;
+        return Ok(ApertureShape::Mask(Arc::new(mask)));
+    }
+    let mut parts = spec.split(':');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("disk"), None, _, _) => Ok(ApertureShape::Disk),
+        (Some("polygon"), Some(blades), rotation, None) => {
+            let blades = blades
+                .parse::<usize>()
+                .ok()
+                .filter(|&b| b >= 3)
+                .ok_or(format!("invalid number of blades: {}", blades))?;
+            let rotation = rotation
+                .map(|r| r.parse::<f64>())
+                .transpose()
+                .map_err(|e| format!("invalid blade rotation: {}", e))?
+                .unwrap_or(0.0);
+            Ok(ApertureShape::Polygon {
+                blades,
+                rotation: rotation.to_radians(),
+            })
+        }
+        _ => Err(format!(
+            "unknown aperture shape: {} (expected disk, polygon:BLADES[:ROTATION] or a .pfm mask)",
+            spec
+        )),
+    }
+}
+
+/// How the lens shapes the defocus blur of a thin lens camera.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bokeh {
+    pub shape: ApertureShape,
+    /// Optical vignetting: the lens barrel cuts the aperture off toward the
+    /// corners of the image, where highlights become cat's eyes. 0 for
+    /// none; at 1 about 40% of the aperture is left in the corners.
+    pub cat_eye: f64,
+    /// Squeeze factor of an anamorphic lens: highlights are this many times
+    /// as tall as wide. 1 for a spherical lens.
+    pub anamorphic_squeeze: f64,
+}
+
+impl Default for Bokeh {
+    fn default() -> Self {
+        Bokeh {
+            shape: ApertureShape::Disk,
+            cat_eye: 0.0,
+            anamorphic_squeeze: 1.0,
+        }
+    }
+}
+
+impl Bokeh {
+    /// Point on the aperture, in units of the lens radius, through which the
+    /// film position `film` sees the scene. `film` runs from -1 to 1 across
+    /// the diagonal of the image. `None` where the barrel blocks the light.
+    pub fn sample_lens(&self, u: (f64, f64), film: (f64, f64)) -> Option<(f64, f64)> {
+        let (x, y) = match &self.shape {
+            ApertureShape::Disk => {
+                let p = sample_unit_disk(u);
+                (p.x, p.y)
+            }
+            ApertureShape::Polygon { blades, rotation } => {
+                let p = sample_regular_polygon(u, *blades, *rotation);
+                (p.x, p.y)
+            }
+            ApertureShape::Mask(mask) => mask.sample(u),
+        };
+        if self.cat_eye > 0.0 {
+            // The exit pupil seen from off the axis: a disk shifted toward
+            // the image position.
+            let (dx, dy) = (x - self.cat_eye * film.0, y - self.cat_eye * film.1);
+            if dx * dx + dy * dy > 1.0 {
+                return None;
+            }
+        }
+        Some((x / self.anamorphic_squeeze, y))
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+
+    fn grid(n: usize) -> impl Iterator<Item = (f64, f64)> {
+        (0..n * n).map(move |i| {
+            (
+                ((i % n) as f64 + 0.5) / n as f64,
+                ((i / n) as f64 + 0.5) / n as f64,
+            )
+        })
+    }
+
+    #[test]
+    fn test_mask_samples_lit_pixels() {
+        // Only the top right quarter of the image is lit.
+        let mut image = Image::new(4, 4);
+        for (y, x) in [(0, 2), (0, 3), (1, 2), (1, 3)] {
+            image.pixels[y * 4 + x] = color![1, 1, 1];
+        }
+        let mask = ApertureMask::new(&image).unwrap();
+        for u in grid(16) {
+            let (x, y) = mask.sample(u);
+            assert!(
+                (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y),
+                "{:?}",
+                (x, y)
+            );
+        }
+        assert!(ApertureMask::new(&Image::new(2, 2)).is_none());
+    }
+
+    #[test]
+    fn test_cat_eye_and_squeeze() {
+        let bokeh = Bokeh {
+            cat_eye: 1.0,
+            anamorphic_squeeze: 2.0,
+            ..Default::default()
+        };
+        let samples = |film| grid(32).filter_map(|u| bokeh.sample_lens(u, film)).count();
+        // Nothing is cut off in the center, about 40% of the disk in the
+        // corners.
+        assert_eq!(samples((0.0, 0.0)), 32 * 32);
+        let corner = samples((0.707, 0.707)) as f64 / (32.0 * 32.0);
+        assert!((0.3..0.5).contains(&corner), "{}", corner);
+
+        for u in grid(8) {
+            let (x, _) = bokeh.sample_lens(u, (0.0, 0.0)).unwrap();
+            assert!(x.abs() <= 0.5 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_shape_from_name() {
+        assert_eq!(shape_from_name("disk"), Ok(ApertureShape::Disk));
+        assert_eq!(
+            shape_from_name("polygon:6:90"),
+            Ok(ApertureShape::Polygon {
+                blades: 6,
+                rotation: std::f64::consts::FRAC_PI_2
+            })
+        );
+        assert!(shape_from_name("polygon:2").is_err());
+        assert!(shape_from_name("star").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use super::{look_at_basis, Camera};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vector3;
+use crate::Point3;
+
+/// Parallel projection: all rays share the viewing direction and start on
+/// a rectangle around `lookfrom`, so sizes do not shrink with distance.
+pub struct OrthographicCamera {
+    pub lower_left_corner: Point3,
+    pub horizontal: Vector3,
+    pub vertical: Vector3,
+    /// Direction of every ray.
+    pub direction: Vector3,
+}
+
+impl OrthographicCamera {
+    /// lookfrom: center of the view rectangle
+    /// lookat: a point in the viewing direction
+    /// vup: view up vector
+    /// view_width, view_height: size of the view rectangle in scene units
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vector3,
+        view_width: f64,
+        view_height: f64,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+
+        let horizontal = view_width * u;
+        let vertical = view_height * v;
+        OrthographicCamera {
+            lower_left_corner: lookfrom - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            direction: -w,
+        }
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: f64, t: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        Some(Ray::new(
+            &(self.lower_left_corner + s * self.horizontal + t * self.vertical),
+            &self.direction,
+        ))
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::RandomSampler;
+    use crate::{point3, vec3};
+
+    #[test]
+    fn test_rays_are_parallel() {
+        let camera =
+            OrthographicCamera::new(point3![0, 0, 5], point3![0, 0, 0], vec3![0, 1, 0], 4.0, 2.0);
+        let mut sampler = RandomSampler::new(0);
+        let center = camera.get_ray(0.5, 0.5, &mut sampler).unwrap();
+        assert_eq!(center.origin, point3![0, 0, 5]);
+        assert_eq!(center.direction, vec3![0, 0, -1]);
+
+        let corner = camera.get_ray(0.0, 0.0, &mut sampler).unwrap();
+        assert_eq!(corner.origin, point3![-2, -1, 5]);
+        assert_eq!(corner.direction, center.direction);
+        let corner = camera.get_ray(1.0, 1.0, &mut sampler).unwrap();
+        assert_eq!(corner.origin, point3![2, 1, 5]);
+    }
+}
@@ -0,0 +1,292 @@
+use super::{look_at_basis, Camera};
+use crate::ray::Ray;
+use crate::rtweekend::degrees_to_radians;
+use crate::sampler::Sampler;
+use crate::vec3::Vector3;
+use crate::Point3;
+use std::f64::consts::PI;
+
+/// The full sphere of directions around `lookfrom` in latitude/longitude
+/// layout: `lookat` is in the center, the left and right edges look
+/// backwards, the top and bottom edges straight up and down.
+pub struct EquirectangularCamera {
+    pub origin: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+    /// Distance between the eyes of an over/under stereo panorama, if any.
+    pub eye_distance: Option<f64>,
+}
+
+impl EquirectangularCamera {
+    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vector3) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        EquirectangularCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            eye_distance: None,
+        }
+    }
+
+    /// Render omni-directional stereo: the upper half of the film is the
+    /// panorama of the left eye, the lower half that of the right eye. For
+    /// every direction the eyes sit on a circle of diameter `eye_distance`
+    /// around `lookfrom`, as if the head turned to look that way.
+    pub fn with_stereo(mut self, eye_distance: f64) -> Self {
+        self.eye_distance = Some(eye_distance);
+        self
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: f64, t: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let (t, eye) = match self.eye_distance {
+            Some(distance) if t >= 0.5 => (2.0 * t - 1.0, -distance / 2.0),
+            Some(distance) => (2.0 * t, distance / 2.0),
+            None => (t, 0.0),
+        };
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (t - 0.5) * PI;
+        let (sin_lon, cos_lon) = longitude.sin_cos();
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        let direction = cos_lat * sin_lon * self.u + sin_lat * self.v - cos_lat * cos_lon * self.w;
+        // To the right of the horizontal viewing direction.
+        let right = cos_lon * self.u + sin_lon * self.w;
+
+        Some(Ray::new(&(self.origin + eye * right), &direction))
+    }
+}
+
+/// How a [`FisheyeCamera`] maps the angle from the optical axis to the
+/// distance from the center of the image circle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeProjection {
+    /// Distance proportional to the angle.
+    Equidistant,
+    /// Equal areas of the image cover equal solid angles.
+    Equisolid,
+}
+
+/// Circular fisheye: the image circle touches the shorter edges of the film
+/// and covers `fov` degrees around the direction of `lookat`. Nothing is
+/// seen outside the circle.
+pub struct FisheyeCamera {
+    pub origin: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+    pub projection: FisheyeProjection,
+    /// Field of view across the image circle in radians.
+    pub fov: f64,
+    /// Width / height.
+    pub aspect_ratio: f64,
+}
+
+impl FisheyeCamera {
+    /// fov: field of view across the image circle in degrees, up to 360
+    /// aspect_ratio: width / height
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vector3,
+        projection: FisheyeProjection,
+        fov: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        FisheyeCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            projection,
+            fov: degrees_to_radians(fov),
+            aspect_ratio,
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: f64, t: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        // Coordinates in units of the radius of the image circle.
+        let (mut x, mut y) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+        if self.aspect_ratio >= 1.0 {
+            x *= self.aspect_ratio;
+        } else {
+            y /= self.aspect_ratio;
+        }
+        let r = (x * x + y * y).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => r * self.fov / 2.0,
+            FisheyeProjection::Equisolid => 2.0 * (r * (self.fov / 4.0).sin()).asin(),
+        };
+        let (cos_phi, sin_phi) = if r > 0.0 { (x / r, y / r) } else { (1.0, 0.0) };
+        let direction = theta.sin() * (cos_phi * self.u + sin_phi * self.v) - theta.cos() * self.w;
+
+        Some(Ray::new(&self.origin, &direction))
+    }
+}
+
+/// Six 90° views from `lookfrom` side by side, for a film six times as wide
+/// as high. From left to right the faces look right, left, up, down, forward
+/// (at `lookat`) and backward. The up and down faces have `u` to the right;
+/// the top of the up face is toward the back.
+pub struct CubeMapCamera {
+    pub origin: Point3,
+    /// Forward, right and up direction of every face.
+    pub faces: [(Vector3, Vector3, Vector3); 6],
+}
+
+impl CubeMapCamera {
+    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vector3) -> Self {
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        CubeMapCamera {
+            origin: lookfrom,
+            faces: [
+                (u, w, v),
+                (-u, -w, v),
+                (v, u, w),
+                (-v, u, -w),
+                (-w, u, v),
+                (w, -u, v),
+            ],
+        }
+    }
+}
+
+impl Camera for CubeMapCamera {
+    fn get_ray(&self, s: f64, t: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let x = (s * 6.0).clamp(0.0, 6.0);
+        let face = (x as usize).min(5);
+        let (forward, right, up) = self.faces[face];
+        let a = 2.0 * (x - face as f64) - 1.0;
+        let b = 2.0 * t - 1.0;
+
+        Some(Ray::new(&self.origin, &(forward + a * right + b * up)))
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::RandomSampler;
+    use crate::{point3, vec3};
+
+    fn assert_direction(ray: &Ray, expected: Vector3) {
+        let d = ray.direction.unit_vector();
+        assert!((d - expected).length() < 1e-9, "{:?} != {:?}", d, expected);
+    }
+
+    #[test]
+    fn test_equirectangular_directions() {
+        let camera =
+            EquirectangularCamera::new(point3![0, 0, 0], point3![0, 0, -1], vec3![0, 1, 0]);
+        let mut sampler = RandomSampler::new(0);
+        let mut ray = |s, t| camera.get_ray(s, t, &mut sampler).unwrap();
+        assert_direction(&ray(0.5, 0.5), vec3![0, 0, -1]);
+        assert_direction(&ray(0.75, 0.5), vec3![1, 0, 0]);
+        assert_direction(&ray(0.25, 0.5), vec3![-1, 0, 0]);
+        assert_direction(&ray(0.0, 0.5), vec3![0, 0, 1]);
+        assert_direction(&ray(0.3, 1.0), vec3![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_stereo_eyes() {
+        let camera =
+            EquirectangularCamera::new(point3![0, 0, 0], point3![0, 0, -1], vec3![0, 1, 0])
+                .with_stereo(0.1);
+        let mut sampler = RandomSampler::new(0);
+        // Looking forward: the left eye on top, the right eye below.
+        let left = camera.get_ray(0.5, 0.75, &mut sampler).unwrap();
+        let right = camera.get_ray(0.5, 0.25, &mut sampler).unwrap();
+        assert_direction(&left, vec3![0, 0, -1]);
+        assert_direction(&right, vec3![0, 0, -1]);
+        assert!((left.origin - point3![-0.05, 0, 0]).length() < 1e-12);
+        assert!((right.origin - point3![0.05, 0, 0]).length() < 1e-12);
+
+        // Looking right, the eyes are in front and behind.
+        let left = camera.get_ray(0.75, 0.75, &mut sampler).unwrap();
+        assert_direction(&left, vec3![1, 0, 0]);
+        assert!((left.origin - point3![0, 0, -0.05]).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_fisheye_projections() {
+        let mut sampler = RandomSampler::new(0);
+        for projection in [FisheyeProjection::Equidistant, FisheyeProjection::Equisolid] {
+            let camera = FisheyeCamera::new(
+                point3![0, 0, 0],
+                point3![0, 0, -1],
+                vec3![0, 1, 0],
+                projection,
+                180.0,
+                2.0,
+            );
+            assert_direction(
+                &camera.get_ray(0.5, 0.5, &mut sampler).unwrap(),
+                vec3![0, 0, -1],
+            );
+            // The rim of the image circle is 90° off the axis.
+            assert_direction(
+                &camera.get_ray(0.75, 0.5, &mut sampler).unwrap(),
+                vec3![1, 0, 0],
+            );
+            assert_direction(
+                &camera.get_ray(0.5, 0.0, &mut sampler).unwrap(),
+                vec3![0, -1, 0],
+            );
+            assert!(camera.get_ray(0.1, 0.5, &mut sampler).is_none());
+            assert!(camera.get_ray(0.7, 0.95, &mut sampler).is_none());
+        }
+
+        // Halfway to the rim, 45° off the axis with the equidistant
+        // projection, less with the equisolid one.
+        let mut angle = |projection| {
+            let camera = FisheyeCamera::new(
+                point3![0, 0, 0],
+                point3![0, 0, -1],
+                vec3![0, 1, 0],
+                projection,
+                180.0,
+                1.0,
+            );
+            let d = camera.get_ray(0.75, 0.5, &mut sampler).unwrap().direction;
+            d.unit_vector().x.asin().to_degrees()
+        };
+        assert!((angle(FisheyeProjection::Equidistant) - 45.0).abs() < 1e-9);
+        let equisolid = angle(FisheyeProjection::Equisolid);
+        assert!(
+            (equisolid - 2.0 * (0.5 * 45f64.to_radians().sin()).asin().to_degrees()).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_cube_map_faces() {
+        let camera = CubeMapCamera::new(point3![0, 0, 0], point3![0, 0, -1], vec3![0, 1, 0]);
+        let mut sampler = RandomSampler::new(0);
+        let mut ray = |s, t| camera.get_ray(s, t, &mut sampler).unwrap();
+        let centers = [
+            vec3![1, 0, 0],
+            vec3![-1, 0, 0],
+            vec3![0, 1, 0],
+            vec3![0, -1, 0],
+            vec3![0, 0, -1],
+            vec3![0, 0, 1],
+        ];
+        for (face, expected) in centers.into_iter().enumerate() {
+            assert_direction(&ray((face as f64 + 0.5) / 6.0, 0.5), expected);
+        }
+        // The faces meet: the right edge of the forward face is the left
+        // edge of the right-facing one.
+        assert_direction(&ray(5.0 / 6.0 - 1e-12, 0.5), vec3![1, 0, -1].unit_vector());
+        assert_direction(&ray(0.0, 0.5), vec3![1, 0, -1].unit_vector());
+        // The top of the up face looks toward the back.
+        assert_direction(&ray(2.5 / 6.0, 1.0), vec3![0, 1, 1].unit_vector());
+    }
+}
@@ -0,0 +1,162 @@
+use super::{Bokeh, Camera, PerspectiveCamera};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vector3;
+use crate::Point3;
+
+/// Settings of a real camera. Scene units are taken to be meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalSettings {
+    /// Focal length in millimeters.
+    pub focal_length: f64,
+    /// Width of the sensor in millimeters; its height follows from the
+    /// aspect ratio of the film.
+    pub sensor_width: f64,
+    /// Focal length / diameter of the aperture.
+    pub f_number: f64,
+    /// Time the shutter is open in seconds.
+    pub shutter_time: f64,
+    /// Sensitivity of the sensor.
+    pub iso: f64,
+}
+
+impl Default for PhysicalSettings {
+    /// A normal lens on a full frame sensor, exposed for luminances around
+    /// 1 like those of the scenes here: f/2, 1 s at ISO 400.
+    fn default() -> Self {
+        PhysicalSettings {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            f_number: 2.0,
+            shutter_time: 1.0,
+            iso: 400.0,
+        }
+    }
+}
+
+impl PhysicalSettings {
+    /// Vertical field of view in degrees.
+    pub fn vfov(&self, aspect_ratio: f64) -> f64 {
+        let sensor_height = self.sensor_width / aspect_ratio;
+        2.0 * (sensor_height / (2.0 * self.focal_length))
+            .atan()
+            .to_degrees()
+    }
+
+    /// Diameter of the aperture in meters.
+    pub fn aperture(&self) -> f64 {
+        self.focal_length / self.f_number / 1000.0
+    }
+
+    /// Exposure value at ISO 100: `log2(N² / t * 100 / S)`.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso).log2()
+    }
+
+    /// Factor from luminance to the value on the film, `1 / (1.2 2^EV100)`:
+    /// a luminance of `1.2 2^EV100` saturates the sensor (ISO 12232).
+    pub fn exposure(&self) -> f64 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
+
+/// Thin lens camera whose field of view, depth of field and brightness
+/// follow from [`PhysicalSettings`], so renders taken with different
+/// settings compare like photographs.
+pub struct PhysicalCamera {
+    pub camera: PerspectiveCamera,
+    pub settings: PhysicalSettings,
+    exposure: f64,
+}
+
+impl PhysicalCamera {
+    /// aspect_ratio: width / height
+    /// focus_dist: distance of the plane in focus
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vector3,
+        aspect_ratio: f64,
+        focus_dist: f64,
+        settings: PhysicalSettings,
+        bokeh: Bokeh,
+    ) -> Self {
+        PhysicalCamera {
+            camera: PerspectiveCamera::new(
+                lookfrom,
+                lookat,
+                vup,
+                settings.vfov(aspect_ratio),
+                aspect_ratio,
+                settings.aperture(),
+                focus_dist,
+            )
+            .with_bokeh(bokeh),
+            settings,
+            exposure: settings.exposure(),
+        }
+    }
+}
+
+impl Camera for PhysicalCamera {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        self.camera.get_ray(s, t, sampler)
+    }
+
+    fn exposure(&self) -> f64 {
+        self.exposure
+    }
+
+    fn view_width(&self, distance: f64) -> Option<f64> {
+        self.camera.view_width(distance)
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_of_view_and_aperture() {
+        let settings = PhysicalSettings::default();
+        // 24 mm high behind a 50 mm lens.
+        assert!((settings.vfov(1.5) - 26.991).abs() < 1e-3);
+        // A wider lens sees more.
+        let wide = PhysicalSettings {
+            focal_length: 24.0,
+            ..settings
+        };
+        assert!(wide.vfov(1.5) > settings.vfov(1.5));
+        assert!((settings.aperture() - 0.025).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exposure() {
+        // Sunny 16: f/16, 1/100 s at ISO 100 is about EV 15.
+        let sunny = PhysicalSettings {
+            f_number: 16.0,
+            shutter_time: 0.01,
+            iso: 100.0,
+            ..Default::default()
+        };
+        assert!((sunny.ev100() - 14.644).abs() < 1e-3);
+
+        // One stop more light, three ways.
+        let base = PhysicalSettings::default();
+        let brighter = [
+            PhysicalSettings {
+                shutter_time: 2.0,
+                ..base
+            },
+            PhysicalSettings { iso: 800.0, ..base },
+            PhysicalSettings {
+                f_number: 2.0 / 2f64.sqrt(),
+                ..base
+            },
+        ];
+        for settings in brighter {
+            assert!((settings.exposure() / base.exposure() - 2.0).abs() < 1e-9);
+        }
+    }
+}
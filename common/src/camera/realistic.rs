@@ -0,0 +1,486 @@
+use super::{look_at_basis, Camera};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vector3;
+use crate::{color, point3, vec3, Color, Point3};
+
+/// Double Gauss 50 mm f/2 (US patent 2,673,491, scaled from 100 mm), as in
+/// pbrt's `dgauss.50mm.dat`, with the Abbe numbers of the glasses.
+pub const DOUBLE_GAUSS_50MM: &str = "\
+# radius  thickness  ior    aperture  abbe
+29.475    3.76       1.670  25.2      47.1
+84.83     0.12       1      25.2
+19.275    4.025      1.670  23        47.1
+40.77     3.275      1.699  23        30.1
+12.75     5.705      1      18
+0         4.5        1      17.1
+-14.495   1.18       1.603  17        38.0
+40.77     6.065      1.658  20        57.3
+-20.385   0.19       1      20
+437.065   3.22       1.717  20        48.0
+-39.73    0          1      20
+";
+
+/// One surface of a lens prescription. Lengths are in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+    /// Radius of curvature, positive if the center of curvature lies toward
+    /// the film; 0 for the aperture stop.
+    pub radius: f64,
+    /// Distance along the axis to the next surface, or from the last
+    /// surface to the film.
+    pub thickness: f64,
+    /// Refractive index at 587.6 nm of the medium behind the surface.
+    pub ior: f64,
+    /// Diameter of the clear aperture.
+    pub aperture: f64,
+    /// Abbe number of the medium behind the surface; 0 for no dispersion.
+    pub abbe: f64,
+}
+
+impl LensElement {
+    pub fn is_stop(&self) -> bool {
+        self.radius == 0.0
+    }
+
+    /// Refractive index at `wavelength` nm, from a Cauchy fit through the
+    /// index at 587.6 nm and the Abbe number.
+    fn ior_at(&self, wavelength: f64) -> f64 {
+        if self.abbe == 0.0 {
+            return self.ior;
+        }
+        let (d, f, c): (f64, f64, f64) = (587.6, 486.1, 656.3);
+        let b = (self.ior - 1.0) / self.abbe / (1.0 / (f * f) - 1.0 / (c * c));
+        self.ior + b * (1.0 / (wavelength * wavelength) - 1.0 / (d * d))
+    }
+}
+
+/// Parse a prescription table: one surface per line from the front of the
+/// lens to the film, with radius, thickness, index of refraction, aperture
+/// and optionally the Abbe number, separated by whitespace. An index of 0
+/// stands for air. `#` starts a comment.
+pub fn parse_lens(text: &str) -> Result<Vec<LensElement>, String> {
+    let mut elements = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values = line
+            .split_whitespace()
+            .map(|v| v.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        let [radius, thickness, ior, aperture, ref rest @ ..] = values[..] else {
+            return Err(format!("line {}: expected at least 4 columns", number + 1));
+        };
+        let abbe = match rest {
+            [] => 0.0,
+            [abbe] => *abbe,
+            _ => return Err(format!("line {}: expected at most 5 columns", number + 1)),
+        };
+        if thickness < 0.0 || aperture <= 0.0 || ior < 0.0 || abbe < 0.0 {
+            return Err(format!("line {}: invalid surface", number + 1));
+        }
+        elements.push(LensElement {
+            radius,
+            thickness,
+            ior: if ior == 0.0 { 1.0 } else { ior },
+            aperture,
+            abbe,
+        });
+    }
+    if elements.is_empty() {
+        return Err("empty lens prescription".to_string());
+    }
+    Ok(elements)
+}
+
+/// Radial zones of the film for which the exit pupil is bounded.
+const PUPIL_ZONES: usize = 64;
+/// Rays traced per zone are `4 PUPIL_GRID²`.
+const PUPIL_GRID: usize = 32;
+/// Wavelength the lens is focused for, in nm.
+const WAVELENGTH_D: f64 = 587.6;
+/// Wavelengths traced for the red, green and blue channel of a lens with
+/// dispersion, in nm.
+const CHANNEL_WAVELENGTHS: [f64; 3] = [620.0, 550.0, 460.0];
+
+/// Camera tracing rays from the film through the spherical surfaces of a
+/// real lens (Kolb et al. 1995, as in pbrt's `RealisticCamera`). Distortion,
+/// field curvature, vignetting and, for glasses with an Abbe number,
+/// chromatic aberration come from the lens itself.
+///
+/// The film center is at `lookfrom`; scene units are taken to be meters.
+pub struct RealisticCamera {
+    pub origin: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+    /// The prescription, with the last thickness set to focus the lens.
+    pub elements: Vec<LensElement>,
+    /// Size of the film in millimeters.
+    pub film_width: f64,
+    pub film_height: f64,
+    /// Distance of the surfaces from the film along the axis.
+    vertex_z: Vec<f64>,
+    /// Bounds `[x0, x1, y0, y1]` on the plane of the rear surface of the
+    /// rays from a film point on the x axis that pass the lens, per zone.
+    exit_pupil: Vec<Option<[f64; 4]>>,
+    /// Area of the exit pupil seen from the film center.
+    reference_area: f64,
+    dispersive: bool,
+}
+
+impl RealisticCamera {
+    /// sensor_width: width of the film in millimeters
+    /// aspect_ratio: width / height
+    /// focus_dist: distance from the film at which the lens is focused
+    pub fn new(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vector3,
+        mut elements: Vec<LensElement>,
+        sensor_width: f64,
+        aspect_ratio: f64,
+        focus_dist: f64,
+    ) -> Result<Self, String> {
+        let thickness = focus(&elements, 1000.0 * focus_dist)?;
+        elements.last_mut().unwrap().thickness = thickness;
+
+        let (u, v, w) = look_at_basis(lookfrom, lookat, vup);
+        let mut camera = RealisticCamera {
+            origin: lookfrom,
+            u,
+            v,
+            w,
+            vertex_z: vertex_z(&elements),
+            dispersive: elements.iter().any(|e| e.abbe > 0.0),
+            elements,
+            film_width: sensor_width,
+            film_height: sensor_width / aspect_ratio,
+            exit_pupil: Vec::new(),
+            reference_area: 0.0,
+        };
+        camera.bound_exit_pupil();
+        if camera.reference_area == 0.0 {
+            return Err("no light reaches the center of the film".to_string());
+        }
+        Ok(camera)
+    }
+
+    /// Effective focal length in millimeters.
+    pub fn focal_length(&self) -> f64 {
+        // A ray parallel to the axis leaves toward the front focal point.
+        let h = 1e-3 * self.elements.last().unwrap().aperture;
+        match trace(
+            &self.elements,
+            &self.vertex_z,
+            point3![h, 0, 0],
+            vec3![0, 0, 1],
+            WAVELENGTH_D,
+        ) {
+            Some((_, d)) if d.x != 0.0 => -h * d.z / d.x,
+            _ => f64::INFINITY,
+        }
+    }
+
+    fn bound_exit_pupil(&mut self) {
+        let half_diagonal = self.film_width.hypot(self.film_height) / 2.0;
+        let rear_z = *self.vertex_z.last().unwrap();
+        let extent = 1.5 * self.elements.last().unwrap().aperture / 2.0;
+        let cell = 2.0 * extent / PUPIL_GRID as f64;
+        let mut pupils = Vec::with_capacity(PUPIL_ZONES);
+        let mut areas = Vec::with_capacity(PUPIL_ZONES);
+        for zone in 0..PUPIL_ZONES {
+            let mut bounds: Option<[f64; 4]> = None;
+            let mut passed = 0;
+            for k in 0..4 {
+                let r = (zone as f64 + (k as f64 + 0.5) / 4.0) / PUPIL_ZONES as f64 * half_diagonal;
+                for gy in 0..PUPIL_GRID {
+                    for gx in 0..PUPIL_GRID {
+                        let x = -extent + (gx as f64 + 0.5) * cell;
+                        let y = -extent + (gy as f64 + 0.5) * cell;
+                        let origin = point3![r, 0, 0];
+                        let direction = vec3![x - r, y, rear_z];
+                        if trace(
+                            &self.elements,
+                            &self.vertex_z,
+                            origin,
+                            direction,
+                            WAVELENGTH_D,
+                        )
+                        .is_none()
+                        {
+                            continue;
+                        }
+                        passed += 1;
+                        let b = bounds.get_or_insert([x, x, y, y]);
+                        *b = [b[0].min(x), b[1].max(x), b[2].min(y), b[3].max(y)];
+                    }
+                }
+            }
+            // Grow by a cell, which the grid may have missed.
+            pupils.push(bounds.map(|b| [b[0] - cell, b[1] + cell, b[2] - cell, b[3] + cell]));
+            areas
+                .push(passed as f64 / (4 * PUPIL_GRID * PUPIL_GRID) as f64 * 4.0 * extent * extent);
+        }
+        self.exit_pupil = pupils;
+        self.reference_area = areas[0];
+    }
+}
+
+impl Camera for RealisticCamera {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        self.get_weighted_ray(s, t, sampler).map(|(ray, _)| ray)
+    }
+
+    fn get_weighted_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<(Ray, Color)> {
+        let lens = sampler.get_2d();
+        let (wavelength, weight) = if self.dispersive {
+            let channel = ((sampler.get_1d() * 3.0) as usize).min(2);
+            let mut weight = color![];
+            weight[channel] = 3.0;
+            (CHANNEL_WAVELENGTHS[channel], weight)
+        } else {
+            (WAVELENGTH_D, color![1, 1, 1])
+        };
+
+        // The lens turns the image upside down.
+        let x = -(s - 0.5) * self.film_width;
+        let y = -(t - 0.5) * self.film_height;
+        let r = x.hypot(y);
+        let half_diagonal = self.film_width.hypot(self.film_height) / 2.0;
+        let zone = ((r / half_diagonal * PUPIL_ZONES as f64) as usize).min(PUPIL_ZONES - 1);
+        let [x0, x1, y0, y1] = self.exit_pupil[zone]?;
+
+        // A point in the bounds, turned from the x axis to the film point.
+        let (px, py) = (x0 + lens.0 * (x1 - x0), y0 + lens.1 * (y1 - y0));
+        let (sin, cos) = if r > 0.0 { (y / r, x / r) } else { (0.0, 1.0) };
+        let target = point3![
+            px * cos - py * sin,
+            px * sin + py * cos,
+            *self.vertex_z.last().unwrap()
+        ];
+        let direction = target - point3![x, y, 0];
+        let (o, d) = trace(
+            &self.elements,
+            &self.vertex_z,
+            point3![x, y, 0],
+            direction,
+            wavelength,
+        )?;
+
+        // Irradiance falls off with cos⁴ and grows with the area sampled;
+        // 1 in the film center.
+        let cos_theta = direction.z / direction.length();
+        let area = (x1 - x0) * (y1 - y0);
+        let scale = cos_theta.powi(4) * area / self.reference_area;
+
+        let to_world = |p: Vector3| p.x * self.u + p.y * self.v - p.z * self.w;
+        Some((
+            Ray::new(&(self.origin + 0.001 * to_world(o.into())), &to_world(d)),
+            scale * weight,
+        ))
+    }
+}
+
+/// Distance of every surface from the film.
+fn vertex_z(elements: &[LensElement]) -> Vec<f64> {
+    let mut z = vec![0.0; elements.len()];
+    let mut distance = 0.0;
+    for (i, element) in elements.iter().enumerate().rev() {
+        distance += element.thickness;
+        z[i] = distance;
+    }
+    z
+}
+
+/// Trace a ray from the film side out of the front of the lens, in lens
+/// space: millimeters, the film at z = 0 and the scene toward +z. `None` if
+/// an aperture blocks it or it is totally reflected.
+fn trace(
+    elements: &[LensElement],
+    vertex_z: &[f64],
+    mut origin: Point3,
+    direction: Vector3,
+    wavelength: f64,
+) -> Option<(Point3, Vector3)> {
+    let mut direction = direction.unit_vector();
+    for i in (0..elements.len()).rev() {
+        let element = &elements[i];
+        let z = vertex_z[i];
+        let (t, normal) = if element.is_stop() {
+            if direction.z <= 0.0 {
+                return None;
+            }
+            ((z - origin.z) / direction.z, None)
+        } else {
+            let center = point3![0, 0, z - element.radius];
+            let oc = origin - center;
+            let b = oc.dot(&direction);
+            let c = oc.length_squared() - element.radius * element.radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let root = discriminant.sqrt();
+            let closer = (direction.z > 0.0) == (element.radius < 0.0);
+            let t = if closer { -b - root } else { -b + root };
+            let normal = (origin + t * direction - center).unit_vector();
+            (t, Some(normal))
+        };
+        if t < 0.0 {
+            return None;
+        }
+        origin += t * direction;
+        let radius = element.aperture / 2.0;
+        if origin.x * origin.x + origin.y * origin.y > radius * radius {
+            return None;
+        }
+        if let Some(normal) = normal {
+            let normal = if normal.dot(&direction) > 0.0 {
+                -normal
+            } else {
+                normal
+            };
+            let eta_i = element.ior_at(wavelength);
+            let eta_t = if i > 0 {
+                elements[i - 1].ior_at(wavelength)
+            } else {
+                1.0
+            };
+            let eta = eta_i / eta_t;
+            let cos_i = -direction.dot(&normal);
+            if eta * eta * (1.0 - cos_i * cos_i) > 1.0 {
+                return None;
+            }
+            direction = direction.refract(&normal.into(), eta).unit_vector();
+        }
+    }
+    Some((origin, direction))
+}
+
+/// Distance from the last surface to the film that focuses the lens at
+/// `distance` millimeters from the film.
+fn focus(elements: &[LensElement], distance: f64) -> Result<f64, String> {
+    // Vergence of a paraxial ray from the film center after the lens: the
+    // reciprocal of where it crosses the axis.
+    let vergence = |thickness: f64| {
+        let mut elements = elements.to_vec();
+        elements.last_mut().unwrap().thickness = thickness;
+        let angle: f64 = 1e-3;
+        let (o, d) = trace(
+            &elements,
+            &vertex_z(&elements),
+            point3![0, 0, 0],
+            vec3![angle.sin(), 0, angle.cos()],
+            WAVELENGTH_D,
+        )?;
+        Some(d.x / (o.z * d.x - o.x * d.z))
+    };
+    // Moving the film back focuses closer, until the film is about twice
+    // the focal length behind the lens; take the first match.
+    let target = 1.0 / distance;
+    let length: f64 = elements.iter().map(|e| e.thickness).sum();
+    let step = 0.5;
+    let mut lo = None;
+    let mut hi = None;
+    for k in 1..=((10.0 * (length + 100.0)) / step) as usize {
+        let thickness = k as f64 * step;
+        match vergence(thickness) {
+            Some(v) if v < target => lo = Some(thickness),
+            Some(_) if lo.is_some() => {
+                hi = Some(thickness);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (Some(mut lo), Some(mut hi)) = (lo, hi) else {
+        return Err(format!("cannot focus the lens at {} mm", distance));
+    };
+    for _ in 0..60 {
+        let mid = (lo + hi) / 2.0;
+        match vergence(mid) {
+            Some(v) if v < target => lo = mid,
+            _ => hi = mid,
+        }
+    }
+    Ok((lo + hi) / 2.0)
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point3;
+    use crate::sampler::RandomSampler;
+
+    fn camera(focus_dist: f64) -> RealisticCamera {
+        RealisticCamera::new(
+            point3![0, 0, 0],
+            point3![0, 0, -1],
+            vec3![0, 1, 0],
+            parse_lens(DOUBLE_GAUSS_50MM).unwrap(),
+            36.0,
+            1.5,
+            focus_dist,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_lens() {
+        let elements = parse_lens(DOUBLE_GAUSS_50MM).unwrap();
+        assert_eq!(elements.len(), 11);
+        assert!(elements[5].is_stop());
+        assert_eq!(elements[1].ior, 1.0);
+        assert_eq!(elements[0].abbe, 47.1);
+        assert!(elements[0].ior_at(460.0) > elements[0].ior_at(620.0));
+
+        assert!(parse_lens("# nothing\n").is_err());
+        assert!(parse_lens("1 2 3").is_err());
+        assert!(parse_lens("1 2 1.5 x").is_err());
+        assert!(parse_lens("10 2 1.5 -4").is_err());
+    }
+
+    #[test]
+    fn test_focal_length_and_focus() {
+        let camera = camera(2.0);
+        let f = camera.focal_length();
+        assert!((f - 50.0).abs() < 5.0, "{}", f);
+
+        // Rays from the film center through the whole pupil meet again 2 m
+        // in front of the film.
+        let mut sampler = RandomSampler::new(1);
+        let mut rays = 0;
+        for _ in 0..200 {
+            let Some(ray) = camera.get_ray(0.5, 0.5, &mut sampler) else {
+                continue;
+            };
+            rays += 1;
+            let p = ray.at((-2.0 - ray.origin.z) / ray.direction.z);
+            assert!(p.x.hypot(p.y) < 2e-3, "{:?}", p);
+        }
+        assert!(rays > 120, "{}", rays);
+    }
+
+    #[test]
+    fn test_vignetting() {
+        let camera = camera(5.0);
+        let mut sampler = RandomSampler::new(2);
+        let mut mean = |s, t| {
+            let n = 2000;
+            let sum: f64 = (0..n)
+                .filter_map(|_| camera.get_weighted_ray(s, t, &mut sampler))
+                .map(|(_, weight)| weight.x + weight.y + weight.z)
+                .sum();
+            sum / (3 * n) as f64
+        };
+        let center = mean(0.5, 0.5);
+        assert!((center - 1.0).abs() < 0.1, "{}", center);
+        let corner = mean(0.0, 0.0);
+        assert!(corner < 0.7 * center, "{} {}", corner, center);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{from_name, Camera, CameraSetup};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::Color;
+
+/// How the eyes of a [`StereoRig`] converge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Convergence {
+    /// Both eyes turn toward the convergence point. Simple, but gives
+    /// vertical parallax toward the sides of the image.
+    ToeIn,
+    /// Both eyes look straight ahead and shift their lenses so that their
+    /// views coincide at the convergence distance.
+    OffAxis,
+}
+
+/// Two cameras `eye_distance` apart to the left and right of `lookfrom`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StereoRig {
+    pub eye_distance: f64,
+    /// Distance in front of `lookfrom` where the eyes converge, i.e. of
+    /// the objects that appear at the depth of the screen.
+    pub convergence_distance: f64,
+    pub convergence: Convergence,
+}
+
+impl StereoRig {
+    /// The setups of the left and the right eye of `camera`, the camera of
+    /// `setup`. An off-axis rig shifts the lenses by the view of `camera`,
+    /// so it fails for cameras that cannot shift them.
+    pub fn eyes(
+        &self,
+        setup: &CameraSetup,
+        camera: &dyn Camera,
+    ) -> Result<[CameraSetup; 2], String> {
+        let w = (setup.lookfrom - setup.lookat).unit_vector();
+        let u = setup.vup.cross(&w).unit_vector();
+        let target = setup.lookfrom - self.convergence_distance * w;
+        let shift = match self.convergence {
+            Convergence::ToeIn => 0.0,
+            Convergence::OffAxis => {
+                let view_width = camera.view_width(self.convergence_distance).ok_or(
+                    "off-axis convergence needs a camera with lens shift; converge toe-in instead",
+                )?;
+                self.eye_distance / view_width
+            }
+        };
+        Ok([-0.5, 0.5].map(|side| {
+            let offset = side * self.eye_distance * u;
+            let mut eye = setup.clone();
+            eye.lookfrom = setup.lookfrom + offset;
+            match self.convergence {
+                Convergence::ToeIn => {
+                    eye.lookat = target;
+                    eye.focus_dist = (target - eye.lookfrom).length();
+                }
+                Convergence::OffAxis => {
+                    eye.lookat = setup.lookat + offset;
+                    eye.shift.0 -= side * shift;
+                }
+            }
+            eye
+        }))
+    }
+}
+
+/// Where [`StereoCamera`] puts the eyes on the film.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// Left eye in the left half.
+    SideBySide,
+    /// Left eye in the upper half.
+    OverUnder,
+}
+
+/// Renders the views of two cameras into the halves of one film.
+pub struct StereoCamera {
+    pub left: Box<dyn Camera>,
+    pub right: Box<dyn Camera>,
+    pub layout: StereoLayout,
+}
+
+impl StereoCamera {
+    /// The cameras named `spec` (see [`from_name`]) at the eyes of `rig`.
+    pub fn from_name(
+        spec: &str,
+        setup: &CameraSetup,
+        rig: &StereoRig,
+        layout: StereoLayout,
+    ) -> Result<Self, String> {
+        let [left, right] = rig.eyes(setup, from_name(spec, setup)?.as_ref())?;
+        Ok(StereoCamera {
+            left: from_name(spec, &left)?,
+            right: from_name(spec, &right)?,
+            layout,
+        })
+    }
+
+    /// The eye and the position on its film.
+    fn eye(&self, s: f64, t: f64) -> (&dyn Camera, f64, f64) {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => (self.left.as_ref(), 2.0 * s, t),
+            StereoLayout::SideBySide => (self.right.as_ref(), 2.0 * s - 1.0, t),
+            StereoLayout::OverUnder if t >= 0.5 => (self.left.as_ref(), s, 2.0 * t - 1.0),
+            StereoLayout::OverUnder => (self.right.as_ref(), s, 2.0 * t),
+        }
+    }
+}
+
+impl Camera for StereoCamera {
+    fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let (camera, s, t) = self.eye(s, t);
+        camera.get_ray(s, t, sampler)
+    }
+
+    fn get_weighted_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Option<(Ray, Color)> {
+        let (camera, s, t) = self.eye(s, t);
+        camera.get_weighted_ray(s, t, sampler)
+    }
+
+    fn exposure(&self) -> f64 {
+        self.left.exposure()
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Bokeh;
+    use crate::sampler::RandomSampler;
+    use crate::{point3, vec3};
+
+    fn setup() -> CameraSetup {
+        CameraSetup {
+            lookfrom: point3![0, 0, 0],
+            lookat: point3![0, 0, -1],
+            vup: vec3![0, 1, 0],
+            vfov: 90.0,
+            aspect_ratio: 1.0,
+            aperture: 0.0,
+            focus_dist: 4.0,
+            bokeh: Bokeh::default(),
+            shift: (0.0, 0.0),
+            tilt: (0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn test_eyes_converge() {
+        let mut sampler = RandomSampler::new(0);
+        for convergence in [Convergence::ToeIn, Convergence::OffAxis] {
+            let rig = StereoRig {
+                eye_distance: 0.2,
+                convergence_distance: 4.0,
+                convergence,
+            };
+            let camera =
+                StereoCamera::from_name("perspective", &setup(), &rig, StereoLayout::SideBySide)
+                    .unwrap();
+            // The centers of both views see the convergence point.
+            let left = camera.get_ray(0.25, 0.5, &mut sampler).unwrap();
+            let right = camera.get_ray(0.75, 0.5, &mut sampler).unwrap();
+            assert!((left.origin - point3![-0.1, 0, 0]).length() < 1e-12);
+            assert!((right.origin - point3![0.1, 0, 0]).length() < 1e-12);
+            for ray in [left, right] {
+                let p = ray.at(-4.0 / ray.direction.z);
+                assert!((p - point3![0, 0, -4]).length() < 1e-9, "{:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn test_off_axis_uses_field_of_view_of_camera() {
+        // A 50 mm lens sees much less than the 90° of the setup; both its
+        // eyes still look at the convergence point through the lens center.
+        let rig = StereoRig {
+            eye_distance: 0.2,
+            convergence_distance: 4.0,
+            convergence: Convergence::OffAxis,
+        };
+        let camera =
+            StereoCamera::from_name("physical", &setup(), &rig, StereoLayout::SideBySide).unwrap();
+        for (eye, x) in [(&camera.left, -0.1), (&camera.right, 0.1)] {
+            let mut sampler = RandomSampler::new(0);
+            let ray = eye.get_ray(0.5, 0.5, &mut sampler).unwrap();
+            let p = ray.at(-4.0 / ray.direction.z);
+            assert!((p - point3![0, 0, -4]).length() < 1e-9, "{:?}", p);
+            assert!((ray.origin.x - x).abs() <= 0.0125, "{:?}", ray.origin);
+        }
+    }
+
+    #[test]
+    fn test_off_axis_needs_lens_shift() {
+        for convergence in [Convergence::ToeIn, Convergence::OffAxis] {
+            let rig = StereoRig {
+                eye_distance: 0.2,
+                convergence_distance: 4.0,
+                convergence,
+            };
+            for spec in ["orthographic", "equirectangular", "fisheye", "realistic"] {
+                let camera =
+                    StereoCamera::from_name(spec, &setup(), &rig, StereoLayout::SideBySide);
+                assert_eq!(
+                    camera.is_ok(),
+                    convergence == Convergence::ToeIn,
+                    "{}",
+                    spec
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_off_axis_has_no_vertical_parallax() {
+        let rig = StereoRig {
+            eye_distance: 0.2,
+            convergence_distance: 4.0,
+            convergence: Convergence::OffAxis,
+        };
+        let camera =
+            StereoCamera::from_name("perspective", &setup(), &rig, StereoLayout::OverUnder)
+                .unwrap();
+        let mut sampler = RandomSampler::new(0);
+        // The same corner of both views; the left eye is on top.
+        let left = camera.get_ray(0.0, 1.0, &mut sampler).unwrap();
+        let right = camera.get_ray(0.0, 0.5 - 1e-12, &mut sampler).unwrap();
+        assert!(left.origin.x < right.origin.x);
+        let (l, r) = (left.direction, right.direction);
+        assert!((l.y / l.z - r.y / r.z).abs() < 1e-9);
+    }
+}
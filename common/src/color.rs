@@ -26,3 +26,8 @@ pub fn write_color(
     os.write_all(s.as_bytes())?;
     Ok(())
 }
+
+/// Rec. 709 luminance of a linear color.
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
@@ -0,0 +1,347 @@
+//! 特征引导的 À-Trous 小波降噪
+
+use crate::color::luminance;
+use crate::image::Image;
+use crate::{color, Color, Film, Vector3};
+
+/// Most passes of [`denoise`]; the taps of the last one are 2^15 pixels
+/// apart, far beyond any image.
+pub const MAX_ITERATIONS: usize = 16;
+
+/// Parameters of [`denoise`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseSettings {
+    /// Passes of the filter; pass `k` reaches `2^(k+1)` pixels away. At
+    /// most [`MAX_ITERATIONS`].
+    pub iterations: usize,
+    /// Luminance differences are measured in standard deviations of the
+    /// noise times this.
+    pub sigma_luminance: f64,
+    /// Exponent of the cosine between normals.
+    pub sigma_normal: f64,
+    /// Albedo differences are measured in units of this.
+    pub sigma_albedo: f64,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        DenoiseSettings {
+            iterations: 4,
+            sigma_luminance: 4.0,
+            sigma_normal: 32.0,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+/// Features that keep the filter from blurring across edges, each with the
+/// size of the noisy image.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Guides<'a> {
+    /// Surface color at the first hit.
+    pub albedo: Option<&'a Image>,
+    /// Surface normal at the first hit; zero where nothing was hit.
+    pub normal: Option<&'a Image>,
+    /// Variance of the luminance of each pixel. Estimated from the
+    /// neighborhood of the pixel if not given.
+    pub variance: Option<&'a [f64]>,
+}
+
+/// B3 spline, the kernel of the À-Trous wavelet transform.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Remove the noise of a rendered image with the edge-avoiding À-Trous
+/// wavelet filter (Dammertz et al. 2010), steering the weights by the
+/// variance like SVGF (Schied et al. 2017).
+///
+/// Every pass blurs with a 5 x 5 kernel whose taps are spread further apart
+/// each time, weighted down where the luminance differs by much more than
+/// the noise or where the normal or albedo differ.
+pub fn denoise(noisy: &Image, guides: &Guides, settings: &DenoiseSettings) -> Image {
+    let (width, height) = (noisy.width, noisy.height);
+    let n = width * height;
+    for guide in [guides.albedo, guides.normal].into_iter().flatten() {
+        assert_eq!((guide.width, guide.height), (width, height));
+    }
+    if let Some(variance) = guides.variance {
+        assert_eq!(variance.len(), n);
+    }
+    assert!(
+        settings.iterations <= MAX_ITERATIONS,
+        "at most {} iterations",
+        MAX_ITERATIONS
+    );
+
+    // Dividing the albedo out before filtering, as SVGF does, goes wrong on
+    // pixels only partly covered by a surface, which have a meaningless
+    // albedo. The albedo only stops the filter at edges here.
+    let mut color = noisy.pixels.clone();
+    let mut variance = match guides.variance {
+        Some(variance) => variance.to_vec(),
+        None => estimate_variance(&color, width, height),
+    };
+
+    for iteration in 0..settings.iterations {
+        let step = 1 << iteration;
+        let blurred = blur_variance(&variance, width, height);
+        let mut next_color = vec![color![]; n];
+        let mut next_variance = vec![0.0; n];
+        for y in 0..height {
+            for x in 0..width {
+                let p = y * width + x;
+                let luminance_p = luminance(&color[p]);
+                let scale = settings.sigma_luminance * blurred[p].max(0.0).sqrt() + 1e-6;
+                let mut sum = color![];
+                let mut sum_variance = 0.0;
+                let mut sum_weight = 0.0;
+                for (ky, &hy) in KERNEL.iter().enumerate() {
+                    let qy = y as isize + (ky as isize - 2) * step;
+                    if qy < 0 || qy >= height as isize {
+                        continue;
+                    }
+                    for (kx, &hx) in KERNEL.iter().enumerate() {
+                        let qx = x as isize + (kx as isize - 2) * step;
+                        if qx < 0 || qx >= width as isize {
+                            continue;
+                        }
+                        let q = qy as usize * width + qx as usize;
+                        let mut weight =
+                            hx * hy * (-(luminance_p - luminance(&color[q])).abs() / scale).exp();
+                        if let Some(normal) = guides.normal {
+                            weight *= normal_weight(&normal.pixels[p], &normal.pixels[q], settings);
+                        }
+                        if let Some(albedo) = guides.albedo {
+                            let difference = Vector3::from(albedo.pixels[p] - albedo.pixels[q]);
+                            weight *= (-difference.length_squared()
+                                / (settings.sigma_albedo * settings.sigma_albedo))
+                                .exp();
+                        }
+                        sum += color[q] * weight;
+                        sum_variance += variance[q] * weight * weight;
+                        sum_weight += weight;
+                    }
+                }
+                // The center tap always has weight, so `sum_weight` > 0.
+                next_color[p] = sum / sum_weight;
+                next_variance[p] = sum_variance / (sum_weight * sum_weight);
+            }
+        }
+        color = next_color;
+        variance = next_variance;
+    }
+
+    Image {
+        width,
+        height,
+        pixels: color,
+    }
+}
+
+/// Denoise the beauty image of `film`, guided by its `albedo` and `normal`
+/// AOVs where recorded and by the variance of its pixels.
+pub fn denoise_film(film: &Film, settings: &DenoiseSettings) -> Image {
+    let image = |name: &str| {
+        film.channel_data(name)
+            .map(|(components, data)| Image::from_data(film.width, film.height, components, &data))
+    };
+    let albedo = image("albedo");
+    let normal = image("normal");
+    let mut variance = Vec::with_capacity(film.width * film.height);
+    for j in (0..film.height).rev() {
+        for i in 0..film.width {
+            let error = film.pixel_stats(i, j).standard_error();
+            variance.push(error * error);
+        }
+    }
+    // Without two samples per pixel the variance is unknown.
+    let known = (0..film.height).all(|j| (0..film.width).all(|i| film.samples(i, j) >= 2));
+    let guides = Guides {
+        albedo: albedo.as_ref(),
+        normal: normal.as_ref(),
+        variance: known.then_some(variance.as_slice()),
+    };
+    denoise(&image("beauty").unwrap(), &guides, settings)
+}
+
+fn normal_weight(n_p: &Color, n_q: &Color, settings: &DenoiseSettings) -> f64 {
+    let (n_p, n_q) = (Vector3::from(*n_p), Vector3::from(*n_q));
+    let (length_p, length_q) = (n_p.length(), n_q.length());
+    match (length_p > 0.5, length_q > 0.5) {
+        (true, true) => (n_p.dot(&n_q) / (length_p * length_q))
+            .max(0.0)
+            .powf(settings.sigma_normal),
+        // Both background.
+        (false, false) => 1.0,
+        _ => 0.0,
+    }
+}
+
+/// Variance of the luminance in the 3 x 3 neighborhood of every pixel.
+fn estimate_variance(color: &[Color], width: usize, height: usize) -> Vec<f64> {
+    let mut variance = Vec::with_capacity(color.len());
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+            for qy in y.saturating_sub(1)..(y + 2).min(height) {
+                for qx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let l = luminance(&color[qy * width + qx]);
+                    sum += l;
+                    sum_squares += l * l;
+                    count += 1.0;
+                }
+            }
+            let mean = sum / count;
+            variance.push((sum_squares / count - mean * mean).max(0.0));
+        }
+    }
+    variance
+}
+
+/// 3 x 3 Gaussian blur of the variance, which is itself noisy.
+fn blur_variance(variance: &[f64], width: usize, height: usize) -> Vec<f64> {
+    const GAUSSIAN: [f64; 3] = [0.25, 0.5, 0.25];
+    let mut blurred = Vec::with_capacity(variance.len());
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut sum_weight) = (0.0, 0.0);
+            for (ky, &hy) in GAUSSIAN.iter().enumerate() {
+                for (kx, &hx) in GAUSSIAN.iter().enumerate() {
+                    let (qx, qy) = (x + kx, y + ky);
+                    if qx < 1 || qy < 1 || qx > width || qy > height {
+                        continue;
+                    }
+                    sum += hx * hy * variance[(qy - 1) * width + qx - 1];
+                    sum_weight += hx * hy;
+                }
+            }
+            blurred.push(sum / sum_weight);
+        }
+    }
+    blurred
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    /// The left half faces one way and is red, the right half faces another
+    /// way and is blue.
+    fn scene(width: usize, height: usize) -> (Image, Image, Image) {
+        let mut clean = Image::new(width, height);
+        let mut albedo = Image::new(width, height);
+        let mut normal = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let left = x < width / 2;
+                let p = y * width + x;
+                albedo.pixels[p] = if left {
+                    color![0.8, 0.2, 0.2]
+                } else {
+                    color![0.2, 0.2, 0.8]
+                };
+                normal.pixels[p] = if left {
+                    color![0, 0, 1]
+                } else {
+                    color![1, 0, 0]
+                };
+                clean.pixels[p] = albedo.pixels[p] * if left { 0.9 } else { 0.3 };
+            }
+        }
+        (clean, albedo, normal)
+    }
+
+    fn add_noise(image: &Image, amount: f64, seed: u64) -> Image {
+        let mut rng = Rng::new(seed);
+        let mut noisy = image.clone();
+        for pixel in &mut noisy.pixels {
+            // Same noise in every channel, like noisy lighting.
+            *pixel *= 1.0 + amount * (2.0 * rng.next_f64() - 1.0);
+        }
+        noisy
+    }
+
+    fn mean_squared_error(a: &Image, b: &Image) -> f64 {
+        let sum: f64 = (a.pixels.iter().zip(&b.pixels))
+            .map(|(p, q)| Vector3::from(*p - *q).length_squared())
+            .sum();
+        sum / a.pixels.len() as f64
+    }
+
+    #[test]
+    fn test_guided_denoise_removes_noise_and_keeps_edge() {
+        let (clean, albedo, normal) = scene(32, 16);
+        let noisy = add_noise(&clean, 0.5, 1);
+        let guides = Guides {
+            albedo: Some(&albedo),
+            normal: Some(&normal),
+            variance: None,
+        };
+        let denoised = denoise(&noisy, &guides, &DenoiseSettings::default());
+        let before = mean_squared_error(&noisy, &clean);
+        let after = mean_squared_error(&denoised, &clean);
+        assert!(after < 0.1 * before, "{} -> {}", before, after);
+
+        // Nothing leaks across the edge.
+        for y in 0..16 {
+            for x in [15, 16] {
+                let error = Vector3::from(denoised.get(x, y) - clean.get(x, y)).length();
+                let clean = Vector3::from(clean.get(x, y)).length();
+                assert!(error < 0.1 * clean, "{} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_clean_image_is_unchanged() {
+        let (clean, albedo, normal) = scene(16, 8);
+        let variance = vec![0.0; 16 * 8];
+        let guides = Guides {
+            albedo: Some(&albedo),
+            normal: Some(&normal),
+            variance: Some(&variance),
+        };
+        let denoised = denoise(&clean, &guides, &DenoiseSettings::default());
+        assert!(mean_squared_error(&denoised, &clean) < 1e-20);
+    }
+
+    #[test]
+    fn test_denoise_without_guides() {
+        let mut clean = Image::new(24, 24);
+        for pixel in &mut clean.pixels {
+            *pixel = color![0.5, 0.5, 0.5];
+        }
+        let noisy = add_noise(&clean, 0.4, 2);
+        let denoised = denoise(&noisy, &Guides::default(), &DenoiseSettings::default());
+        assert!(mean_squared_error(&denoised, &clean) < 0.2 * mean_squared_error(&noisy, &clean));
+    }
+
+    #[test]
+    fn test_iterations_limit() {
+        let (clean, albedo, normal) = scene(4, 2);
+        let guides = Guides {
+            albedo: Some(&albedo),
+            normal: Some(&normal),
+            variance: None,
+        };
+        let settings = DenoiseSettings {
+            iterations: MAX_ITERATIONS,
+            ..DenoiseSettings::default()
+        };
+        // Taps beyond the image are left out.
+        let denoised = denoise(&clean, &guides, &settings);
+        assert!(mean_squared_error(&denoised, &clean) < 1e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 16 iterations")]
+    fn test_too_many_iterations() {
+        let (clean, _, _) = scene(4, 2);
+        let settings = DenoiseSettings {
+            iterations: 64,
+            ..DenoiseSettings::default()
+        };
+        denoise(&clean, &Guides::default(), &settings);
+    }
+}
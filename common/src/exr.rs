@@ -0,0 +1,427 @@
+//! OpenEXR 输入输出 (未压缩, 单部件, 扫描线)
+
+use std::io::{self, Read, Write};
+
+/// A named image channel, e.g. `albedo.R`, stored top scanline first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExrChannel {
+    pub name: String,
+    pub data: Vec<f32>,
+}
+
+fn write_attribute(
+    os: &mut dyn Write,
+    name: &str,
+    kind: &str,
+    value: &[u8],
+) -> std::io::Result<()> {
+    os.write_all(name.as_bytes())?;
+    os.write_all(&[0])?;
+    os.write_all(kind.as_bytes())?;
+    os.write_all(&[0])?;
+    os.write_all(&(value.len() as i32).to_le_bytes())?;
+    os.write_all(value)
+}
+
+fn box2i(width: usize, height: usize) -> Vec<u8> {
+    [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+/// Write an uncompressed scanline EXR with 32-bit float channels.
+///
+/// Channels are sorted by name as the format requires; layers are expressed
+/// through the usual `layer.channel` naming.
+pub fn write_exr(
+    os: &mut dyn Write,
+    width: usize,
+    height: usize,
+    channels: &[ExrChannel],
+) -> std::io::Result<()> {
+    let mut channels: Vec<&ExrChannel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    for channel in &channels {
+        assert_eq!(
+            channel.data.len(),
+            width * height,
+            "channel {}",
+            channel.name
+        );
+    }
+
+    let long_names = channels.iter().any(|c| c.name.len() > 31);
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&[2, if long_names { 0x04 } else { 0 }, 0, 0]);
+
+    let mut chlist = Vec::new();
+    for channel in &channels {
+        chlist.extend_from_slice(channel.name.as_bytes());
+        chlist.push(0);
+        // pixel type FLOAT, pLinear + reserved, x/y sampling
+        chlist.extend_from_slice(&2i32.to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist)?;
+    write_attribute(&mut header, "compression", "compression", &[0])?;
+    write_attribute(&mut header, "dataWindow", "box2i", &box2i(width, height))?;
+    write_attribute(&mut header, "displayWindow", "box2i", &box2i(width, height))?;
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0])?;
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    )?;
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8])?;
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    )?;
+    header.push(0);
+
+    // One scanline per block: y, data size, then each channel's row.
+    let line_size = channels.len() * width * 4;
+    let block_size = 8 + line_size;
+    let table_start = header.len() + height * 8;
+    os.write_all(&header)?;
+    for y in 0..height {
+        os.write_all(&((table_start + y * block_size) as u64).to_le_bytes())?;
+    }
+    let mut block = Vec::with_capacity(block_size);
+    for y in 0..height {
+        block.clear();
+        block.extend_from_slice(&(y as i32).to_le_bytes());
+        block.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for channel in &channels {
+            for v in &channel.data[y * width..(y + 1) * width] {
+                block.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        os.write_all(&block)?;
+    }
+    Ok(())
+}
+
+/// An EXR as read by [`read_exr`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExrImage {
+    pub width: usize,
+    pub height: usize,
+    /// Sorted by name.
+    pub channels: Vec<ExrChannel>,
+}
+
+impl ExrImage {
+    pub fn channel(&self, name: &str) -> Option<&[f32]> {
+        self.channels
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.data.as_slice())
+    }
+}
+
+/// Read an uncompressed single-part scanline EXR, like those of
+/// [`write_exr`]. Channels may be half, float or uint; the values are
+/// converted to floats.
+pub fn read_exr(is: &mut dyn Read) -> io::Result<ExrImage> {
+    let mut bytes = Vec::new();
+    is.read_to_end(&mut bytes)?;
+    let mut reader = ByteReader {
+        bytes: &bytes,
+        position: 0,
+    };
+    if reader.take(4)? != [0x76, 0x2f, 0x31, 0x01] {
+        return Err(invalid_data("not an OpenEXR file"));
+    }
+    let version = reader.take(4)?;
+    // Tiled, deep or multi-part files.
+    if version[1] & !0x04 != 0 {
+        return Err(invalid_data("only single-part scanline EXRs are supported"));
+    }
+
+    let mut channels = Vec::new();
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let kind = reader.string()?;
+        let size = reader.i32()? as usize;
+        let mut value = ByteReader {
+            bytes: reader.take(size)?,
+            position: 0,
+        };
+        match (name.as_str(), kind.as_str()) {
+            ("channels", "chlist") => loop {
+                let name = value.string()?;
+                if name.is_empty() {
+                    break;
+                }
+                let pixel_type = value.i32()?;
+                value.take(4)?;
+                if value.i32()? != 1 || value.i32()? != 1 {
+                    return Err(invalid_data("subsampled channels are not supported"));
+                }
+                channels.push((name, pixel_type));
+            },
+            ("compression", "compression") if value.bytes != [0] => {
+                return Err(invalid_data("only uncompressed EXRs are supported"));
+            }
+            ("dataWindow", "box2i") => {
+                let b = [value.i32()?, value.i32()?, value.i32()?, value.i32()?];
+                data_window = Some(b);
+            }
+            _ => {}
+        }
+    }
+    let [x_min, y_min, x_max, y_max] =
+        data_window.ok_or_else(|| invalid_data("missing dataWindow"))?;
+    let extent = |min: i32, max: i32| (max as i64 - min as i64 + 1).max(0) as usize;
+    let (width, height) = (extent(x_min, x_max), extent(y_min, y_max));
+    // Every value takes at least two bytes, so a larger image cannot be in
+    // the file.
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels.len()))
+        .filter(|&values| values <= bytes.len() / 2)
+        .ok_or_else(|| invalid_data("data window larger than the file"))?;
+
+    let mut image = ExrImage {
+        width,
+        height,
+        channels: channels
+            .iter()
+            .map(|(name, _)| ExrChannel {
+                name: name.clone(),
+                data: vec![0.0; width * height],
+            })
+            .collect(),
+    };
+    let table = reader.position;
+    for chunk in 0..height {
+        reader.position = table + 8 * chunk;
+        reader.position = reader.u64()? as usize;
+        let y = reader
+            .i32()?
+            .checked_sub(y_min)
+            .and_then(|y| usize::try_from(y).ok())
+            .filter(|&y| y < height)
+            .ok_or_else(|| invalid_data("scanline outside the data window"))?;
+        reader.i32()?;
+        for (channel, &(_, pixel_type)) in image.channels.iter_mut().zip(&channels) {
+            let row = &mut channel.data[y * width..(y + 1) * width];
+            for value in row {
+                *value = match pixel_type {
+                    0 => reader.u32()? as f32,
+                    1 => half_to_f32(u16::from_le_bytes([reader.byte()?, reader.byte()?])),
+                    2 => f32::from_bits(reader.u32()?),
+                    _ => return Err(invalid_data("unknown pixel type")),
+                };
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Little-endian fields of an in-memory file.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .position
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or_else(|| invalid_data("truncated EXR"))?;
+        self.position += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    /// A null-terminated string.
+    fn string(&mut self) -> io::Result<String> {
+        let rest = &self.bytes[self.position.min(self.bytes.len())..];
+        let end = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid_data("truncated EXR"))?;
+        let s = String::from_utf8_lossy(&rest[..end]).into_owned();
+        self.position += end + 1;
+        Ok(s)
+    }
+}
+
+/// IEEE 754 binary16 to binary32.
+fn half_to_f32(h: u16) -> f32 {
+    let sign = ((h >> 15) as u32) << 31;
+    let exponent = ((h >> 10) & 0x1f) as u32;
+    let mantissa = (h & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // Subnormal: normalize the mantissa.
+        0 => {
+            let shift = mantissa.leading_zeros() - 21;
+            sign | (113 - shift) << 23 | ((mantissa << shift) & 0x3ff) << 13
+        }
+        0x1f => sign | 0xff << 23 | mantissa << 13,
+        _ => sign | (exponent + 112) << 23 | mantissa << 13,
+    };
+    f32::from_bits(bits)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let channels = vec![
+            ExrChannel {
+                name: "R".to_string(),
+                data: (0..6).map(|i| i as f32).collect(),
+            },
+            ExrChannel {
+                name: "a_rather_long_layer_name_over_31.R".to_string(),
+                data: (0..6).map(|i| -0.25 * i as f32).collect(),
+            },
+            ExrChannel {
+                name: "depth".to_string(),
+                data: vec![1e-3, 2.0, f32::MAX, 0.0, 5.5, 1e9],
+            },
+        ];
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, 3, 2, &channels).unwrap();
+        let image = read_exr(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            image,
+            ExrImage {
+                width: 3,
+                height: 2,
+                channels
+            }
+        );
+        assert_eq!(image.channel("depth").unwrap()[4], 5.5);
+        assert!(read_exr(&mut &bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn test_bytes() {
+        let channels = [ExrChannel {
+            name: "Y".to_string(),
+            data: vec![1.0, 2.0],
+        }];
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, 2, 1, &channels).unwrap();
+
+        let attribute = |name: &str, kind: &str, value: &[u8]| {
+            let mut bytes = format!("{}\0{}\0", name, kind).into_bytes();
+            bytes.extend_from_slice(&(value.len() as i32).to_le_bytes());
+            bytes.extend_from_slice(value);
+            bytes
+        };
+        let ints =
+            |values: &[i32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_le_bytes()).collect() };
+        let mut expected = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+        let mut chlist = b"Y\0".to_vec();
+        chlist.extend(ints(&[2, 0, 1, 1]));
+        chlist.push(0);
+        expected.extend(attribute("channels", "chlist", &chlist));
+        expected.extend(attribute("compression", "compression", &[0]));
+        expected.extend(attribute("dataWindow", "box2i", &ints(&[0, 0, 1, 0])));
+        expected.extend(attribute("displayWindow", "box2i", &ints(&[0, 0, 1, 0])));
+        expected.extend(attribute("lineOrder", "lineOrder", &[0]));
+        expected.extend(attribute("pixelAspectRatio", "float", &1f32.to_le_bytes()));
+        expected.extend(attribute("screenWindowCenter", "v2f", &[0; 8]));
+        expected.extend(attribute("screenWindowWidth", "float", &1f32.to_le_bytes()));
+        expected.push(0);
+        // The offset table of one scanline, then the scanline: y, size and
+        // the values.
+        let block = expected.len() as u64 + 8;
+        expected.extend_from_slice(&block.to_le_bytes());
+        expected.extend(ints(&[0, 8]));
+        expected.extend_from_slice(&1f32.to_le_bytes());
+        expected.extend_from_slice(&2f32.to_le_bytes());
+        assert_eq!(bytes, expected);
+
+        // Names over 31 bytes set the long names flag.
+        let channels = [ExrChannel {
+            name: "a".repeat(32),
+            data: vec![0.0],
+        }];
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, 1, 1, &channels).unwrap();
+        assert_eq!(bytes[4..8], [2, 0x04, 0, 0]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let channels = [ExrChannel {
+            name: "Y".to_string(),
+            data: vec![1.0],
+        }];
+        let mut bytes = Vec::new();
+        write_exr(&mut bytes, 1, 1, &channels).unwrap();
+        let window = bytes.windows(10).position(|w| w == b"dataWindow").unwrap()
+            + "dataWindow\0box2i\0".len()
+            + 4;
+        let patched = |patches: &[(usize, i32)]| {
+            let mut bytes = bytes.clone();
+            for &(at, value) in patches {
+                bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            read_exr(&mut bytes.as_slice())
+        };
+        assert!(patched(&[]).is_ok());
+        // A scanline y so far below y_min that y - y_min overflows.
+        let y = bytes.len() - 12;
+        assert!(patched(&[(window + 4, 1), (window + 12, 1), (y, i32::MIN)]).is_err());
+        // A data window wider than the file, or than an i32.
+        assert!(patched(&[(window + 8, 1 << 20)]).is_err());
+        assert!(patched(&[(window, i32::MIN), (window + 8, i32::MAX)]).is_err());
+        // An attribute size pointing past the end.
+        assert!(patched(&[(window - 4, -1)]).is_err());
+    }
+
+    #[test]
+    fn test_half_to_f32() {
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x0200), 2f32.powi(-15));
+        assert!(half_to_f32(0x7c00).is_infinite());
+    }
+}
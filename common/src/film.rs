@@ -0,0 +1,553 @@
+//! 胶片: 浮点帧缓冲
+
+use crate::binary::{read_f64, read_u64, write_f64, write_u64};
+use crate::color::{luminance, write_color};
+use crate::exr::{write_exr, ExrChannel};
+use crate::filter::{BoxFilter, Filter};
+use crate::pfm::write_pfm;
+use crate::stats::RunningStats;
+use crate::Color;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// Values of the named AOVs (arbitrary output variables) for one sample.
+#[derive(Default)]
+pub struct AovSample {
+    values: Vec<(&'static str, Color)>,
+}
+
+impl AovSample {
+    pub fn new() -> Self {
+        AovSample { values: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn set(&mut self, name: &'static str, value: Color) {
+        match self.values.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.values.push((name, value)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+}
+
+/// A named buffer accumulated next to the beauty image.
+struct AovChannel {
+    name: String,
+    /// 1 for scalar AOVs such as depth, 3 for colors and vectors.
+    components: usize,
+    pixels: Vec<Color>,
+}
+
+/// Accumulates the radiance samples of every pixel.
+///
+/// Pixels are addressed as `(i, j)` with `j = 0` being the bottom scanline,
+/// matching the `u`/`v` orientation of [`crate::Camera::get_ray`]. Pixel
+/// `(i, j)` covers the film positions `[i, i + 1) x [j, j + 1)`. Samples are
+/// splatted to all pixels within the radius of the reconstruction filter,
+/// and every pixel is the filter-weighted average of its samples.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    filter: Arc<dyn Filter>,
+    /// Filter-weighted sums of the samples and of the weights.
+    pixels: Vec<Color>,
+    weights: Vec<f64>,
+    /// Number of samples taken inside each pixel.
+    samples: Vec<usize>,
+    /// Running mean and variance of the luminance of each pixel's samples.
+    stats: Vec<RunningStats>,
+    aovs: Vec<AovChannel>,
+}
+
+impl Film {
+    /// Film with a box filter, which counts every sample for exactly the
+    /// pixel it lies in.
+    pub fn new(width: usize, height: usize) -> Self {
+        Film::with_filter(width, height, Arc::new(BoxFilter::new(0.5)))
+    }
+
+    pub fn with_filter(width: usize, height: usize, filter: Arc<dyn Filter>) -> Self {
+        Film {
+            width,
+            height,
+            filter,
+            pixels: vec![Color::new0(); width * height],
+            weights: vec![0.0; width * height],
+            samples: vec![0; width * height],
+            stats: vec![RunningStats::new(); width * height],
+            aovs: Vec::new(),
+        }
+    }
+
+    fn offset(&self, i: usize, j: usize) -> usize {
+        j * self.width + i
+    }
+
+    pub fn filter(&self) -> &Arc<dyn Filter> {
+        &self.filter
+    }
+
+    /// Number of pixels beyond its own pixel that a sample is splatted to.
+    pub fn filter_margin(&self) -> usize {
+        (self.filter.radius() - 0.5).ceil().max(0.0) as usize
+    }
+
+    /// Empty film of the given size with the same filter, recording the same
+    /// AOVs, e.g. for a tile.
+    pub fn new_like(&self, width: usize, height: usize) -> Film {
+        let mut film = Film::with_filter(width, height, self.filter.clone());
+        for channel in &self.aovs {
+            film.enable_aov(&channel.name, channel.components);
+        }
+        film
+    }
+
+    /// Add the samples of `tile`, whose pixel `(0, 0)` is `(x0, y0)` here.
+    /// Tiles may overlap where their samples were splatted across the tile
+    /// edges.
+    pub fn merge_tile(&mut self, tile: &Film, x0: usize, y0: usize) {
+        for j in 0..tile.height {
+            for i in 0..tile.width {
+                let src = tile.offset(i, j);
+                let dst = self.offset(x0 + i, y0 + j);
+                self.pixels[dst] += tile.pixels[src];
+                self.weights[dst] += tile.weights[src];
+                self.samples[dst] += tile.samples[src];
+                self.stats[dst].merge(&tile.stats[src]);
+                for channel in &mut self.aovs {
+                    if let Some(other) = tile.aovs.iter().find(|c| c.name == channel.name) {
+                        channel.pixels[dst] += other.pixels[src];
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start recording the AOV `name`; samples not providing it count as zero.
+    pub fn enable_aov(&mut self, name: &str, components: usize) {
+        if self.aovs.iter().all(|c| c.name != name) {
+            self.aovs.push(AovChannel {
+                name: name.to_string(),
+                components,
+                pixels: vec![Color::new0(); self.width * self.height],
+            });
+        }
+    }
+
+    pub fn aov_names(&self) -> Vec<&str> {
+        self.aovs.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    /// Add a sample taken at film position `(x, y)`, which must lie on the
+    /// film.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: &Color) {
+        self.splat(x, y, color, None);
+    }
+
+    pub fn add_sample_with_aovs(&mut self, x: f64, y: f64, color: &Color, aovs: &AovSample) {
+        self.splat(x, y, color, Some(aovs));
+    }
+
+    fn splat(&mut self, x: f64, y: f64, color: &Color, aovs: Option<&AovSample>) {
+        let i = (x.floor() as usize).min(self.width - 1);
+        let j = (y.floor() as usize).min(self.height - 1);
+        let offset = self.offset(i, j);
+        self.samples[offset] += 1;
+        self.stats[offset].add(luminance(color));
+
+        // Pixels whose center is within the filter radius.
+        let radius = self.filter.radius();
+        let range = |t: f64, size: usize| {
+            let lo = (t - radius - 0.5).ceil().max(0.0) as usize;
+            let hi = ((t + radius - 0.5).floor() as isize).min(size as isize - 1);
+            lo..(hi + 1).max(0) as usize
+        };
+        for pj in range(y, self.height) {
+            for pi in range(x, self.width) {
+                let weight = self
+                    .filter
+                    .evaluate(pi as f64 + 0.5 - x, pj as f64 + 0.5 - y);
+                if weight == 0.0 {
+                    continue;
+                }
+                let offset = self.offset(pi, pj);
+                self.pixels[offset] += weight * *color;
+                self.weights[offset] += weight;
+                if let Some(aovs) = aovs {
+                    for channel in &mut self.aovs {
+                        if let Some(value) = aovs.get(&channel.name) {
+                            channel.pixels[offset] += weight * value;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Filter-weighted sum of the samples splatted to the pixel.
+    pub fn pixel_color(&self, i: usize, j: usize) -> Color {
+        self.pixels[self.offset(i, j)]
+    }
+
+    /// Reconstructed value of the pixel, black if no sample reached it.
+    pub fn pixel_value(&self, i: usize, j: usize) -> Color {
+        let offset = self.offset(i, j);
+        self.average(&self.pixels, offset)
+    }
+
+    fn average(&self, pixels: &[Color], offset: usize) -> Color {
+        let weight = self.weights[offset];
+        if weight == 0.0 {
+            Color::new0()
+        } else {
+            pixels[offset] / weight
+        }
+    }
+
+    pub fn samples(&self, i: usize, j: usize) -> usize {
+        self.samples[self.offset(i, j)]
+    }
+
+    /// Statistics of the luminance of the samples added to the pixel.
+    pub fn pixel_stats(&self, i: usize, j: usize) -> &RunningStats {
+        &self.stats[self.offset(i, j)]
+    }
+
+    /// Estimated error of the displayed pixel value: the standard error of
+    /// the luminance carried through the gamma 2 of [`write_color`], i.e.
+    /// divided by `2 sqrt(mean)`.
+    pub fn pixel_error(&self, i: usize, j: usize) -> f64 {
+        let stats = self.pixel_stats(i, j);
+        stats.standard_error() / (2.0 * stats.mean.max(0.0).sqrt() + 1e-4)
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(Color::new0());
+        self.weights.fill(0.0);
+        self.samples.fill(0);
+        self.stats.fill(RunningStats::new());
+        for channel in &mut self.aovs {
+            channel.pixels.fill(Color::new0());
+        }
+    }
+
+    /// Averaged values of `beauty` or an AOV, top scanline first, together
+    /// with the number of components per pixel.
+    pub fn channel_data(&self, name: &str) -> Option<(usize, Vec<f32>)> {
+        let (components, pixels) = if name == "beauty" {
+            (3, &self.pixels)
+        } else {
+            let channel = self.aovs.iter().find(|c| c.name == name)?;
+            (channel.components, &channel.pixels)
+        };
+
+        let mut data = Vec::with_capacity(self.width * self.height * components);
+        for j in (0..self.height).rev() {
+            for i in 0..self.width {
+                let offset = self.offset(i, j);
+                let value = self.average(pixels, offset);
+                for c in 0..components {
+                    data.push(value[c] as f32);
+                }
+            }
+        }
+        Some((components, data))
+    }
+
+    /// Write everything accumulated so far, so that [`Film::read_state`]
+    /// can continue from it.
+    pub fn write_state(&self, os: &mut dyn Write) -> io::Result<()> {
+        os.write_all(STATE_MAGIC)?;
+        write_u64(os, self.width as u64)?;
+        write_u64(os, self.height as u64)?;
+        for offset in 0..self.width * self.height {
+            write_color_f64(os, &self.pixels[offset])?;
+            write_f64(os, self.weights[offset])?;
+            write_u64(os, self.samples[offset] as u64)?;
+            let stats = &self.stats[offset];
+            write_u64(os, stats.count as u64)?;
+            write_f64(os, stats.mean)?;
+            write_f64(os, stats.m2)?;
+        }
+        write_u64(os, self.aovs.len() as u64)?;
+        for channel in &self.aovs {
+            write_u64(os, channel.name.len() as u64)?;
+            os.write_all(channel.name.as_bytes())?;
+            write_u64(os, channel.components as u64)?;
+            for value in &channel.pixels {
+                write_color_f64(os, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a film of `width` x `height` written by [`Film::write_state`].
+    /// The filter is not part of the state and has to be the one the film
+    /// was recorded with. A state of another size, or one that is corrupt,
+    /// is invalid data.
+    pub fn read_state(
+        is: &mut dyn Read,
+        width: usize,
+        height: usize,
+        filter: Arc<dyn Filter>,
+    ) -> io::Result<Film> {
+        let mut magic = [0; 8];
+        is.read_exact(&mut magic)?;
+        if &magic != STATE_MAGIC {
+            return Err(invalid_data("not a film state"));
+        }
+        let (state_width, state_height) = (read_u64(is)?, read_u64(is)?);
+        if (state_width, state_height) != (width as u64, height as u64) {
+            return Err(invalid_data(&format!(
+                "film state of {}x{} where {}x{} was expected",
+                state_width, state_height, width, height
+            )));
+        }
+        let mut film = Film::with_filter(width, height, filter);
+        for offset in 0..width * height {
+            film.pixels[offset] = read_color_f64(is)?;
+            film.weights[offset] = read_f64(is)?;
+            film.samples[offset] = read_u64(is)? as usize;
+            let stats = &mut film.stats[offset];
+            stats.count = read_u64(is)? as usize;
+            stats.mean = read_f64(is)?;
+            stats.m2 = read_f64(is)?;
+        }
+        let aovs = read_u64(is)?;
+        if aovs > MAX_STATE_AOVS {
+            return Err(invalid_data("too many AOVs in film state"));
+        }
+        for _ in 0..aovs {
+            let length = read_u64(is)?;
+            if length > MAX_AOV_NAME {
+                return Err(invalid_data("AOV name too long in film state"));
+            }
+            let mut name = vec![0; length as usize];
+            is.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid_data("invalid AOV name"))?;
+            if film.aovs.iter().any(|c| c.name == name) {
+                return Err(invalid_data(&format!(
+                    "duplicate AOV {} in film state",
+                    name
+                )));
+            }
+            let components = read_u64(is)?;
+            if components != 1 && components != 3 {
+                return Err(invalid_data("AOV with neither 1 nor 3 components"));
+            }
+            film.enable_aov(&name, components as usize);
+            let channel = film.aovs.last_mut().unwrap();
+            for value in &mut channel.pixels {
+                *value = read_color_f64(is)?;
+            }
+        }
+        Ok(film)
+    }
+
+    /// Write the image as an ASCII PPM, top scanline first.
+    pub fn write_ppm(&self, os: &mut dyn Write) -> io::Result<()> {
+        os.write_all(format!("P3\n{} {}\n255\n", self.width, self.height).as_bytes())?;
+        for j in (0..self.height).rev() {
+            for i in 0..self.width {
+                write_color(os, &self.pixel_value(i, j), 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `beauty` or an AOV as a linear PFM.
+    pub fn write_pfm(&self, name: &str, os: &mut dyn Write) -> io::Result<()> {
+        let (components, data) = self.channel_data(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no channel {}", name))
+        })?;
+        write_pfm(os, self.width, self.height, components, &data)
+    }
+
+    /// Write the beauty image as `R`, `G`, `B` and every AOV as its own layer
+    /// (`albedo.R`, ..., or just `depth` for scalar AOVs) of one EXR.
+    pub fn write_exr(&self, os: &mut dyn Write) -> io::Result<()> {
+        let mut channels = Vec::new();
+        let mut names = vec!["beauty"];
+        names.extend(self.aov_names());
+        for name in names {
+            let (components, data) = self.channel_data(name).unwrap();
+            for c in 0..components {
+                let channel_name = match (name, components) {
+                    ("beauty", _) => ["R", "G", "B"][c].to_string(),
+                    (_, 1) => name.to_string(),
+                    _ => format!("{}.{}", name, ["R", "G", "B"][c]),
+                };
+                channels.push(ExrChannel {
+                    name: channel_name,
+                    data: data.iter().skip(c).step_by(components).copied().collect(),
+                });
+            }
+        }
+        write_exr(os, self.width, self.height, &channels)
+    }
+}
+
+const STATE_MAGIC: &[u8; 8] = b"FILM\x00\x00\x00\x01";
+
+/// Most AOVs and longest AOV name accepted by [`Film::read_state`].
+const MAX_STATE_AOVS: u64 = 64;
+const MAX_AOV_NAME: u64 = 256;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_color_f64(os: &mut dyn Write, value: &Color) -> io::Result<()> {
+    write_f64(os, value.x)?;
+    write_f64(os, value.y)?;
+    write_f64(os, value.z)
+}
+
+fn read_color_f64(is: &mut dyn Read) -> io::Result<Color> {
+    Ok(Color::new([read_f64(is)?, read_f64(is)?, read_f64(is)?]))
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter;
+    use crate::rng::Rng;
+    use crate::{color, Vector3};
+
+    fn samples(width: usize, height: usize) -> Vec<(f64, f64, Color)> {
+        let mut rng = Rng::new(5);
+        (0..width * height * 8)
+            .map(|_| {
+                let x = rng.next_f64() * width as f64;
+                let y = rng.next_f64() * height as f64;
+                (x, y, color![x, y, rng.next_f64()])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_constant_image_is_reconstructed_exactly() {
+        for name in filter::FILTER_NAMES {
+            let mut film = Film::with_filter(8, 8, filter::from_name(name).unwrap());
+            for (x, y, _) in samples(8, 8) {
+                film.add_sample(x, y, &color![0.25, 0.5, 1.0]);
+            }
+            for j in 0..8 {
+                for i in 0..8 {
+                    let value = film.pixel_value(i, j);
+                    let d = Vector3::from(value - color![0.25, 0.5, 1.0]);
+                    assert!(d.length() < 1e-9, "{}", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiles_with_margin_match_whole_film() {
+        let filter = filter::from_name("gaussian:2").unwrap();
+        let (width, height) = (12, 10);
+        let mut whole = Film::with_filter(width, height, filter.clone());
+        let mut merged = whole.new_like(width, height);
+        let margin = whole.filter_margin();
+        assert_eq!(margin, 2);
+
+        // Two halves, each splatting onto a film reaching `margin` pixels
+        // into the other half.
+        let split = 6;
+        let mut left = whole.new_like(split + margin, height);
+        let mut right = whole.new_like(width - split + margin, height);
+        for (x, y, color) in samples(width, height) {
+            whole.add_sample(x, y, &color);
+            if x < split as f64 {
+                left.add_sample(x, y, &color);
+            } else {
+                right.add_sample(x - (split - margin) as f64, y, &color);
+            }
+        }
+        merged.merge_tile(&left, 0, 0);
+        merged.merge_tile(&right, split - margin, 0);
+
+        for j in 0..height {
+            for i in 0..width {
+                let d = Vector3::from(whole.pixel_value(i, j) - merged.pixel_value(i, j));
+                assert!(d.length() < 1e-9);
+                assert_eq!(whole.samples(i, j), merged.samples(i, j));
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let filter = filter::from_name("triangle").unwrap();
+        let mut film = Film::with_filter(5, 4, filter.clone());
+        film.enable_aov("albedo", 3);
+        film.enable_aov("depth", 1);
+        let mut aovs = AovSample::new();
+        for (x, y, color) in samples(5, 4) {
+            aovs.set("albedo", color * 0.5);
+            aovs.set("depth", color![x, 0.0, 0.0]);
+            film.add_sample_with_aovs(x, y, &color, &aovs);
+        }
+
+        let mut bytes = Vec::new();
+        film.write_state(&mut bytes).unwrap();
+        let read = Film::read_state(&mut bytes.as_slice(), 5, 4, filter.clone()).unwrap();
+        for name in ["beauty", "albedo", "depth"] {
+            assert_eq!(film.channel_data(name), read.channel_data(name));
+        }
+        for j in 0..4 {
+            for i in 0..5 {
+                assert_eq!(film.samples(i, j), read.samples(i, j));
+                assert_eq!(film.pixel_stats(i, j), read.pixel_stats(i, j));
+            }
+        }
+
+        assert!(Film::read_state(&mut &bytes[..bytes.len() - 1], 5, 4, filter.clone()).is_err());
+        assert!(Film::read_state(&mut &b"P3\n5 4\n255\n"[..], 5, 4, filter.clone()).is_err());
+        assert!(Film::read_state(&mut bytes.as_slice(), 4, 5, filter.clone()).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_state() {
+        let filter = filter::from_name("box").unwrap();
+        let mut film = Film::with_filter(1, 1, filter.clone());
+        film.enable_aov("depth", 1);
+        let mut bytes = Vec::new();
+        film.write_state(&mut bytes).unwrap();
+        let read = |bytes: &[u8]| Film::read_state(&mut &bytes[..], 1, 1, filter.clone());
+        assert!(read(&bytes).is_ok());
+
+        // Magic, size and one pixel, then the AOV count, name length, name,
+        // components and value.
+        let aovs = 8 + 16 + 8 * 8;
+        let patch = |at: usize, value: u64| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+            bytes
+        };
+        // A size that would not fit in memory.
+        assert!(read(&patch(8, u64::MAX)).is_err());
+        assert!(read(&patch(8, 1 << 32)).is_err());
+        assert!(read(&patch(aovs, u64::MAX)).is_err());
+        assert!(read(&patch(aovs + 8, u64::MAX)).is_err());
+        assert!(read(&patch(aovs + 8 + 8 + 5, 7)).is_err());
+
+        // The same AOV twice.
+        let mut twice = patch(aovs, 2);
+        twice.extend_from_slice(&bytes[aovs + 8..]);
+        let error = read(&twice).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("duplicate"), "{}", error);
+    }
+}
@@ -0,0 +1,298 @@
+//! 像素重建滤波器
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Weight of a sample for the pixels around it, as a function of the offset
+/// `(x, y)` in pixels from the sample to the pixel center.
+pub trait Filter: Send + Sync {
+    /// The filter is zero where `|x|` or `|y|` exceeds the radius.
+    fn radius(&self) -> f64;
+
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+/// Names accepted by [`from_name`].
+pub const FILTER_NAMES: [&str; 6] = [
+    "box",
+    "triangle",
+    "gaussian",
+    "mitchell",
+    "lanczos",
+    "blackman-harris",
+];
+
+/// Create a filter from `name[:radius]`, e.g. `gaussian:2`.
+pub fn from_name(spec: &str) -> Result<Arc<dyn Filter>, String> {
+    let mut parts = spec.splitn(2, ':');
+    let name = parts.next().unwrap_or_default();
+    let radius = match parts.next() {
+        Some(radius) => {
+            let radius: f64 = radius
+                .parse()
+                .map_err(|e| format!("invalid filter radius {:?}: {}", radius, e))?;
+            if radius <= 0.0 {
+                return Err(format!("filter radius must be positive: {}", radius));
+            }
+            Some(radius)
+        }
+        None => None,
+    };
+
+    let filter: Arc<dyn Filter> = match name {
+        "box" => Arc::new(BoxFilter::new(radius.unwrap_or(0.5))),
+        "triangle" => Arc::new(TriangleFilter::new(radius.unwrap_or(1.0))),
+        "gaussian" => {
+            let radius = radius.unwrap_or(1.5);
+            Arc::new(GaussianFilter::new(radius, radius / 3.0))
+        }
+        "mitchell" => Arc::new(MitchellFilter::new(
+            radius.unwrap_or(2.0),
+            1.0 / 3.0,
+            1.0 / 3.0,
+        )),
+        "lanczos" => Arc::new(LanczosFilter::new(radius.unwrap_or(3.0))),
+        "blackman-harris" => Arc::new(BlackmanHarrisFilter::new(radius.unwrap_or(1.5))),
+        _ => {
+            return Err(format!(
+                "unknown filter: {} (expected one of {})",
+                name,
+                FILTER_NAMES.join(", ")
+            ))
+        }
+    };
+    Ok(filter)
+}
+
+/// Constant weight over a square. With the default radius of 0.5 every
+/// sample counts for exactly the pixel it lies in.
+pub struct BoxFilter {
+    pub radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        BoxFilter { radius }
+    }
+
+    /// Half open, so that a sample on a pixel edge counts once.
+    fn inside(&self, x: f64) -> bool {
+        -self.radius < x && x <= self.radius
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if self.inside(x) && self.inside(y) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Tent falling linearly to zero at the radius.
+pub struct TriangleFilter {
+    pub radius: f64,
+}
+
+impl TriangleFilter {
+    pub fn new(radius: f64) -> Self {
+        TriangleFilter { radius }
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let tent = |t: f64| (1.0 - t.abs() / self.radius).max(0.0);
+        tent(x) * tent(y)
+    }
+}
+
+/// Gaussian of standard deviation `sigma`, shifted down to reach zero at
+/// the radius.
+pub struct GaussianFilter {
+    pub radius: f64,
+    pub sigma: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        GaussianFilter { radius, sigma }
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        (-x * x / (2.0 * self.sigma * self.sigma)).exp()
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let edge = self.gaussian(self.radius);
+        let g = |t: f64| (self.gaussian(t) - edge).max(0.0);
+        g(x) * g(y)
+    }
+}
+
+/// Mitchell-Netravali cubic with parameters `b` and `c`, stretched to the
+/// radius. It has negative lobes, which sharpen.
+pub struct MitchellFilter {
+    pub radius: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> Self {
+        MitchellFilter { radius, b, c }
+    }
+
+    /// The cubic on its natural support [-2, 2].
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        if x < 1.0 {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b))
+                / 6.0
+        } else if x < 2.0 {
+            ((-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let scale = 2.0 / self.radius;
+        self.mitchell(x * scale) * self.mitchell(y * scale)
+    }
+}
+
+/// Sinc windowed by a wider sinc, with as many lobes as the radius.
+pub struct LanczosFilter {
+    pub radius: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64) -> Self {
+        LanczosFilter { radius }
+    }
+
+    fn lanczos(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            return 0.0;
+        }
+        sinc(x) * sinc(x / self.radius)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.lanczos(x) * self.lanczos(y)
+    }
+}
+
+/// Four-term Blackman-Harris window spanning the diameter.
+pub struct BlackmanHarrisFilter {
+    pub radius: f64,
+}
+
+impl BlackmanHarrisFilter {
+    pub fn new(radius: f64) -> Self {
+        BlackmanHarrisFilter { radius }
+    }
+
+    fn window(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            return 0.0;
+        }
+        let n = 2.0 * PI * (x + self.radius) / (2.0 * self.radius);
+        0.35875 - 0.48829 * n.cos() + 0.14128 * (2.0 * n).cos() - 0.01168 * (3.0 * n).cos()
+    }
+}
+
+impl Filter for BlackmanHarrisFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.window(x) * self.window(y)
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters_peak_at_center_and_vanish_outside() {
+        for name in FILTER_NAMES {
+            let filter = from_name(name).unwrap();
+            let center = filter.evaluate(0.0, 0.0);
+            assert!(center > 0.0, "{}", name);
+            let r = filter.radius();
+            for &(x, y) in &[(0.3, 0.1), (0.7 * r, 0.0), (0.2, -0.5 * r)] {
+                assert!(filter.evaluate(x, y) <= center, "{}", name);
+            }
+            assert_eq!(filter.evaluate(r + 0.01, 0.0), 0.0, "{}", name);
+            assert_eq!(filter.evaluate(0.0, -r - 0.01), 0.0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_filters_are_symmetric() {
+        for name in FILTER_NAMES {
+            let filter = from_name(name).unwrap();
+            for &(x, y) in &[(0.3, 0.2), (1.1, 0.4), (0.05, 0.45)] {
+                let w = filter.evaluate(x, y);
+                assert!((filter.evaluate(-x, y) - w).abs() < 1e-12, "{}", name);
+                assert!((filter.evaluate(y, x) - w).abs() < 1e-12, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_radius_spec() {
+        assert_eq!(from_name("triangle:2.5").unwrap().radius(), 2.5);
+        assert_eq!(from_name("box").unwrap().radius(), 0.5);
+        assert!(from_name("gaussian:0").is_err());
+        assert!(from_name("sinc").is_err());
+    }
+}
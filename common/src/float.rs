@@ -0,0 +1,73 @@
+//! 浮点精度
+
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Scalar type of the math types: `f64` for accuracy, `f32` where memory
+/// bandwidth matters more, such as mesh vertices.
+pub trait Float:
+    Copy
+    + Debug
+    + Default
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const INFINITY: Self;
+
+    /// The nearest value of this precision.
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {
+        $(
+            impl Float for $t {
+                const ZERO: Self = 0.0;
+                const ONE: Self = 1.0;
+                const INFINITY: Self = <$t>::INFINITY;
+
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn sqrt(self) -> Self {
+                    <$t>::sqrt(self)
+                }
+
+                fn abs(self) -> Self {
+                    <$t>::abs(self)
+                }
+
+                fn min(self, other: Self) -> Self {
+                    <$t>::min(self, other)
+                }
+
+                fn max(self, other: Self) -> Self {
+                    <$t>::max(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32, f64);
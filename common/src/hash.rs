@@ -0,0 +1,56 @@
+//! 稳定哈希
+
+use std::hash::Hasher;
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is guaranteed to give the same
+/// hash in every build, so hashes may be stored, e.g. in checkpoints.
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Feed the bits of `values` into `hasher`.
+pub fn write_f64s(hasher: &mut dyn Hasher, values: &[f64]) {
+    for value in values {
+        hasher.write_u64(value.to_bits());
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values of the FNV-1a specification.
+        assert_eq!(Fnv1a::new().finish(), 0xcbf29ce484222325);
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"foobar");
+        assert_eq!(hasher.finish(), 0x85944171f73967e8);
+    }
+}
@@ -0,0 +1,82 @@
+//! 浮点 RGB 图像
+
+use crate::color::write_color;
+use crate::pfm::write_pfm;
+use crate::{color, Color};
+use std::io::{self, Write};
+
+/// A linear float RGB image, top scanline first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![color![]; width * height],
+        }
+    }
+
+    /// An image of `components` (1 or 3) floats per pixel; a single
+    /// component is repeated in all three channels.
+    pub fn from_data(width: usize, height: usize, components: usize, data: &[f32]) -> Self {
+        assert!(components == 1 || components == 3);
+        assert_eq!(data.len(), width * height * components);
+        let pixels = data
+            .chunks_exact(components)
+            .map(|p| {
+                let c = |k: usize| p[k.min(components - 1)] as f64;
+                color![c(0), c(1), c(2)]
+            })
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Three floats per pixel.
+    pub fn to_data(&self) -> Vec<f32> {
+        self.pixels
+            .iter()
+            .flat_map(|p| [p.x as f32, p.y as f32, p.z as f32])
+            .collect()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// The `width` x `height` part whose top left pixel is `(x, y)`.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        assert!(x + width <= self.width && y + height <= self.height);
+        let pixels = (y..y + height)
+            .flat_map(|j| (x..x + width).map(move |i| (i, j)))
+            .map(|(i, j)| self.get(i, j))
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Write as an ASCII PPM, gamma corrected like [`crate::Film::write_ppm`].
+    pub fn write_ppm(&self, os: &mut dyn Write) -> io::Result<()> {
+        os.write_all(format!("P3\n{} {}\n255\n", self.width, self.height).as_bytes())?;
+        for pixel in &self.pixels {
+            write_color(os, pixel, 1)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_pfm(&self, os: &mut dyn Write) -> io::Result<()> {
+        write_pfm(os, self.width, self.height, 3, &self.to_data())
+    }
+}
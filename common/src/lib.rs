@@ -1,12 +1,39 @@
+pub mod aabb;
+pub mod animation;
+pub mod binary;
 pub mod camera;
 pub mod color;
+pub mod denoise;
+pub mod exr;
+pub mod film;
+pub mod filter;
+pub mod float;
+pub mod hash;
+pub mod image;
 mod macros;
+pub mod matrix;
+pub mod onb;
+pub mod pfm;
+pub mod quaternion;
 pub mod ray;
+pub mod rng;
 pub mod rtweekend;
+pub mod sampler;
+pub mod sampling;
+pub mod simd;
+pub mod stats;
+pub mod transform;
 pub mod vec3;
 
-pub use camera::Camera;
+pub use aabb::Aabb;
+pub use camera::{Camera, OrthographicCamera, PerspectiveCamera};
+pub use film::Film;
+pub use float::Float;
+pub use image::Image;
+pub use matrix::{Mat3, Mat4};
+pub use onb::Onb;
+pub use quaternion::Quaternion;
+pub use sampler::Sampler;
+pub use transform::Transform;
 
-pub use vec3::Vec3;
-pub type Color = Vec3;
-pub type Point3 = Vec3;
+pub use vec3::{Color, Normal3, Point3, Vector3};
@@ -1,39 +1,39 @@
 #[macro_export]
 macro_rules! vec3 {
     () => (
-        $crate::Vec3::new0()
+        $crate::vec3::Vector3::<f64>::new0()
     );
     ($($x:expr),+ $(,)?) => (
-        $crate::Vec3::new([$($x as f64),+])
+        $crate::vec3::Vector3::new([$($x as f64),+])
     );
 }
 
 #[macro_export]
 macro_rules! point3 {
-    () => {
-        vec3!()
-    };
+    () => (
+        $crate::vec3::Point3::<f64>::new0()
+    );
     ($($x:expr),+ $(,)?) => (
-        vec3!($($x),+)
+        $crate::vec3::Point3::new([$($x as f64),+])
     );
 }
 
 #[macro_export]
-macro_rules! color {
-    () => {
-        vec3!()
-    };
+macro_rules! normal3 {
+    () => (
+        $crate::vec3::Normal3::<f64>::new0()
+    );
     ($($x:expr),+ $(,)?) => (
-        vec3!($($x),+)
+        $crate::vec3::Normal3::new([$($x as f64),+])
     );
 }
 
 #[macro_export]
-macro_rules! rand_vec3 {
-    () => {
-        $crate::Vec3::random()
-    };
-    ($min:expr, $max:expr) => {
-        $crate::Vec3::random_range($min, $max)
-    };
+macro_rules! color {
+    () => (
+        $crate::vec3::Color::<f64>::new0()
+    );
+    ($($x:expr),+ $(,)?) => (
+        $crate::vec3::Color::new([$($x as f64),+])
+    );
 }
@@ -0,0 +1,251 @@
+//! 3x3 与 4x4 矩阵
+
+use crate::{vec3, Vector3};
+use std::ops::Mul;
+
+/// Row-major 3 x 3 matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    pub m: [[f64; 3]; 3],
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3 {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+
+    pub fn new(m: [[f64; 3]; 3]) -> Self {
+        Mat3 { m }
+    }
+
+    /// The matrix whose columns are `a`, `b` and `c`.
+    pub fn from_columns(a: &Vector3, b: &Vector3, c: &Vector3) -> Self {
+        Mat3 {
+            m: [[a.x, b.x, c.x], [a.y, b.y, c.y], [a.z, b.z, c.z]],
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let m = &self.m;
+        Mat3 {
+            m: [
+                [m[0][0], m[1][0], m[2][0]],
+                [m[0][1], m[1][1], m[2][1]],
+                [m[0][2], m[1][2], m[2][2]],
+            ],
+        }
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        // The adjugate: transposed cofactors.
+        let m = &self.m;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) / det
+        };
+        Some(Mat3 {
+            m: [
+                [
+                    cofactor(1, 2, 1, 2),
+                    -cofactor(0, 2, 1, 2),
+                    cofactor(0, 1, 1, 2),
+                ],
+                [
+                    -cofactor(1, 2, 0, 2),
+                    cofactor(0, 2, 0, 2),
+                    -cofactor(0, 1, 0, 2),
+                ],
+                [
+                    cofactor(1, 2, 0, 1),
+                    -cofactor(0, 2, 0, 1),
+                    cofactor(0, 1, 0, 1),
+                ],
+            ],
+        })
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, rhs: Mat3) -> Self::Output {
+        let mut m = [[0.0; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat3 { m }
+    }
+}
+
+impl Mul<Vector3> for Mat3 {
+    type Output = Vector3;
+
+    fn mul(self, v: Vector3) -> Self::Output {
+        let m = &self.m;
+        vec3![
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z
+        ]
+    }
+}
+
+/// Row-major 4 x 4 matrix, acting on column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+
+    /// The affine map `p -> linear p + translation`.
+    pub fn from_affine(linear: &Mat3, translation: &Vector3) -> Self {
+        let l = &linear.m;
+        Mat4 {
+            m: [
+                [l[0][0], l[0][1], l[0][2], translation.x],
+                [l[1][0], l[1][1], l[1][2], translation.y],
+                [l[2][0], l[2][1], l[2][2], translation.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// The upper left 3 x 3 block.
+    pub fn linear(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3 {
+            m: [
+                [m[0][0], m[0][1], m[0][2]],
+                [m[1][0], m[1][1], m[1][2]],
+                [m[2][0], m[2][1], m[2][2]],
+            ],
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting; `None` if the
+    /// matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Mat4::IDENTITY.m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column] == 0.0 || !a[pivot][column].is_finite() {
+                return None;
+            }
+            a.swap(column, pivot);
+            inv.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inv[column][j] *= scale;
+            }
+            for i in 0..4 {
+                let factor = a[i][column];
+                if i == column || factor == 0.0 {
+                    continue;
+                }
+                for j in 0..4 {
+                    a[i][j] -= factor * a[column][j];
+                    inv[i][j] -= factor * inv[column][j];
+                }
+            }
+        }
+        Some(Mat4 { m: inv })
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(a: &Mat4, b: &Mat4) -> bool {
+        (0..4).all(|i| (0..4).all(|j| (a.m[i][j] - b.m[i][j]).abs() < 1e-12))
+    }
+
+    #[test]
+    fn test_mat4_inverse() {
+        let m = Mat4::new([
+            [2.0, 0.0, 1.0, 3.0],
+            [0.0, 0.0, 4.0, -1.0],
+            [1.0, 3.0, 0.0, 2.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let inv = m.inverse().unwrap();
+        assert!(near(&(m * inv), &Mat4::IDENTITY));
+        assert!(near(&(inv * m), &Mat4::IDENTITY));
+        assert_eq!(Mat4::IDENTITY.inverse(), Some(Mat4::IDENTITY));
+        assert!(Mat4::new([[1.0; 4]; 4]).inverse().is_none());
+        assert_eq!(m.transpose().transpose(), m);
+    }
+
+    #[test]
+    fn test_mat3() {
+        let m = Mat3::from_columns(&vec3![1, 0, 2], &vec3![0, 3, 0], &vec3![1, 1, 1]);
+        assert_eq!(m * vec3![1, 0, 0], vec3![1, 0, 2]);
+        assert!((m.determinant() - -3.0).abs() < 1e-12);
+        let inv = m.inverse().unwrap();
+        let product = inv * m;
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.m[i][j] - expected).abs() < 1e-12);
+            }
+        }
+        assert_eq!(Mat4::from_affine(&m, &vec3![]).linear(), m);
+        assert!(Mat3::new([[1.0; 3]; 3]).inverse().is_none());
+    }
+}
@@ -0,0 +1,119 @@
+//! 正交基
+
+use crate::{vec3, Vector3};
+
+/// Right-handed orthonormal basis. As a shading frame `w` is the normal, `u`
+/// the tangent and `v` the bitangent; local coordinates have the normal
+/// along z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    /// The basis of the coordinate axes.
+    pub const AXES: Onb = Onb {
+        u: Vector3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        v: Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        w: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+    };
+
+    /// Some basis around the unit vector `w`, without branches and without
+    /// the loss of precision of building it from a cross product (Frisvad
+    /// 2012, as corrected by Duff et al. 2017).
+    pub fn from_w(w: &Vector3) -> Self {
+        let sign = 1f64.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        Onb {
+            u: vec3![1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x],
+            v: vec3![b, sign + w.y * w.y * a, -w.y],
+            w: *w,
+        }
+    }
+
+    /// The basis around the unit vector `w` whose `u` is `tangent` made
+    /// perpendicular to `w`. Falls back to [`Onb::from_w`] if `tangent` is
+    /// parallel to `w`.
+    pub fn from_w_u(w: &Vector3, tangent: &Vector3) -> Self {
+        let u = *tangent - tangent.dot(w) * w;
+        if u.near_zero() {
+            return Onb::from_w(w);
+        }
+        let u = u.unit_vector();
+        Onb {
+            u,
+            v: w.cross(&u),
+            w: *w,
+        }
+    }
+
+    /// Coordinates of the world space vector `a` in this basis.
+    pub fn to_local(&self, a: &Vector3) -> Vector3 {
+        vec3![a.dot(&self.u), a.dot(&self.v), a.dot(&self.w)]
+    }
+
+    /// The world space vector with coordinates `a` in this basis.
+    pub fn to_world(&self, a: &Vector3) -> Vector3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_orthonormal(onb: &Onb) {
+        for (a, b) in [(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+            assert!(a.dot(&b).abs() < 1e-12);
+            assert!((a.length() - 1.0).abs() < 1e-12);
+        }
+        // Right-handed.
+        assert!((onb.u.cross(&onb.v) - onb.w).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_from_w() {
+        for w in [
+            vec3![0, 0, 1],
+            vec3![0, 0, -1],
+            vec3![1, 0, 0],
+            vec3![0.3, -0.4, 0.5].unit_vector(),
+            vec3![1e-9, 0, -1].unit_vector(),
+        ] {
+            let onb = Onb::from_w(&w);
+            assert_orthonormal(&onb);
+            assert_eq!(onb.w, w);
+        }
+        assert_eq!(Onb::from_w(&vec3![0, 0, 1]), Onb::AXES);
+    }
+
+    #[test]
+    fn test_from_w_u_and_conversions() {
+        let w = vec3![0, 1, 0];
+        let onb = Onb::from_w_u(&w, &vec3![2, 1, 0]);
+        assert_orthonormal(&onb);
+        assert_eq!(onb.u, vec3![1, 0, 0]);
+        assert_orthonormal(&Onb::from_w_u(&w, &vec3![0, 3, 0]));
+
+        let a = vec3![0.2, -0.7, 0.4];
+        let local = onb.to_local(&a);
+        assert!((local.z - a.dot(&w)).abs() < 1e-12);
+        assert!((onb.to_world(&local) - a).length() < 1e-12);
+    }
+}
@@ -0,0 +1,163 @@
+//! PFM (Portable Float Map) 输入输出
+
+use std::io::{self, BufRead, Read, Write};
+
+/// Write a little-endian PFM. `data` holds `components` (1 or 3) floats per
+/// pixel, top scanline first.
+pub fn write_pfm(
+    os: &mut dyn Write,
+    width: usize,
+    height: usize,
+    components: usize,
+    data: &[f32],
+) -> std::io::Result<()> {
+    assert!(components == 1 || components == 3);
+    assert_eq!(data.len(), width * height * components);
+
+    let magic = if components == 3 { "PF" } else { "Pf" };
+    os.write_all(format!("{}\n{} {}\n-1.0\n", magic, width, height).as_bytes())?;
+    // PFM stores the bottom scanline first.
+    let row = width * components;
+    let mut bytes = Vec::with_capacity(row * 4);
+    for y in (0..height).rev() {
+        bytes.clear();
+        for v in &data[y * row..(y + 1) * row] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        os.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// A PFM as read by [`read_pfm`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PfmImage {
+    pub width: usize,
+    pub height: usize,
+    /// 1 or 3.
+    pub components: usize,
+    /// `components` floats per pixel, top scanline first.
+    pub data: Vec<f32>,
+}
+
+/// Read a PFM of either byte order.
+pub fn read_pfm(is: &mut dyn BufRead) -> io::Result<PfmImage> {
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        let mut line = String::new();
+        if is.read_line(&mut line)? == 0 {
+            return Err(invalid_data("truncated PFM header"));
+        }
+        header.extend(line.split_whitespace().map(str::to_string));
+    }
+    let components = match header[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM")),
+    };
+    let number = |s: &str| {
+        s.parse::<f64>()
+            .map_err(|_| invalid_data("invalid PFM header"))
+    };
+    let width = number(&header[1])? as usize;
+    let height = number(&header[2])? as usize;
+    let little_endian = number(&header[3])? < 0.0;
+
+    let size = width
+        .checked_mul(components)
+        .and_then(|row| row.checked_mul(height))
+        .and_then(|values| values.checked_mul(4))
+        .ok_or_else(|| invalid_data("PFM too large"))?;
+    // Read what is there rather than allocating the size of the header.
+    let mut bytes = Vec::new();
+    is.take(size as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < size {
+        return Err(invalid_data("truncated PFM"));
+    }
+    let row = width * components;
+    let values: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(b)
+            } else {
+                f32::from_be_bytes(b)
+            }
+        })
+        .collect();
+    let mut data = Vec::with_capacity(values.len());
+    for y in (0..height).rev() {
+        data.extend_from_slice(&values[y * row..(y + 1) * row]);
+    }
+    Ok(PfmImage {
+        width,
+        height,
+        components,
+        data,
+    })
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for components in [1, 3] {
+            let data: Vec<f32> = (0..3 * 2 * components)
+                .map(|i| i as f32 * 0.5 - 1.0)
+                .collect();
+            let mut bytes = Vec::new();
+            write_pfm(&mut bytes, 3, 2, components, &data).unwrap();
+            let image = read_pfm(&mut bytes.as_slice()).unwrap();
+            assert_eq!(
+                image,
+                PfmImage {
+                    width: 3,
+                    height: 2,
+                    components,
+                    data
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, 2, 2, 1, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+        let mut expected = b"Pf\n2 2\n-1.0\n".to_vec();
+        // Bottom scanline first.
+        for v in [3.0f32, 4.0, 1.0, 2.0] {
+            expected.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(bytes, expected);
+
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, 1, 1, 3, &[0.5, -1.0, 2.0]).unwrap();
+        assert!(bytes.starts_with(b"PF\n1 1\n-1.0\n"));
+        assert_eq!(bytes.len(), 12 + 12);
+        assert_eq!(bytes[12..16], 0.5f32.to_le_bytes());
+
+        // Short of data, or claiming more than fits in memory.
+        assert!(read_pfm(&mut &expected[..expected.len() - 1]).is_err());
+        let huge = format!("PF\n{} {}\n-1.0\n", usize::MAX / 2, 2);
+        assert!(read_pfm(&mut huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let mut bytes = b"Pf\n2 1\n1.0\n".to_vec();
+        bytes.extend_from_slice(&1.5f32.to_be_bytes());
+        bytes.extend_from_slice(&(-2.0f32).to_be_bytes());
+        let image = read_pfm(&mut bytes.as_slice()).unwrap();
+        assert_eq!(image.data, vec![1.5, -2.0]);
+        assert!(read_pfm(&mut &b"P6\n2 1\n255\n"[..]).is_err());
+    }
+}
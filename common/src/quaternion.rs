@@ -0,0 +1,170 @@
+//! 四元数
+
+use crate::matrix::Mat3;
+use crate::{vec3, Vector3};
+use std::ops::Mul;
+
+/// Quaternion `w + xi + yj + zk`; unit quaternions represent rotations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    /// Rotation by `degrees` counterclockwise about `axis`, looking against
+    /// it.
+    pub fn from_axis_angle(axis: &Vector3, degrees: f64) -> Self {
+        let half = degrees.to_radians() / 2.0;
+        let v = half.sin() * axis.unit_vector();
+        Quaternion::new(half.cos(), v.x, v.y, v.z)
+    }
+
+    /// The vector part `xi + yj + zk`.
+    pub fn vector(&self) -> Vector3 {
+        vec3![self.x, self.y, self.z]
+    }
+
+    pub fn dot(&self, rhs: &Quaternion) -> f64 {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        self.scale(1.0 / self.length())
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    fn scale(&self, s: f64) -> Self {
+        Quaternion::new(s * self.w, s * self.x, s * self.y, s * self.z)
+    }
+
+    fn add(&self, rhs: &Quaternion) -> Self {
+        Quaternion::new(
+            self.w + rhs.w,
+            self.x + rhs.x,
+            self.y + rhs.y,
+            self.z + rhs.z,
+        )
+    }
+
+    /// `v` turned by this unit quaternion.
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        // v + 2 q x (q x v + w v), the sandwich q v q* expanded.
+        let q = self.vector();
+        let t = 2.0 * q.cross(v);
+        *v + self.w * t + q.cross(&t)
+    }
+
+    /// Rotation matrix of this unit quaternion.
+    pub fn to_mat3(&self) -> Mat3 {
+        let Quaternion { w, x, y, z } = *self;
+        Mat3::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ])
+    }
+
+    /// Spherical linear interpolation from `self` at `t = 0` to `rhs` at
+    /// `t = 1` between unit quaternions: constant angular velocity along the
+    /// shorter arc.
+    pub fn slerp(&self, rhs: &Quaternion, t: f64) -> Self {
+        // q and -q are the same rotation; take the one closer to `self`.
+        let (rhs, cos_theta) = match self.dot(rhs) {
+            d if d < 0.0 => (rhs.scale(-1.0), -d),
+            d => (*rhs, d),
+        };
+        if cos_theta > 0.9995 {
+            // Nearly parallel: linear interpolation avoids dividing by
+            // sin(theta) ~ 0.
+            return self.scale(1.0 - t).add(&rhs.scale(t)).normalize();
+        }
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        self.scale(a).add(&rhs.scale(b))
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    /// The rotation by `rhs` followed by `self`.
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        let (a, b) = (self.vector(), rhs.vector());
+        let v = self.w * b + rhs.w * a + a.cross(&b);
+        Quaternion::new(self.w * rhs.w - a.dot(&b), v.x, v.y, v.z)
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let q = Quaternion::from_axis_angle(&vec3![0, 0, 2], 90.0);
+        assert!((q.rotate(&vec3![1, 0, 0]) - vec3![0, 1, 0]).length() < 1e-12);
+        assert!((q.to_mat3() * vec3![1, 2, 3] - q.rotate(&vec3![1, 2, 3])).length() < 1e-12);
+
+        // Two quarter turns make a half turn.
+        let half = q * q;
+        assert!((half.rotate(&vec3![1, 0, 0]) - vec3![-1, 0, 0]).length() < 1e-12);
+        let back = q.conjugate() * q;
+        assert!((back.rotate(&vec3![1, 2, 3]) - vec3![1, 2, 3]).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let a = Quaternion::IDENTITY;
+        let b = Quaternion::from_axis_angle(&vec3![0, 1, 0], 120.0);
+        assert!((a.slerp(&b, 0.0).dot(&a) - 1.0).abs() < 1e-12);
+        assert!((a.slerp(&b, 1.0).dot(&b) - 1.0).abs() < 1e-12);
+        // Halfway is the rotation by half the angle.
+        let mid = a.slerp(&b, 0.5);
+        let expected = Quaternion::from_axis_angle(&vec3![0, 1, 0], 60.0);
+        assert!((mid.dot(&expected) - 1.0).abs() < 1e-12);
+        assert!((mid.length() - 1.0).abs() < 1e-12);
+
+        // The shorter way round even if the sign of `b` is flipped.
+        let flipped = b.scale(-1.0);
+        assert!((a.slerp(&flipped, 0.5).dot(&expected).abs() - 1.0).abs() < 1e-12);
+        // Nearly equal rotations.
+        let c = Quaternion::from_axis_angle(&vec3![0, 1, 0], 0.01);
+        assert!((a.slerp(&c, 0.5).length() - 1.0).abs() < 1e-12);
+    }
+}
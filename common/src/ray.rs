@@ -1,24 +1,36 @@
-use crate::Point3;
+use crate::{Point3, Vector3};
 
 pub struct Ray {
     pub origin: Point3,
-    pub direction: Point3,
+    pub direction: Vector3,
+    /// Moment the ray exists at, 0 unless set with [`Ray::with_time`].
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new0() -> Self {
         Ray {
             origin: Point3::new0(),
-            direction: Point3::new0(),
+            direction: Vector3::new0(),
+            time: 0.0,
         }
     }
-    pub fn new(origin: &Point3, direction: &Point3) -> Self
+    pub fn new(origin: &Point3, direction: &Vector3) -> Self
     where
         Self: Sized,
     {
         Ray {
-            origin: origin.clone(),
-            direction: direction.clone(),
+            origin: *origin,
+            direction: *direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(origin: &Point3, direction: &Vector3, time: f64) -> Self {
+        Ray {
+            origin: *origin,
+            direction: *direction,
+            time,
         }
     }
 
@@ -1,22 +1,10 @@
 //! 工具函数
 
-use crate::Vec3;
-
 /// 角度转弧度
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * std::f64::consts::PI / 180.0
 }
 
-/// Returns a random real in [0,1).
-pub fn random_double() -> f64 {
-    rand::random::<f64>()
-}
-
-/// Returns a random real in [min,max).
-pub fn random_double_range(min: f64, max: f64) -> f64 {
-    min + (max - min) * random_double()
-}
-
 pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     if x < min {
         min
@@ -26,41 +14,3 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
         x
     }
 }
-
-pub fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = Vec3::random_range(-1.0, 1.0);
-        if p.length_squared() >= 1.0 {
-            continue;
-        }
-        return p;
-    }
-}
-
-pub fn random_unit_vector() -> Vec3 {
-    random_in_unit_sphere().unit_vector()
-}
-
-pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
-    let in_unit_sphere = random_in_unit_sphere();
-    if in_unit_sphere.dot(normal) > 0.0 {
-        // In the same hemisphere as the normal
-        in_unit_sphere
-    } else {
-        -in_unit_sphere
-    }
-}
-
-pub fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new([
-            random_double_range(-1.0, 1.0),
-            random_double_range(-1.0, 1.0),
-            0.0,
-        ]);
-        if p.length_squared() >= 1.0 {
-            continue;
-        }
-        return p;
-    }
-}
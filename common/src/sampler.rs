@@ -0,0 +1,214 @@
+//! 采样器
+
+mod blue_noise;
+mod halton;
+mod pmj02;
+mod sobol;
+mod stratified;
+
+pub use blue_noise::{BlueNoiseMask, BlueNoiseMask2D, BlueNoiseSampler};
+pub use halton::HaltonSampler;
+pub use pmj02::Pmj02Sampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+use crate::rng::{mix_bits, Rng};
+
+/// Largest f64 below one.
+pub const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// Source of the sample values of a path: the position inside the pixel,
+/// the lens position and every scattering decision.
+///
+/// Before each sample the renderer calls `start_pixel_sample`; the values
+/// returned afterwards must only depend on the pixel, the sample index, the
+/// dimension and the sampler's seed so renders are reproducible.
+pub trait Sampler: Send {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize);
+
+    /// Returns a sample in [0,1) and moves on to the next dimension.
+    fn get_1d(&mut self) -> f64;
+
+    /// Returns a sample in [0,1)^2 and moves on by two dimensions.
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    /// Offset of the sample within its pixel, the first dimensions used.
+    fn get_pixel_2d(&mut self) -> (f64, f64) {
+        self.get_2d()
+    }
+
+    /// Fresh sampler with the same configuration, e.g. one per thread.
+    fn clone_sampler(&self) -> Box<dyn Sampler>;
+}
+
+/// Names accepted by [`from_name`].
+pub const SAMPLER_NAMES: [&str; 6] = [
+    "random",
+    "stratified",
+    "halton",
+    "sobol",
+    "pmj02",
+    "blue-noise",
+];
+
+/// Create the sampler `name` for renders of `samples_per_pixel` samples.
+pub fn from_name(
+    name: &str,
+    samples_per_pixel: usize,
+    seed: u64,
+) -> Result<Box<dyn Sampler>, String> {
+    match name {
+        "random" => Ok(Box::new(RandomSampler::new(seed))),
+        "stratified" => Ok(Box::new(StratifiedSampler::new(
+            samples_per_pixel,
+            true,
+            seed,
+        ))),
+        "halton" => Ok(Box::new(HaltonSampler::new(seed))),
+        "sobol" => Ok(Box::new(SobolSampler::new(seed))),
+        "pmj02" => Ok(Box::new(Pmj02Sampler::new(samples_per_pixel, seed))),
+        "blue-noise" => Ok(Box::new(BlueNoiseSampler::new(seed))),
+        _ => Err(format!(
+            "unknown sampler: {} (expected one of {})",
+            name,
+            SAMPLER_NAMES.join(", ")
+        )),
+    }
+}
+
+/// Unique index of pixel `(i, j)`, independent of the image size.
+pub fn pixel_index(i: usize, j: usize) -> u64 {
+    ((j as u64) << 32) | i as u64
+}
+
+/// Hash identifying one dimension of one pixel, used to decorrelate the
+/// randomization between pixels and dimensions.
+pub fn sample_hash(seed: u64, i: usize, j: usize, dimension: u32) -> u64 {
+    mix_bits(mix_bits(seed ^ pixel_index(i, j)) ^ dimension as u64)
+}
+
+/// Element `i` of a random permutation of [0, l) selected by `p`
+/// (Kensler, "Correlated Multi-Jittered Sampling").
+pub fn permutation_element(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    ((i as u64 + p as u64) % l as u64) as u32
+}
+
+/// Map 32 bits to [0,1).
+pub(crate) fn u32_to_unit(x: u32) -> f64 {
+    x as f64 * (1.0 / 4294967296.0)
+}
+
+/// Independent uniform samples.
+pub struct RandomSampler {
+    seed: u64,
+    rng: Rng,
+}
+
+impl RandomSampler {
+    pub fn new(seed: u64) -> Self {
+        RandomSampler {
+            seed,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.rng = Rng::for_sample(self.seed, pixel_index(i, j), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.next_f64()
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(RandomSampler::new(self.seed))
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permutation_element() {
+        for &l in &[1u32, 5, 16, 30, 100] {
+            let mut seen = vec![false; l as usize];
+            for i in 0..l {
+                let e = permutation_element(i, l, 0x1234567);
+                assert!(!seen[e as usize]);
+                seen[e as usize] = true;
+            }
+        }
+    }
+
+    /// Every sampler must be reproducible and stay within [0,1).
+    #[test]
+    fn test_samplers() {
+        for name in SAMPLER_NAMES {
+            let mut a = from_name(name, 16, 3).unwrap();
+            let mut b = a.clone_sampler();
+            for s in 0..32 {
+                a.start_pixel_sample(5, 7, s);
+                b.start_pixel_sample(5, 7, s);
+                for _ in 0..20 {
+                    let x = a.get_1d();
+                    assert!((0.0..1.0).contains(&x), "{} {}", name, x);
+                    assert_eq!(x, b.get_1d());
+                    let (u, v) = a.get_2d();
+                    assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+                    assert_eq!((u, v), b.get_2d());
+                }
+            }
+        }
+    }
+
+    /// The pixel samples of the stratified and low discrepancy samplers put
+    /// exactly one of 16 samples into each cell of a 4x4 grid.
+    #[test]
+    fn test_pixel_stratification() {
+        for name in ["stratified", "sobol", "pmj02"] {
+            let mut sampler = from_name(name, 16, 9).unwrap();
+            let mut cells = [0; 16];
+            for s in 0..16 {
+                sampler.start_pixel_sample(2, 3, s);
+                let (u, v) = sampler.get_pixel_2d();
+                cells[(u * 4.0) as usize + 4 * (v * 4.0) as usize] += 1;
+            }
+            assert_eq!(cells, [1; 16], "{}", name);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use super::sobol::{nested_uniform_scramble, sobol_dimension1};
+use super::{sample_hash, u32_to_unit, Sampler, ONE_MINUS_EPSILON};
+use crate::rng::{mix_bits, Rng};
+use std::sync::Arc;
+
+/// Edge length of the blue-noise tile used by [`BlueNoiseSampler`].
+pub const MASK_SIZE: usize = 64;
+
+/// Tileable blue-noise dither mask: a ranking of the pixels of a toroidal
+/// `size` x `size` tile such that every threshold of it is a well spread
+/// point set, generated with Ulichney's void-and-cluster method.
+pub struct BlueNoiseMask {
+    pub size: usize,
+    ranks: Vec<u32>,
+}
+
+/// Toroidal Gaussian energy of a binary pattern.
+struct Energy {
+    size: usize,
+    radius: isize,
+    kernel: Vec<f64>,
+    energy: Vec<f64>,
+}
+
+impl Energy {
+    fn new(size: usize, sigma: f64) -> Self {
+        let radius = ((3.0 * sigma).ceil() as isize).min(size as isize / 2);
+        let width = (2 * radius + 1) as usize;
+        let mut kernel = vec![0.0; width * width];
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let r2 = (dx * dx + dy * dy) as f64;
+                kernel[(dy + radius) as usize * width + (dx + radius) as usize] =
+                    (-r2 / (2.0 * sigma * sigma)).exp();
+            }
+        }
+        Energy {
+            size,
+            radius,
+            kernel,
+            energy: vec![0.0; size * size],
+        }
+    }
+
+    /// Add (`sign` = 1) or remove (`sign` = -1) a point's contribution.
+    fn splat(&mut self, index: usize, sign: f64) {
+        let (x, y) = ((index % self.size) as isize, (index / self.size) as isize);
+        let width = (2 * self.radius + 1) as usize;
+        let n = self.size as isize;
+        for dy in -self.radius..=self.radius {
+            for dx in -self.radius..=self.radius {
+                let px = (x + dx).rem_euclid(n) as usize;
+                let py = (y + dy).rem_euclid(n) as usize;
+                let k =
+                    self.kernel[(dy + self.radius) as usize * width + (dx + self.radius) as usize];
+                self.energy[py * self.size + px] += sign * k;
+            }
+        }
+    }
+
+    /// Pixel set to `value` in `pattern` with the highest (or lowest) energy.
+    fn extreme(&self, pattern: &[bool], value: bool, highest: bool) -> usize {
+        let mut best = usize::MAX;
+        for (index, &set) in pattern.iter().enumerate() {
+            if set != value {
+                continue;
+            }
+            if best == usize::MAX
+                || (highest && self.energy[index] > self.energy[best])
+                || (!highest && self.energy[index] < self.energy[best])
+            {
+                best = index;
+            }
+        }
+        best
+    }
+}
+
+impl BlueNoiseMask {
+    pub fn generate(size: usize, seed: u64) -> Self {
+        let n = size * size;
+        let sigma = 1.5;
+        let mut rng = Rng::new(seed);
+
+        // Initial binary pattern: about a tenth of the pixels, at random.
+        let mut pattern = vec![false; n];
+        let mut energy = Energy::new(size, sigma);
+        let ones = (n / 10).max(1);
+        let mut placed = 0;
+        while placed < ones {
+            let index = rng.next_u32() as usize % n;
+            if !pattern[index] {
+                pattern[index] = true;
+                energy.splat(index, 1.0);
+                placed += 1;
+            }
+        }
+
+        // Spread it out: move the point of the tightest cluster into the
+        // largest void until that no longer changes anything.
+        for _ in 0..n {
+            let cluster = energy.extreme(&pattern, true, true);
+            pattern[cluster] = false;
+            energy.splat(cluster, -1.0);
+            let void = energy.extreme(&pattern, false, false);
+            pattern[void] = true;
+            energy.splat(void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0usize; n];
+
+        // Ranks below the initial pattern: remove tightest clusters.
+        let mut prototype = pattern.clone();
+        let mut prototype_energy = Energy::new(size, sigma);
+        prototype_energy.energy.clone_from(&energy.energy);
+        for r in (0..ones).rev() {
+            let cluster = prototype_energy.extreme(&prototype, true, true);
+            prototype[cluster] = false;
+            prototype_energy.splat(cluster, -1.0);
+            rank[cluster] = r;
+        }
+
+        // Ranks above it: fill the largest voids. Past half coverage this is
+        // the same as picking the tightest cluster of the empty pixels, as
+        // their energy is the constant total minus that of the set pixels.
+        for r in ones..n {
+            let void = energy.extreme(&pattern, false, false);
+            pattern[void] = true;
+            energy.splat(void, 1.0);
+            rank[void] = r;
+        }
+
+        BlueNoiseMask {
+            size,
+            ranks: rank.iter().map(|&r| r as u32).collect(),
+        }
+    }
+
+    /// Rank in [0, size^2) of pixel `(x, y)`, tiled over the plane.
+    pub fn rank(&self, x: usize, y: usize) -> u32 {
+        self.ranks[(y % self.size) * self.size + x % self.size]
+    }
+
+    /// Dither value in (0,1) of pixel `(x, y)`.
+    pub fn value(&self, x: usize, y: usize) -> f64 {
+        (self.rank(x, y) as f64 + 0.5) / (self.size * self.size) as f64
+    }
+}
+
+/// Tileable blue-noise mask of 2D values: every pixel of a toroidal `size`
+/// x `size` tile holds a point of [0,1)^2, and nearby pixels hold distant
+/// points. Generated by Georgiev and Fajardo's method, swapping the values
+/// of pixel pairs as long as that lowers the energy of the tile.
+pub struct BlueNoiseMask2D {
+    pub size: usize,
+    values: Vec<(f64, f64)>,
+}
+
+/// Pixel radius of the energy of [`BlueNoiseMask2D`].
+const MASK_2D_RADIUS: isize = 3;
+
+impl BlueNoiseMask2D {
+    pub fn generate(size: usize, seed: u64) -> Self {
+        let n = size * size;
+        let mut rng = Rng::new(seed);
+
+        // Start from a jittered grid of values, which is as uniform as the
+        // result has to be, assigned to the pixels at random.
+        let mut values: Vec<(f64, f64)> = (0..n)
+            .map(|k| {
+                (
+                    ((k % size) as f64 + rng.next_f64()) / size as f64,
+                    ((k / size) as f64 + rng.next_f64()) / size as f64,
+                )
+            })
+            .collect();
+        for k in (1..n).rev() {
+            values.swap(k, rng.next_u32() as usize % (k + 1));
+        }
+
+        // Spatial weights exp(-d^2 / sigma_i^2), sigma_i = 2.1.
+        let width = (2 * MASK_2D_RADIUS + 1) as usize;
+        let mut weights = vec![0.0; width * width];
+        for dy in -MASK_2D_RADIUS..=MASK_2D_RADIUS {
+            for dx in -MASK_2D_RADIUS..=MASK_2D_RADIUS {
+                let k = (dy + MASK_2D_RADIUS) as usize * width + (dx + MASK_2D_RADIUS) as usize;
+                weights[k] = (-((dx * dx + dy * dy) as f64) / (2.1 * 2.1)).exp();
+            }
+        }
+        let torus = |a: f64, b: f64| {
+            let d = (a - b).abs();
+            d.min(1.0 - d)
+        };
+        let energy = |values: &[(f64, f64)], index: usize| {
+            let (x, y) = ((index % size) as isize, (index / size) as isize);
+            let (u, v) = values[index];
+            let mut e = 0.0;
+            for dy in -MASK_2D_RADIUS..=MASK_2D_RADIUS {
+                for dx in -MASK_2D_RADIUS..=MASK_2D_RADIUS {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    let px = (x + dx).rem_euclid(size as isize) as usize;
+                    let py = (y + dy).rem_euclid(size as isize) as usize;
+                    let (qu, qv) = values[py * size + px];
+                    let (du, dv) = (torus(u, qu), torus(v, qv));
+                    let w = weights
+                        [(dy + MASK_2D_RADIUS) as usize * width + (dx + MASK_2D_RADIUS) as usize];
+                    // Value distance to the power d/2 = 1, over sigma_s =
+                    // 0.1: much larger and the energy is almost linear in
+                    // the distance, which favors antipodal neighbors.
+                    e += w * (-(du * du + dv * dv).sqrt() / 0.1).exp();
+                }
+            }
+            e
+        };
+
+        for _ in 0..n * 64 {
+            let a = rng.next_u32() as usize % n;
+            let b = rng.next_u32() as usize % n;
+            if a == b {
+                continue;
+            }
+            let before = energy(&values, a) + energy(&values, b);
+            values.swap(a, b);
+            if energy(&values, a) + energy(&values, b) >= before {
+                values.swap(a, b);
+            }
+        }
+
+        BlueNoiseMask2D { size, values }
+    }
+
+    /// Value of pixel `(x, y)`, tiled over the plane.
+    pub fn value(&self, x: usize, y: usize) -> (f64, f64) {
+        self.values[(y % self.size) * self.size + x % self.size]
+    }
+}
+
+/// Blue-noise dithered sampling for low sample counts.
+///
+/// Every pixel uses the same globally Owen-scrambled Sobol points, rotated
+/// toroidally by the value of the pixel in a blue-noise mask (a scalar mask
+/// for 1D samples and a 2D one for 2D samples). Nearby pixels are rotated by
+/// very different amounts, so their errors cancel locally and are pushed to
+/// high spatial frequencies. Every dimension looks up the masks with a
+/// different toroidal offset so the dimensions stay uncorrelated.
+pub struct BlueNoiseSampler {
+    seed: u64,
+    mask: Arc<BlueNoiseMask>,
+    mask_2d: Arc<BlueNoiseMask2D>,
+    pixel: (usize, usize),
+    sample_index: usize,
+    dimension: u32,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64) -> Self {
+        BlueNoiseSampler::with_masks(
+            Arc::new(BlueNoiseMask::generate(MASK_SIZE, seed)),
+            Arc::new(BlueNoiseMask2D::generate(MASK_SIZE, seed)),
+            seed,
+        )
+    }
+
+    pub fn with_masks(mask: Arc<BlueNoiseMask>, mask_2d: Arc<BlueNoiseMask2D>, seed: u64) -> Self {
+        BlueNoiseSampler {
+            seed,
+            mask,
+            mask_2d,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Mask lookup position and scrambling hash of the current dimension.
+    /// The hash is the same for every pixel.
+    fn lookup(&self) -> ((usize, usize), u64) {
+        let hash = sample_hash(self.seed, 0, 0, self.dimension);
+        let shift = mix_bits(hash);
+        (
+            (
+                self.pixel.0 + (shift as u32) as usize,
+                self.pixel.1 + (shift >> 32) as usize,
+            ),
+            hash,
+        )
+    }
+}
+
+/// `a + b` wrapped to [0,1).
+fn rotate(a: f64, b: f64) -> f64 {
+    let x = a + b;
+    if x >= 1.0 {
+        (x - 1.0).min(ONE_MINUS_EPSILON)
+    } else {
+        x
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.pixel = (i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let ((x, y), hash) = self.lookup();
+        self.dimension += 1;
+        let index = self.sample_index as u32;
+        let u = u32_to_unit(nested_uniform_scramble(index.reverse_bits(), hash as u32));
+        rotate(u, self.mask.value(x, y))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let ((x, y), hash) = self.lookup();
+        self.dimension += 2;
+        let index = self.sample_index as u32;
+        let u = u32_to_unit(nested_uniform_scramble(index.reverse_bits(), hash as u32));
+        let v = u32_to_unit(nested_uniform_scramble(
+            sobol_dimension1(index),
+            (hash >> 32) as u32,
+        ));
+        let (du, dv) = self.mask_2d.value(x, y);
+        (rotate(u, du), rotate(v, dv))
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(BlueNoiseSampler {
+            seed: self.seed,
+            mask: self.mask.clone(),
+            mask_2d: self.mask_2d.clone(),
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        })
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_is_a_ranking() {
+        let mask = BlueNoiseMask::generate(16, 1);
+        let mut ranks: Vec<u32> = (0..16 * 16).map(|i| mask.rank(i % 16, i / 16)).collect();
+        ranks.sort_unstable();
+        assert_eq!(ranks, (0..256).collect::<Vec<_>>());
+    }
+
+    /// The darkest eighth of the mask has no two pixels next to each other.
+    #[test]
+    fn test_mask_is_spread_out() {
+        let size = 32;
+        let mask = BlueNoiseMask::generate(size, 2);
+        for y in 0..size {
+            for x in 0..size {
+                if mask.value(x, y) >= 0.125 {
+                    continue;
+                }
+                for (dx, dy) in [(1, 0), (0, 1), (1, 1), (size - 1, 1)] {
+                    assert!(mask.value(x + dx, y + dy) >= 0.125, "{} {}", x, y);
+                }
+            }
+        }
+    }
+
+    /// Neighboring values of the 2D mask are further apart than random pairs.
+    #[test]
+    fn test_mask_2d_is_spread_out() {
+        let size = 16;
+        let mask = BlueNoiseMask2D::generate(size, 3);
+        let torus = |a: f64, b: f64| (a - b).abs().min(1.0 - (a - b).abs());
+        let distance = |(u0, v0): (f64, f64), (u1, v1): (f64, f64)| {
+            (torus(u0, u1).powi(2) + torus(v0, v1).powi(2)).sqrt()
+        };
+        let mut mean = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                mean += distance(mask.value(x, y), mask.value(x + 1, y));
+                mean += distance(mask.value(x, y), mask.value(x, y + 1));
+            }
+        }
+        mean /= (2 * size * size) as f64;
+        // Random pairs on the torus are about 0.38 apart on average.
+        assert!(mean > 0.41, "{}", mean);
+    }
+}
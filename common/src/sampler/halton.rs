@@ -0,0 +1,87 @@
+use super::sobol::nested_uniform_scramble;
+use super::{
+    permutation_element, pixel_index, sample_hash, u32_to_unit, Sampler, ONE_MINUS_EPSILON,
+};
+use crate::rng::{mix_bits, Rng};
+
+/// Bases of the Halton dimensions; later dimensions fall back to
+/// independent random numbers.
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Radical inverse of `a` in `base` with every digit permuted depending on
+/// the digits before it, i.e. Owen scrambling.
+///
+/// The scrambled digits are computed for all digits of `a` but at least down
+/// to a resolution of 1/65536; the remaining digits of an Owen scrambled
+/// point are uniformly random given that prefix, so they are filled in with
+/// one hashed value instead of digit by digit.
+pub fn owen_scrambled_radical_inverse(base: u64, mut a: u64, hash: u64) -> f64 {
+    if base == 2 {
+        let bits = nested_uniform_scramble((a as u32).reverse_bits(), hash as u32);
+        return u32_to_unit(bits);
+    }
+
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut reversed_digits: u128 = 0;
+    while a != 0 || inv_base_m > 1.0 / 65536.0 {
+        let next = a / base;
+        let digit = a - next * base;
+        let digit_hash = mix_bits(hash ^ reversed_digits as u64);
+        let digit = permutation_element(digit as u32, base as u32, digit_hash as u32) as u64;
+        reversed_digits = reversed_digits * base as u128 + digit as u128;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+    let tail = u32_to_unit(mix_bits(!hash ^ reversed_digits as u64) as u32);
+    ((reversed_digits as f64 + tail) * inv_base_m).min(ONE_MINUS_EPSILON)
+}
+
+/// Owen-scrambled Halton sequence, scrambled independently for every pixel.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    sample_index: usize,
+    dimension: u32,
+    rng: Rng,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.pixel = (i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng = Rng::for_sample(self.seed, pixel_index(i, j), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.dimension as usize;
+        self.dimension += 1;
+        if dimension >= PRIMES.len() {
+            return self.rng.next_f64();
+        }
+        let hash = sample_hash(self.seed, self.pixel.0, self.pixel.1, dimension as u32);
+        owen_scrambled_radical_inverse(PRIMES[dimension], self.sample_index as u64, hash)
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(HaltonSampler::new(self.seed))
+    }
+}
@@ -0,0 +1,286 @@
+use super::{sample_hash, u32_to_unit, Sampler};
+use crate::rng::{mix_bits, Rng};
+use std::sync::Arc;
+
+/// Number of independently generated sample sets pixels choose from.
+const SETS: usize = 32;
+
+/// Which cells of the elementary intervals of `2^m` points are taken.
+///
+/// Interval shape `a` has `2^a` columns and `2^(m-a)` rows. A point is fully
+/// described by its column and row in the finest `2^m` x `2^m` grid.
+struct Occupancy {
+    m: u32,
+    cells: Vec<Vec<bool>>,
+}
+
+impl Occupancy {
+    fn new(m: u32) -> Self {
+        Occupancy {
+            m,
+            cells: vec![vec![false; 1 << m]; m as usize + 1],
+        }
+    }
+
+    fn index(&self, a: u32, col: usize, row: usize) -> usize {
+        let cx = col >> (self.m - a);
+        let cy = row >> a;
+        (cy << a) + cx
+    }
+
+    fn is_free(&self, col: usize, row: usize) -> bool {
+        (0..=self.m).all(|a| !self.cells[a as usize][self.index(a, col, row)])
+    }
+
+    fn mark(&mut self, col: usize, row: usize) {
+        for a in 0..=self.m {
+            let index = self.index(a, col, row);
+            self.cells[a as usize][index] = true;
+        }
+    }
+
+    fn grid_cell(&self, x: f64) -> usize {
+        (x * (1usize << self.m) as f64) as usize
+    }
+}
+
+/// Generate one sample in cell `(cx, cy)` of a `cells` x `cells` grid that
+/// keeps all points in distinct elementary intervals.
+fn generate_sample(
+    occupancy: &mut Occupancy,
+    cx: usize,
+    cy: usize,
+    cells: usize,
+    rng: &mut Rng,
+) -> Option<(f64, f64)> {
+    let fine = 1usize << occupancy.m;
+    let per_cell = fine / cells;
+    let free_cols: Vec<usize> = (cx * per_cell..(cx + 1) * per_cell)
+        .filter(|&col| !occupancy.cells[occupancy.m as usize][occupancy.index(occupancy.m, col, 0)])
+        .collect();
+    let free_rows: Vec<usize> = (cy * per_cell..(cy + 1) * per_cell)
+        .filter(|&row| !occupancy.cells[0][occupancy.index(0, 0, row)])
+        .collect();
+    if free_cols.is_empty() || free_rows.is_empty() {
+        return None;
+    }
+
+    // Try the candidate pairs in random order.
+    let col_start = (rng.next_u32() as usize) % free_cols.len();
+    let row_start = (rng.next_u32() as usize) % free_rows.len();
+    for dc in 0..free_cols.len() {
+        for dr in 0..free_rows.len() {
+            let col = free_cols[(col_start + dc) % free_cols.len()];
+            let row = free_rows[(row_start + dr) % free_rows.len()];
+            if occupancy.is_free(col, row) {
+                occupancy.mark(col, row);
+                return Some((
+                    (col as f64 + rng.next_f64()) / fine as f64,
+                    (row as f64 + rng.next_f64()) / fine as f64,
+                ));
+            }
+        }
+    }
+    None
+}
+
+fn occupancy_for(samples: &[(f64, f64)], total: usize) -> Occupancy {
+    let mut occupancy = Occupancy::new(total.trailing_zeros());
+    for &(x, y) in samples {
+        let (col, row) = (occupancy.grid_cell(x), occupancy.grid_cell(y));
+        occupancy.mark(col, row);
+    }
+    occupancy
+}
+
+/// Sub-quadrant cell of `(x, y)` in a grid twice as fine as `n` x `n`.
+fn subcell(x: f64, y: f64, n: usize) -> (usize, usize) {
+    ((x * 2.0 * n as f64) as usize, (y * 2.0 * n as f64) as usize)
+}
+
+/// Double `count = 4^k` samples, each new one diagonally opposite its parent.
+fn extend_even(samples: &mut Vec<(f64, f64)>, count: usize, rng: &mut Rng) -> Option<()> {
+    let n = (count as f64).sqrt() as usize;
+    let mut occupancy = occupancy_for(samples, 2 * count);
+    for s in 0..count {
+        let (x, y) = samples[s];
+        let (sx, sy) = subcell(x, y, n);
+        let sample = generate_sample(&mut occupancy, sx ^ 1, sy ^ 1, 2 * n, rng)?;
+        samples.push(sample);
+    }
+    Some(())
+}
+
+/// Double `count = 2 * 4^k` samples, filling the two sub-quadrants of every
+/// cell not taken by the two samples already in it.
+fn extend_odd(samples: &mut Vec<(f64, f64)>, count: usize, rng: &mut Rng) -> Option<()> {
+    let n = ((count / 2) as f64).sqrt() as usize;
+    let mut occupancy = occupancy_for(samples, 2 * count);
+    let mut second = Vec::with_capacity(count / 2);
+    for s in 0..count / 2 {
+        let (x, y) = samples[s];
+        let (sx, sy) = subcell(x, y, n);
+        let (first, other) = if rng.next_u32() & 1 == 0 {
+            ((sx ^ 1, sy), (sx, sy ^ 1))
+        } else {
+            ((sx, sy ^ 1), (sx ^ 1, sy))
+        };
+        samples.push(generate_sample(
+            &mut occupancy,
+            first.0,
+            first.1,
+            2 * n,
+            rng,
+        )?);
+        second.push(other);
+    }
+    for (sx, sy) in second {
+        samples.push(generate_sample(&mut occupancy, sx, sy, 2 * n, rng)?);
+    }
+    Some(())
+}
+
+/// Progressive multi-jittered (0,2) sequence of `n` (a power of two) points
+/// (Christensen, Kensler and Kilpatrick 2018): every power of two prefix is
+/// stratified in all elementary intervals.
+pub fn pmj02_samples(n: usize, rng: &mut Rng) -> Vec<(f64, f64)> {
+    'restart: loop {
+        let mut samples = Vec::with_capacity(n);
+        samples.push((rng.next_f64(), rng.next_f64()));
+        let mut count = 1;
+        while count < n {
+            if extend_even(&mut samples, count, rng).is_none() {
+                continue 'restart;
+            }
+            if 2 * count < n && extend_odd(&mut samples, 2 * count, rng).is_none() {
+                continue 'restart;
+            }
+            count *= 4;
+        }
+        samples.truncate(n);
+        return samples;
+    }
+}
+
+/// Samples from precomputed pmj02 sets. Each pixel and dimension picks a set,
+/// visits its first `samples_per_pixel` points in order and applies a random
+/// digital shift, which keeps the stratification. Visiting them in order
+/// keeps every power of two prefix stratified too, as progressive and
+/// adaptive renders take prefixes.
+pub struct Pmj02Sampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    sets: Arc<Vec<Vec<(f64, f64)>>>,
+    pixel: (usize, usize),
+    sample_index: usize,
+    dimension: u32,
+}
+
+impl Pmj02Sampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let n = samples_per_pixel.next_power_of_two();
+        let sets = (0..SETS)
+            .map(|set| pmj02_samples(n, &mut Rng::with_stream(seed, set as u64)))
+            .collect();
+        Pmj02Sampler {
+            samples_per_pixel,
+            seed,
+            sets: Arc::new(sets),
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample(&self) -> (f64, f64) {
+        let hash = sample_hash(self.seed, self.pixel.0, self.pixel.1, self.dimension);
+        let count = self.samples_per_pixel;
+        let round = (self.sample_index / count) as u64;
+        let set = &self.sets[(hash ^ round) as usize % SETS];
+        let (x, y) = set[self.sample_index % count];
+        let shift = mix_bits(hash ^ round);
+        (
+            u32_to_unit((x * 4294967296.0) as u32 ^ shift as u32),
+            u32_to_unit((y * 4294967296.0) as u32 ^ (shift >> 32) as u32),
+        )
+    }
+}
+
+impl Sampler for Pmj02Sampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.pixel = (i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (x, _) = self.sample();
+        self.dimension += 1;
+        x
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let sample = self.sample();
+        self.dimension += 2;
+        sample
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(Pmj02Sampler {
+            samples_per_pixel: self.samples_per_pixel,
+            seed: self.seed,
+            sets: self.sets.clone(),
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        })
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elementary_intervals() {
+        let samples = pmj02_samples(256, &mut Rng::new(5));
+        for m in 0..=8u32 {
+            let prefix = &samples[..1 << m];
+            for a in 0..=m {
+                let mut cells = vec![false; 1 << m];
+                for &(x, y) in prefix {
+                    let cx = (x * (1u64 << a) as f64) as usize;
+                    let cy = (y * (1u64 << (m - a)) as f64) as usize;
+                    let cell = (cy << a) + cx;
+                    assert!(!cells[cell], "m {} a {}", m, a);
+                    cells[cell] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixel_prefixes_stratified() {
+        let mut sampler = Pmj02Sampler::new(64, 9);
+        for (i, j) in [(0, 0), (5, 3), (100, 41)] {
+            let samples: Vec<_> = (0..64)
+                .map(|s| {
+                    sampler.start_pixel_sample(i, j, s);
+                    sampler.get_2d()
+                })
+                .collect();
+            for m in 0..=6u32 {
+                let mut cells = vec![false; 1 << m];
+                for &(x, y) in &samples[..1 << m] {
+                    let cx = (x * (1u64 << (m / 2)) as f64) as usize;
+                    let cy = (y * (1u64 << (m - m / 2)) as f64) as usize;
+                    let cell = (cy << (m / 2)) + cx;
+                    assert!(!cells[cell], "pixel ({}, {}) m {}", i, j, m);
+                    cells[cell] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::{sample_hash, u32_to_unit, Sampler};
+use crate::rng::mix_bits;
+
+/// Second dimension of the Sobol sequence; the first is `index.reverse_bits()`.
+pub(super) fn sobol_dimension1(mut index: u32) -> u32 {
+    let mut v: u32 = 1 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Hash based Owen scrambling of the bits of `x` (Burley, "Practical
+/// Hash-based Owen Scrambling").
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Owen-scrambled Sobol samples. Every pair of dimensions is a shuffled,
+/// independently scrambled copy of the 2D Sobol (0,2)-sequence ("padded"
+/// Sobol), so any number of dimensions is supported.
+pub struct SobolSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    sample_index: usize,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn hash(&self) -> u64 {
+        sample_hash(self.seed, self.pixel.0, self.pixel.1, self.dimension)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.pixel = (i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let hash = self.hash();
+        self.dimension += 1;
+        let index = nested_uniform_scramble(self.sample_index as u32, hash as u32);
+        u32_to_unit(nested_uniform_scramble(
+            index.reverse_bits(),
+            (hash >> 32) as u32,
+        ))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let hash = self.hash();
+        self.dimension += 2;
+        let index = nested_uniform_scramble(self.sample_index as u32, hash as u32);
+        let x = nested_uniform_scramble(index.reverse_bits(), (hash >> 32) as u32);
+        let y = nested_uniform_scramble(sobol_dimension1(index), mix_bits(hash) as u32);
+        (u32_to_unit(x), u32_to_unit(y))
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(SobolSampler::new(self.seed))
+    }
+}
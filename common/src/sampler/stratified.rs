@@ -0,0 +1,94 @@
+use super::{permutation_element, pixel_index, sample_hash, Sampler};
+use crate::rng::Rng;
+
+/// Jittered stratification of every dimension: the samples of a pixel fall
+/// into distinct 1D strata (or cells of a 2D grid), and the strata are
+/// visited in a different random order for every pixel and dimension.
+pub struct StratifiedSampler {
+    samples_per_pixel: usize,
+    x_strata: usize,
+    y_strata: usize,
+    jitter: bool,
+    seed: u64,
+    pixel: (usize, usize),
+    sample_index: usize,
+    dimension: u32,
+    rng: Rng,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, jitter: bool, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        // Most square grid with exactly samples_per_pixel cells.
+        let mut x_strata = (samples_per_pixel as f64).sqrt() as usize;
+        while !samples_per_pixel.is_multiple_of(x_strata) {
+            x_strata -= 1;
+        }
+        StratifiedSampler {
+            samples_per_pixel,
+            x_strata,
+            y_strata: samples_per_pixel / x_strata,
+            jitter,
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Stratum of the current sample in the current dimension. Samples past
+    /// `samples_per_pixel` start another, differently permuted round.
+    fn stratum(&self) -> u32 {
+        let count = self.samples_per_pixel;
+        let round = (self.sample_index / count) as u64;
+        let hash = sample_hash(self.seed, self.pixel.0, self.pixel.1, self.dimension) ^ round;
+        permutation_element(
+            (self.sample_index % count) as u32,
+            count as u32,
+            hash as u32,
+        )
+    }
+
+    fn offset(&mut self) -> f64 {
+        if self.jitter {
+            self.rng.next_f64()
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, sample_index: usize) {
+        self.pixel = (i, j);
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng = Rng::for_sample(self.seed, pixel_index(i, j), sample_index as u64);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let stratum = self.stratum();
+        self.dimension += 1;
+        (stratum as f64 + self.offset()) / self.samples_per_pixel as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let stratum = self.stratum() as usize;
+        self.dimension += 2;
+        let x = stratum % self.x_strata;
+        let y = stratum / self.x_strata;
+        (
+            (x as f64 + self.offset()) / self.x_strata as f64,
+            (y as f64 + self.offset()) / self.y_strata as f64,
+        )
+    }
+
+    fn clone_sampler(&self) -> Box<dyn Sampler> {
+        Box::new(StratifiedSampler::new(
+            self.samples_per_pixel,
+            self.jitter,
+            self.seed,
+        ))
+    }
+}
@@ -0,0 +1,117 @@
+//! 采样变换: 把 [0,1)^n 的样本映射到几何分布
+
+use crate::Vector3;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+/// Uniform point in the unit disk (z = 0), using Shirley's concentric
+/// mapping so stratification of `u` carries over to the disk.
+pub fn sample_unit_disk(u: (f64, f64)) -> Vector3 {
+    let (ox, oy) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if ox == 0.0 && oy == 0.0 {
+        return Vector3::new0();
+    }
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, FRAC_PI_4 * (oy / ox))
+    } else {
+        (oy, FRAC_PI_2 - FRAC_PI_4 * (ox / oy))
+    };
+    Vector3::new([r * theta.cos(), r * theta.sin(), 0.0])
+}
+
+/// Uniform point in the regular polygon with `sides` corners on the unit
+/// circle, the first at angle `rotation` (z = 0).
+pub fn sample_regular_polygon(u: (f64, f64), sides: usize, rotation: f64) -> Vector3 {
+    // Pick one of the triangles between the center and two corners, then a
+    // uniform point in it.
+    let x = u.0 * sides as f64;
+    let k = (x as usize).min(sides - 1);
+    let (a, b) = ((x - k as f64).sqrt(), u.1);
+    let corner = |i: usize| {
+        let angle = rotation + 2.0 * PI * i as f64 / sides as f64;
+        Vector3::new([angle.cos(), angle.sin(), 0.0])
+    };
+    a * ((1.0 - b) * corner(k) + b * corner(k + 1))
+}
+
+/// Cosine-weighted direction in the hemisphere around +z, by projecting a
+/// uniform point on the unit disk up to the hemisphere (Malley's method).
+pub fn sample_cosine_hemisphere(u: (f64, f64)) -> Vector3 {
+    let d = sample_unit_disk(u);
+    let z = (1.0 - d.x * d.x - d.y * d.y).max(0.0).sqrt();
+    Vector3::new([d.x, d.y, z])
+}
+
+/// Uniform direction on the unit sphere.
+pub fn sample_unit_sphere(u: (f64, f64)) -> Vector3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vector3::new([r * phi.cos(), r * phi.sin(), z])
+}
+
+/// Uniform point inside the unit sphere.
+pub fn sample_unit_ball(u: (f64, f64), u_radius: f64) -> Vector3 {
+    u_radius.cbrt() * sample_unit_sphere(u)
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_disk() {
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.999, 0.1), (0.25, 0.75)] {
+            let p = sample_unit_disk(u);
+            assert!(p.length_squared() <= 1.0 + 1e-12);
+            assert_eq!(p.z, 0.0);
+        }
+        assert_eq!(sample_unit_disk((0.5, 0.5)), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_regular_polygon() {
+        // A square with corners on the axes: |x| + |y| <= 1.
+        for &u in &[
+            (0.0, 0.0),
+            (0.5, 0.5),
+            (0.999, 0.1),
+            (0.25, 0.75),
+            (0.6, 0.99),
+        ] {
+            let p = sample_regular_polygon(u, 4, 0.0);
+            assert!(p.x.abs() + p.y.abs() <= 1.0 + 1e-12);
+            assert_eq!(p.z, 0.0);
+        }
+        let corner = sample_regular_polygon((1.0 / 6.0 - 1e-9, 0.0), 6, FRAC_PI_2);
+        assert!((corner - Vector3::new([0.0, 1.0, 0.0])).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_cosine_hemisphere() {
+        // The mean of cos(theta) under a cosine distribution is 2/3; the
+        // grid converges slowly near the rim where z has a square root.
+        let n = 64;
+        let mut sum = 0.0;
+        for i in 0..n * n {
+            let u = (
+                ((i % n) as f64 + 0.5) / n as f64,
+                ((i / n) as f64 + 0.5) / n as f64,
+            );
+            let d = sample_cosine_hemisphere(u);
+            assert!((d.length() - 1.0).abs() < 1e-12);
+            assert!(d.z >= 0.0);
+            sum += d.z;
+        }
+        let mean = sum / (n * n) as f64;
+        assert!((mean - 2.0 / 3.0).abs() < 2e-3, "{}", mean);
+    }
+
+    #[test]
+    fn test_unit_sphere() {
+        for &u in &[(0.0, 0.0), (0.5, 0.5), (0.999, 0.1), (0.25, 0.75)] {
+            assert!((sample_unit_sphere(u).length() - 1.0).abs() < 1e-12);
+            assert!(sample_unit_ball(u, 0.5).length() < 1.0);
+        }
+    }
+}
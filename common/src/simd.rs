@@ -0,0 +1,608 @@
+//! 四路 SIMD 向量
+
+use crate::float::Float;
+use crate::vec3::Vector3;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use std::arch::x86_64::*;
+
+/// Four lanes of a [`Float`], which every operation works on at once. The
+/// operations are `#[inline]` for the loops of other crates to keep them in
+/// registers.
+pub trait Lanes:
+    Copy
+    + Debug
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    type Scalar: Float;
+
+    fn new(lanes: [Self::Scalar; 4]) -> Self;
+    /// `value` in every lane.
+    fn splat(value: Self::Scalar) -> Self;
+    fn to_array(self) -> [Self::Scalar; 4];
+    fn sqrt(self) -> Self;
+    /// Like `minps`: `rhs` where either lane is NaN.
+    fn min(self, rhs: Self) -> Self;
+    /// Like `maxps`: `rhs` where either lane is NaN.
+    fn max(self, rhs: Self) -> Self;
+    /// Bit `i` is set if lane `i` is less than that of `rhs`.
+    fn lt_mask(self, rhs: Self) -> u32;
+    /// Lanes of `a` where bit `i` of `mask` is set, of `b` elsewhere.
+    fn select(mask: u32, a: Self, b: Self) -> Self;
+}
+
+/// Four `f32` lanes. With the `simd` feature on x86-64 they live in an SSE
+/// register, which every x86-64 CPU has; elsewhere in an array the compiler
+/// may vectorize.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[derive(Clone, Copy)]
+pub struct F32x4(__m128);
+
+/// Four `f32` lanes. With the `simd` feature on x86-64 they live in an SSE
+/// register, which every x86-64 CPU has; elsewhere in an array the compiler
+/// may vectorize.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+#[derive(Clone, Copy)]
+#[repr(align(16))]
+pub struct F32x4([f32; 4]);
+
+/// Four `f64` lanes, in two SSE2 registers with the `simd` feature on x86-64
+/// and in an array elsewhere.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[derive(Clone, Copy)]
+pub struct F64x4([__m128d; 2]);
+
+/// Four `f64` lanes, in two SSE2 registers with the `simd` feature on x86-64
+/// and in an array elsewhere.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+#[derive(Clone, Copy)]
+#[repr(align(32))]
+pub struct F64x4([f64; 4]);
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+impl Lanes for F32x4 {
+    type Scalar = f32;
+
+    #[inline]
+    fn new(lanes: [f32; 4]) -> Self {
+        // SAFETY: SSE is part of x86-64; unaligned loads accept any address.
+        F32x4(unsafe { _mm_loadu_ps(lanes.as_ptr()) })
+    }
+
+    #[inline]
+    fn splat(value: f32) -> Self {
+        // SAFETY: SSE is part of x86-64.
+        F32x4(unsafe { _mm_set1_ps(value) })
+    }
+
+    #[inline]
+    fn to_array(self) -> [f32; 4] {
+        let mut lanes = [0.0; 4];
+        // SAFETY: SSE is part of x86-64; unaligned stores accept any address.
+        unsafe { _mm_storeu_ps(lanes.as_mut_ptr(), self.0) };
+        lanes
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        // SAFETY: SSE is part of x86-64.
+        F32x4(unsafe { _mm_sqrt_ps(self.0) })
+    }
+
+    #[inline]
+    fn min(self, rhs: Self) -> Self {
+        // SAFETY: SSE is part of x86-64.
+        F32x4(unsafe { _mm_min_ps(self.0, rhs.0) })
+    }
+
+    #[inline]
+    fn max(self, rhs: Self) -> Self {
+        // SAFETY: SSE is part of x86-64.
+        F32x4(unsafe { _mm_max_ps(self.0, rhs.0) })
+    }
+
+    #[inline]
+    fn lt_mask(self, rhs: Self) -> u32 {
+        // SAFETY: SSE is part of x86-64.
+        unsafe { _mm_movemask_ps(_mm_cmplt_ps(self.0, rhs.0)) as u32 }
+    }
+
+    #[inline]
+    fn select(mask: u32, a: Self, b: Self) -> Self {
+        let bit = |i: u32| if mask & (1 << i) != 0 { -1 } else { 0 };
+        // SAFETY: SSE and SSE2 are part of x86-64.
+        unsafe {
+            let m = _mm_castsi128_ps(_mm_set_epi32(bit(3), bit(2), bit(1), bit(0)));
+            F32x4(_mm_or_ps(_mm_and_ps(m, a.0), _mm_andnot_ps(m, b.0)))
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+impl Lanes for F64x4 {
+    type Scalar = f64;
+
+    #[inline]
+    fn new(lanes: [f64; 4]) -> Self {
+        // SAFETY: SSE2 is part of x86-64; unaligned loads accept any address.
+        F64x4(unsafe {
+            [
+                _mm_loadu_pd(lanes.as_ptr()),
+                _mm_loadu_pd(lanes.as_ptr().add(2)),
+            ]
+        })
+    }
+
+    #[inline]
+    fn splat(value: f64) -> Self {
+        // SAFETY: SSE2 is part of x86-64.
+        F64x4(unsafe { [_mm_set1_pd(value); 2] })
+    }
+
+    #[inline]
+    fn to_array(self) -> [f64; 4] {
+        let mut lanes = [0.0; 4];
+        // SAFETY: SSE2 is part of x86-64; unaligned stores accept any address.
+        unsafe {
+            _mm_storeu_pd(lanes.as_mut_ptr(), self.0[0]);
+            _mm_storeu_pd(lanes.as_mut_ptr().add(2), self.0[1]);
+        }
+        lanes
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        // SAFETY: SSE2 is part of x86-64.
+        F64x4(self.0.map(|half| unsafe { _mm_sqrt_pd(half) }))
+    }
+
+    #[inline]
+    fn min(self, rhs: Self) -> Self {
+        // SAFETY: SSE2 is part of x86-64.
+        self.zip(rhs, |a, b| unsafe { _mm_min_pd(a, b) })
+    }
+
+    #[inline]
+    fn max(self, rhs: Self) -> Self {
+        // SAFETY: SSE2 is part of x86-64.
+        self.zip(rhs, |a, b| unsafe { _mm_max_pd(a, b) })
+    }
+
+    #[inline]
+    fn lt_mask(self, rhs: Self) -> u32 {
+        // SAFETY: SSE2 is part of x86-64.
+        let [low, high] =
+            [0, 1].map(|i| unsafe { _mm_movemask_pd(_mm_cmplt_pd(self.0[i], rhs.0[i])) as u32 });
+        low | high << 2
+    }
+
+    #[inline]
+    fn select(mask: u32, a: Self, b: Self) -> Self {
+        let bit = |i: u32| if mask & (1 << i) != 0 { -1 } else { 0 };
+        F64x4([0, 1].map(|i| {
+            // SAFETY: SSE2 is part of x86-64.
+            unsafe {
+                let m = _mm_castsi128_pd(_mm_set_epi64x(bit(2 * i + 1), bit(2 * i)));
+                let (a, b) = (a.0[i as usize], b.0[i as usize]);
+                _mm_or_pd(_mm_and_pd(m, a), _mm_andnot_pd(m, b))
+            }
+        }))
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+impl F64x4 {
+    #[inline]
+    fn zip(self, rhs: Self, f: impl Fn(__m128d, __m128d) -> __m128d) -> Self {
+        F64x4([f(self.0[0], rhs.0[0]), f(self.0[1], rhs.0[1])])
+    }
+}
+
+/// The lanes of `$t` in an array where there is no SSE, and what both
+/// layouts share.
+macro_rules! impl_lanes {
+    ($($t:ident $scalar:ty),*) => {
+        $(
+            #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+            impl Lanes for $t {
+                type Scalar = $scalar;
+
+                #[inline]
+                fn new(lanes: [$scalar; 4]) -> Self {
+                    $t(lanes)
+                }
+
+                #[inline]
+                fn splat(value: $scalar) -> Self {
+                    $t([value; 4])
+                }
+
+                #[inline]
+                fn to_array(self) -> [$scalar; 4] {
+                    self.0
+                }
+
+                #[inline]
+                fn sqrt(self) -> Self {
+                    $t(self.0.map(<$scalar>::sqrt))
+                }
+
+                #[inline]
+                fn min(self, rhs: Self) -> Self {
+                    self.zip(rhs, |a, b| if a < b { a } else { b })
+                }
+
+                #[inline]
+                fn max(self, rhs: Self) -> Self {
+                    self.zip(rhs, |a, b| if a > b { a } else { b })
+                }
+
+                #[inline]
+                fn lt_mask(self, rhs: Self) -> u32 {
+                    (0..4).fold(0, |mask, i| mask | ((self.0[i] < rhs.0[i]) as u32) << i)
+                }
+
+                #[inline]
+                fn select(mask: u32, a: Self, b: Self) -> Self {
+                    $t(std::array::from_fn(|i| {
+                        if mask & (1 << i) != 0 {
+                            a.0[i]
+                        } else {
+                            b.0[i]
+                        }
+                    }))
+                }
+            }
+
+            #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+            impl $t {
+                #[inline]
+                fn zip(self, rhs: Self, f: impl Fn($scalar, $scalar) -> $scalar) -> Self {
+                    $t(std::array::from_fn(|i| f(self.0[i], rhs.0[i])))
+                }
+            }
+
+            impl Debug for $t {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_tuple(stringify!($t)).field(&self.to_array()).finish()
+                }
+            }
+
+            impl PartialEq for $t {
+                fn eq(&self, other: &$t) -> bool {
+                    self.to_array() == other.to_array()
+                }
+            }
+        )*
+    };
+}
+
+impl_lanes!(F32x4 f32, F64x4 f64);
+
+macro_rules! impl_lanewise {
+    ($($op:ident $method:ident $ps:ident $pd:ident),*) => {
+        $(
+            impl $op for F32x4 {
+                type Output = F32x4;
+
+                #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+                #[inline]
+                fn $method(self, rhs: F32x4) -> F32x4 {
+                    // SAFETY: SSE is part of x86-64.
+                    F32x4(unsafe { $ps(self.0, rhs.0) })
+                }
+
+                #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+                #[inline]
+                fn $method(self, rhs: F32x4) -> F32x4 {
+                    self.zip(rhs, |a, b| a.$method(b))
+                }
+            }
+
+            impl $op for F64x4 {
+                type Output = F64x4;
+
+                #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+                #[inline]
+                fn $method(self, rhs: F64x4) -> F64x4 {
+                    // SAFETY: SSE2 is part of x86-64.
+                    self.zip(rhs, |a, b| unsafe { $pd(a, b) })
+                }
+
+                #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+                #[inline]
+                fn $method(self, rhs: F64x4) -> F64x4 {
+                    self.zip(rhs, |a, b| a.$method(b))
+                }
+            }
+        )*
+    };
+}
+
+impl_lanewise!(
+    Add add _mm_add_ps _mm_add_pd,
+    Sub sub _mm_sub_ps _mm_sub_pd,
+    Mul mul _mm_mul_ps _mm_mul_pd,
+    Div div _mm_div_ps _mm_div_pd
+);
+
+/// Flips the sign bit like scalar negation, keeping `-0.0` apart from `0.0`.
+impl Neg for F32x4 {
+    type Output = F32x4;
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn neg(self) -> F32x4 {
+        // SAFETY: SSE is part of x86-64.
+        F32x4(unsafe { _mm_xor_ps(self.0, _mm_set1_ps(-0.0)) })
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    #[inline]
+    fn neg(self) -> F32x4 {
+        F32x4(self.0.map(|a| -a))
+    }
+}
+
+/// Flips the sign bit like scalar negation, keeping `-0.0` apart from `0.0`.
+impl Neg for F64x4 {
+    type Output = F64x4;
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[inline]
+    fn neg(self) -> F64x4 {
+        // SAFETY: SSE2 is part of x86-64.
+        F64x4(
+            self.0
+                .map(|half| unsafe { _mm_xor_pd(half, _mm_set1_pd(-0.0)) }),
+        )
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    #[inline]
+    fn neg(self) -> F64x4 {
+        F64x4(self.0.map(|a| -a))
+    }
+}
+
+/// Four vectors, a lane each, stored by component so that one operation
+/// works on all four, e.g. to test a ray against four shapes at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector3x4<L = F32x4> {
+    pub x: L,
+    pub y: L,
+    pub z: L,
+}
+
+impl<L: Lanes> Vector3x4<L> {
+    pub fn new(vectors: [Vector3<L::Scalar>; 4]) -> Self {
+        Vector3x4 {
+            x: L::new(vectors.map(|v| v.x)),
+            y: L::new(vectors.map(|v| v.y)),
+            z: L::new(vectors.map(|v| v.z)),
+        }
+    }
+
+    /// `v` in every lane.
+    pub fn splat(v: Vector3<L::Scalar>) -> Self {
+        Vector3x4 {
+            x: L::splat(v.x),
+            y: L::splat(v.y),
+            z: L::splat(v.z),
+        }
+    }
+
+    pub fn lanes(self) -> [Vector3<L::Scalar>; 4] {
+        let (x, y, z) = (self.x.to_array(), self.y.to_array(), self.z.to_array());
+        std::array::from_fn(|i| Vector3::new([x[i], y[i], z[i]]))
+    }
+
+    pub fn dot(&self, rhs: &Vector3x4<L>) -> L {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn cross(&self, rhs: &Vector3x4<L>) -> Vector3x4<L> {
+        Vector3x4 {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+
+    pub fn length_squared(&self) -> L {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> L {
+        self.length_squared().sqrt()
+    }
+}
+
+impl<L: Lanes> Add for Vector3x4<L> {
+    type Output = Vector3x4<L>;
+
+    fn add(self, rhs: Vector3x4<L>) -> Vector3x4<L> {
+        Vector3x4 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl<L: Lanes> Sub for Vector3x4<L> {
+    type Output = Vector3x4<L>;
+
+    fn sub(self, rhs: Vector3x4<L>) -> Vector3x4<L> {
+        Vector3x4 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl<L: Lanes> Mul<L> for Vector3x4<L> {
+    type Output = Vector3x4<L>;
+
+    fn mul(self, rhs: L) -> Vector3x4<L> {
+        Vector3x4 {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+impl<L: Lanes> Neg for Vector3x4<L> {
+    type Output = Vector3x4<L>;
+
+    fn neg(self) -> Vector3x4<L> {
+        Vector3x4 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+/// Tests a ray against four spheres: bit `i` of the mask is set if it hits
+/// sphere `i` within `t_min..=t_max`, and lane `i` holds the nearer such hit,
+/// infinite where there is none. Each lane takes the steps of the scalar
+/// sphere test in the same order, so that both find the same hits.
+pub fn hit_spheres<L: Lanes>(
+    centers: &Vector3x4<L>,
+    radii: L,
+    origin: Vector3<L::Scalar>,
+    direction: Vector3<L::Scalar>,
+    t_min: L::Scalar,
+    t_max: L::Scalar,
+) -> (u32, L) {
+    let oc = Vector3x4::splat(origin) - *centers;
+    let d = Vector3x4::splat(direction);
+    let a = d.length_squared();
+    let half_b = oc.dot(&d);
+    let c = oc.length_squared() - radii * radii;
+    let discriminant = half_b * half_b - a * c;
+    let hit = !discriminant.lt_mask(L::splat(L::Scalar::ZERO)) & 0xf;
+    if hit == 0 {
+        // Most rays miss most spheres; skip the roots like the scalar test.
+        return (0, L::splat(L::Scalar::INFINITY));
+    }
+    let sqrtd = discriminant.sqrt();
+    let (t_min, t_max) = (L::splat(t_min), L::splat(t_max));
+    let inside = |t: L| !t.lt_mask(t_min) & !t_max.lt_mask(t) & hit;
+    let near = (-half_b - sqrtd) / a;
+    let far = (-half_b + sqrtd) / a;
+    let (near_hit, far_hit) = (inside(near), inside(far));
+    let t = L::select(far_hit, far, L::splat(L::Scalar::INFINITY));
+    (near_hit | far_hit, L::select(near_hit, near, t))
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v<T: Float>(x: f64, y: f64, z: f64) -> Vector3<T> {
+        Vector3::new([x, y, z].map(T::from_f64))
+    }
+
+    fn check_lanes<L: Lanes>() {
+        let f = |lanes: [f64; 4]| L::new(lanes.map(L::Scalar::from_f64));
+        let array = |l: L| l.to_array().map(Float::to_f64);
+        let a = f([1.0, 4.0, 9.0, 16.0]);
+        let b = L::splat(L::Scalar::from_f64(2.0));
+        assert_eq!(array(a), [1.0, 4.0, 9.0, 16.0]);
+        assert_eq!(array(a + b), [3.0, 6.0, 11.0, 18.0]);
+        assert_eq!(array(a - b), [-1.0, 2.0, 7.0, 14.0]);
+        assert_eq!(array(a * b), [2.0, 8.0, 18.0, 32.0]);
+        assert_eq!(array(a / b), [0.5, 2.0, 4.5, 8.0]);
+        assert_eq!(array(-a), [-1.0, -4.0, -9.0, -16.0]);
+        assert!(array(-f([0.0; 4])).iter().all(|z| z.is_sign_negative()));
+        assert_eq!(array(a.sqrt()), [1.0, 2.0, 3.0, 4.0]);
+        let c = f([3.0, 1.0, 10.0, 16.0]);
+        assert_eq!(array(a.min(c)), [1.0, 1.0, 9.0, 16.0]);
+        assert_eq!(array(a.max(c)), [3.0, 4.0, 10.0, 16.0]);
+        assert_eq!(a.lt_mask(c), 0b0101);
+        assert_eq!(array(L::select(0b0110, a, c)), [3.0, 4.0, 9.0, 16.0]);
+    }
+
+    #[test]
+    fn test_lanes() {
+        check_lanes::<F32x4>();
+        check_lanes::<F64x4>();
+    }
+
+    #[test]
+    fn test_vectors_match_scalar() {
+        let a = [
+            v(1.0, 2.0, 3.0),
+            v(-1.0, 0.5, 2.0),
+            v(0.0, 0.0, 1.0),
+            v(4.0, -3.0, 0.0),
+        ];
+        let b = [
+            v(3.0, 2.0, 1.0),
+            v(2.0, 2.0, 2.0),
+            v(1.0, 0.0, 0.0),
+            v(0.5, 0.5, 0.5),
+        ];
+        let (a4, b4) = (Vector3x4::<F32x4>::new(a), Vector3x4::new(b));
+        assert_eq!(a4.lanes(), a);
+        let (sum, difference, cross) =
+            ((a4 + b4).lanes(), (a4 - b4).lanes(), a4.cross(&b4).lanes());
+        let (dot, length) = (a4.dot(&b4).to_array(), a4.length().to_array());
+        let scaled = (a4 * F32x4::new([1.0, 2.0, 3.0, 4.0])).lanes();
+        for i in 0..4 {
+            assert_eq!(sum[i], a[i] + b[i]);
+            assert_eq!(difference[i], a[i] - b[i]);
+            assert_eq!(cross[i], a[i].cross(&b[i]));
+            assert_eq!(dot[i], a[i].dot(&b[i]));
+            assert_eq!(length[i], a[i].length());
+            assert_eq!(scaled[i], a[i] * (i + 1) as f32);
+            assert_eq!((-a4).lanes()[i], -a[i]);
+        }
+        assert_eq!(Vector3x4::<F32x4>::splat(a[1]).lanes(), [a[1]; 4]);
+    }
+
+    fn check_hit_spheres<L: Lanes>() {
+        let centers = Vector3x4::<L>::new([
+            v(0.0, 0.0, -5.0),
+            v(0.0, 3.0, -5.0),
+            v(0.0, 0.0, 0.0),
+            v(0.0, 0.0, 5.0),
+        ]);
+        let radii = L::new([1.0, 1.0, 2.0, 1.0].map(L::Scalar::from_f64));
+        let hit = |t_max: f64| {
+            let (mask, t) = hit_spheres(
+                &centers,
+                radii,
+                v(0.0, 0.0, 0.0),
+                v(0.0, 0.0, -1.0),
+                L::Scalar::from_f64(0.001),
+                L::Scalar::from_f64(t_max),
+            );
+            (mask, t.to_array().map(Float::to_f64))
+        };
+        let inf = f64::INFINITY;
+        // In front, off to the side, around the origin and behind.
+        assert_eq!(hit(100.0), (0b0101, [4.0, inf, 2.0, inf]));
+        assert_eq!(hit(3.0), (0b0100, [inf, inf, 2.0, inf]));
+        // Up to and including `t_max`.
+        assert_eq!(hit(4.0), (0b0101, [4.0, inf, 2.0, inf]));
+    }
+
+    #[test]
+    fn test_hit_spheres() {
+        check_hit_spheres::<F32x4>();
+        check_hit_spheres::<F64x4>();
+    }
+}
@@ -0,0 +1,87 @@
+//! 统计: 样本的均值与方差
+
+/// Running mean and variance of a stream of samples (Welford's algorithm).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunningStats {
+    pub count: usize,
+    pub mean: f64,
+    /// Sum of squared deviations from the mean.
+    pub(crate) m2: f64,
+}
+
+impl RunningStats {
+    pub fn new() -> Self {
+        RunningStats::default()
+    }
+
+    pub fn add(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Combine with the statistics of another, disjoint set of samples.
+    pub fn merge(&mut self, other: &RunningStats) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count as f64 / count as f64;
+        self.m2 += other.m2 + delta * delta * (self.count * other.count) as f64 / count as f64;
+        self.count = count;
+    }
+
+    /// Unbiased sample variance, 0 for fewer than two samples.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Estimated standard deviation of the mean.
+    pub fn standard_error(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.variance() / self.count as f64).sqrt()
+        }
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_and_variance() {
+        let mut stats = RunningStats::new();
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.add(x);
+        }
+        assert_eq!(stats.count, 8);
+        assert!((stats.mean - 5.0).abs() < 1e-12);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_merge_matches_sequential() {
+        let samples: Vec<f64> = (0..20).map(|i| ((i * 37) % 11) as f64 * 0.3).collect();
+        let mut all = RunningStats::new();
+        samples.iter().for_each(|&x| all.add(x));
+
+        let (mut a, mut b) = (RunningStats::new(), RunningStats::new());
+        samples[..7].iter().for_each(|&x| a.add(x));
+        samples[7..].iter().for_each(|&x| b.add(x));
+        a.merge(&b);
+        a.merge(&RunningStats::new());
+
+        assert_eq!(a.count, all.count);
+        assert!((a.mean - all.mean).abs() < 1e-12);
+        assert!((a.variance() - all.variance()).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,237 @@
+//! 仿射变换
+
+use crate::aabb::Aabb;
+use crate::matrix::{Mat3, Mat4};
+use crate::quaternion::Quaternion;
+use crate::ray::Ray;
+use crate::{point3, vec3, Normal3, Point3, Vector3};
+use std::ops::Mul;
+
+/// Invertible affine transform with its inverse cached, so that mapping
+/// into the space of an object and back costs no inversion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Mat4,
+    m_inv: Mat4,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        m: Mat4::IDENTITY,
+        m_inv: Mat4::IDENTITY,
+    };
+
+    /// `None` if `m` is singular. The last row of `m` is taken to be
+    /// `0 0 0 1`.
+    pub fn new(m: Mat4) -> Option<Self> {
+        Some(Transform {
+            m_inv: m.inverse()?,
+            m,
+        })
+    }
+
+    pub fn translate(delta: &Vector3) -> Self {
+        Transform {
+            m: Mat4::from_affine(&Mat3::IDENTITY, delta),
+            m_inv: Mat4::from_affine(&Mat3::IDENTITY, &-*delta),
+        }
+    }
+
+    /// Panics on a zero factor.
+    pub fn scale(x: f64, y: f64, z: f64) -> Self {
+        assert!(x != 0.0 && y != 0.0 && z != 0.0, "scale by zero");
+        let diagonal =
+            |x: f64, y: f64, z: f64| Mat3::new([[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, z]]);
+        Transform {
+            m: Mat4::from_affine(&diagonal(x, y, z), &vec3![]),
+            m_inv: Mat4::from_affine(&diagonal(1.0 / x, 1.0 / y, 1.0 / z), &vec3![]),
+        }
+    }
+
+    /// Rotation by the unit quaternion `q`.
+    pub fn rotate(q: &Quaternion) -> Self {
+        let r = q.to_mat3();
+        // The inverse of a rotation is its transpose.
+        Transform {
+            m: Mat4::from_affine(&r, &vec3![]),
+            m_inv: Mat4::from_affine(&r.transpose(), &vec3![]),
+        }
+    }
+
+    /// Rotation by `degrees` counterclockwise about `axis`, looking against
+    /// it.
+    pub fn rotate_axis(axis: &Vector3, degrees: f64) -> Self {
+        Transform::rotate(&Quaternion::from_axis_angle(axis, degrees))
+    }
+
+    /// Rotation by `degrees` about the y axis, built from exact sines and
+    /// cosines: a rotation by 0 is the identity.
+    pub fn rotate_y(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let r = Mat3::new([[cos, 0.0, sin], [0.0, 1.0, 0.0], [-sin, 0.0, cos]]);
+        Transform {
+            m: Mat4::from_affine(&r, &vec3![]),
+            m_inv: Mat4::from_affine(&r.transpose(), &vec3![]),
+        }
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn from_trs(translation: &Vector3, rotation: &Quaternion, scale: &Vector3) -> Self {
+        Transform::translate(translation)
+            * Transform::rotate(rotation)
+            * Transform::scale(scale.x, scale.y, scale.z)
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    pub fn inverse_matrix(&self) -> &Mat4 {
+        &self.m_inv
+    }
+
+    pub fn inverse(&self) -> Self {
+        Transform {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.m == Mat4::IDENTITY
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        let m = &self.m.m;
+        point3![
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3]
+        ]
+    }
+
+    /// A direction or offset, which the translation does not move.
+    pub fn vector(&self, v: &Vector3) -> Vector3 {
+        self.m.linear() * *v
+    }
+
+    /// A surface normal, by the inverse transpose so that it stays
+    /// perpendicular to the transformed surface. Not normalized.
+    pub fn normal(&self, n: &Normal3) -> Normal3 {
+        (self.m_inv.linear().transpose() * Vector3::from(*n)).into()
+    }
+
+    /// Distances along the ray keep their parameter `t`, since the direction
+    /// is not normalized.
+    pub fn ray(&self, ray: &Ray) -> Ray {
+        Ray::with_time(
+            &self.point(&ray.origin),
+            &self.vector(&ray.direction),
+            ray.time,
+        )
+    }
+
+    /// The smallest box around the transformed box (Arvo 1990).
+    pub fn aabb(&self, bounds: &Aabb) -> Aabb {
+        if bounds.is_empty() {
+            return Aabb::EMPTY;
+        }
+        let m = &self.m.m;
+        let mut min = [m[0][3], m[1][3], m[2][3]];
+        let mut max = min;
+        for (i, row) in m.iter().take(3).enumerate() {
+            for (j, &m_ij) in row.iter().take(3).enumerate() {
+                let (a, b) = (m_ij * bounds.min[j], m_ij * bounds.max[j]);
+                min[i] += a.min(b);
+                max[i] += a.max(b);
+            }
+        }
+        Aabb {
+            min: Point3::new(min),
+            max: Point3::new(max),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// `rhs` followed by `self`.
+    fn mul(self, rhs: Transform) -> Self::Output {
+        Transform {
+            m: self.m * rhs.m,
+            m_inv: rhs.m_inv * self.m_inv,
+        }
+    }
+}
+
+// 测试用例
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normal3;
+
+    #[test]
+    fn test_points_vectors_normals() {
+        let t = Transform::translate(&vec3![1, 2, 3])
+            * Transform::rotate_y(90.0)
+            * Transform::scale(2.0, 1.0, 1.0);
+        let p = t.point(&point3![1, 0, 0]);
+        assert!((p - point3![1, 2, 1]).length() < 1e-12, "{:?}", p);
+        assert!((t.vector(&vec3![1, 0, 0]) - vec3![0, 0, -2]).length() < 1e-12);
+        assert!((t.inverse().point(&p) - point3![1, 0, 0]).length() < 1e-12);
+
+        // The normal of the plane x + y = 0 stays perpendicular to it after
+        // a non-uniform scale.
+        let s = Transform::scale(4.0, 1.0, 1.0);
+        let n = s.normal(&normal3![1, 1, 0]);
+        let along = s.vector(&vec3![1, -1, 0]);
+        assert!(n.dot(&along).abs() < 1e-12);
+
+        let general = Transform::new(*t.matrix()).unwrap();
+        let q = general.inverse().point(&p);
+        assert!((q - point3![1, 0, 0]).length() < 1e-12);
+        assert!(Transform::new(Mat4::new([[0.0; 4]; 4])).is_none());
+    }
+
+    #[test]
+    fn test_rotations_agree() {
+        let by_quaternion = Transform::rotate_axis(&vec3![0, 1, 0], 30.0);
+        let by_angle = Transform::rotate_y(30.0);
+        let v = vec3![1, 2, 3];
+        assert!((by_quaternion.vector(&v) - by_angle.vector(&v)).length() < 1e-12);
+        assert!(Transform::rotate_y(0.0).is_identity());
+
+        let trs = Transform::from_trs(
+            &vec3![0, 0, 1],
+            &Quaternion::from_axis_angle(&vec3![0, 1, 0], 30.0),
+            &vec3![2, 2, 2],
+        );
+        let expected = Point3::from(by_angle.vector(&(2.0 * v)) + vec3![0, 0, 1]);
+        assert!((trs.point(&v.into()) - expected).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_ray_and_aabb() {
+        let t = Transform::translate(&vec3![0, 0, -5]) * Transform::scale(2.0, 2.0, 2.0);
+        let ray = t.ray(&Ray::with_time(&point3![0, 0, 1], &vec3![0, 0, -1], 0.5));
+        assert_eq!(ray.origin, point3![0, 0, -3]);
+        assert_eq!(ray.direction, vec3![0, 0, -2]);
+        assert_eq!(ray.time, 0.5);
+
+        let unit = Aabb::new(&point3![-1, -1, -1], &point3![1, 1, 1]);
+        let b = t.aabb(&unit);
+        assert_eq!(b, Aabb::new(&point3![-2, -2, -7], &point3![2, 2, -3]));
+        // A turned box grows to hold its corners.
+        let turned = Transform::rotate_y(45.0).aabb(&unit);
+        assert!((turned.max.x - 2f64.sqrt()).abs() < 1e-12);
+        assert!((turned.max.y - 1.0).abs() < 1e-12);
+        assert!(Transform::IDENTITY.aabb(&Aabb::EMPTY).is_empty());
+    }
+}
@@ -1,325 +1,380 @@
-//! 三维向量
-
-use crate::rtweekend::{random_double, random_double_range};
-use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
-
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+//! 三维向量、点、法线与颜色
+
+use crate::float::Float;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
+
+/// A direction or an offset between points, of precision `T`, `f64` unless
+/// stated otherwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Vector3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl Vec3 {
-    pub fn new0() -> Vec3 {
-        Vec3 {
-            x: 0f64,
-            y: 0f64,
-            z: 0f64,
-        }
-    }
-
-    pub fn new(e: [f64; 3]) -> Vec3 {
-        Vec3 {
-            x: e[0],
-            y: e[1],
-            z: e[2],
-        }
-    }
-
-    pub fn random() -> Vec3 {
-        Vec3 {
-            x: random_double(),
-            y: random_double(),
-            z: random_double(),
-        }
-    }
-
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
-        Vec3 {
-            x: random_double_range(min, max),
-            y: random_double_range(min, max),
-            z: random_double_range(min, max),
-        }
-    }
-
-    pub fn length_squared(&self) -> f64 {
-        self.x * self.x + self.y * self.y + self.z * self.z
-    }
-
-    pub fn length(&self) -> f64 {
-        self.length_squared().sqrt()
-    }
-
-    pub fn dot(&self, rhs: &Vec3) -> f64 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
-    }
-
-    pub fn cross(&self, rhs: &Vec3) -> Vec3 {
-        Vec3 {
-            x: self.y * rhs.z - self.z * rhs.y,
-            y: self.z * rhs.x - self.x * rhs.z,
-            z: self.x * rhs.y - self.y * rhs.x,
-        }
-    }
-
-    pub fn unit_vector(&self) -> Vec3 {
-        *self / self.length()
-    }
-
-    /// Return true if the vector is close to zero in all dimensions.
-    pub fn near_zero(&self) -> bool {
-        let s = 1e-8;
-        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
-    }
-
-    pub fn reflect(&self, n: &Vec3) -> Vec3 {
-        *self - 2.0 * self.dot(n) * n
-    }
-
-    pub fn refract(&self, n: &Vec3, etai_over_etat: f64) -> Vec3 {
-        let cos_theta = (-*self).dot(n).min(1.0);
-        let r_out_perp = etai_over_etat * (*self + cos_theta * n);
-        let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
-        r_out_perp + r_out_parallel
-    }
+/// A position. Points differ by a [`Vector3`]; weighted sums of points, such
+/// as averages, are points again.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Point3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl Neg for Vec3 {
-    type Output = Vec3;
-
-    fn neg(self) -> Self::Output {
-        Vec3::new([-self.x, -self.y, -self.z])
-    }
+/// The normal of a surface, which a transform maps by its inverse transpose
+/// rather than like a vector.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Normal3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl Add for Vec3 {
-    type Output = Vec3;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Vec3 {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
+/// Linear RGB radiance or reflectance, red in `x`, green in `y` and blue in
+/// `z`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Color<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl Add<&Vec3> for Vec3 {
-    type Output = Vec3;
-
-    fn add(self, rhs: &Self) -> Self::Output {
-        Vec3 {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
+/// What every one of the types holds.
+macro_rules! impl_triple {
+    ($($t:ident),*) => {
+        $(
+            impl<T: Float> $t<T> {
+                pub fn new(e: [T; 3]) -> $t<T> {
+                    $t {
+                        x: e[0],
+                        y: e[1],
+                        z: e[2],
+                    }
+                }
+
+                pub fn new0() -> $t<T> {
+                    $t::new([T::ZERO; 3])
+                }
+
+                /// The same value at precision `U`, rounded to the nearest.
+                pub fn cast<U: Float>(&self) -> $t<U> {
+                    $t {
+                        x: U::from_f64(self.x.to_f64()),
+                        y: U::from_f64(self.y.to_f64()),
+                        z: U::from_f64(self.z.to_f64()),
+                    }
+                }
+            }
+
+            impl<T> Index<usize> for $t<T> {
+                type Output = T;
+
+                fn index(&self, index: usize) -> &Self::Output {
+                    match index {
+                        0 => &self.x,
+                        1 => &self.y,
+                        2 => &self.z,
+                        _ => panic!("index out of range"),
+                    }
+                }
+            }
+
+            impl<T> IndexMut<usize> for $t<T> {
+                fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+                    match index {
+                        0 => &mut self.x,
+                        1 => &mut self.y,
+                        2 => &mut self.z,
+                        _ => panic!("index out of range"),
+                    }
+                }
+            }
+
+            impl<T: PartialEq> PartialEq<[T; 3]> for $t<T> {
+                fn eq(&self, other: &[T; 3]) -> bool {
+                    self.x == other[0] && self.y == other[1] && self.z == other[2]
+                }
+            }
+        )*
+    };
 }
 
-impl Add<&mut Vec3> for Vec3 {
-    type Output = Vec3;
-
-    fn add(self, rhs: &mut Self) -> Self::Output {
-        Vec3 {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
+impl_triple!(Vector3, Point3, Normal3, Color);
+
+/// Explicit conversions between the types, keeping the components.
+macro_rules! impl_from {
+    ($($from:ident => $($to:ident),*;)*) => {
+        $($(
+            impl<T> From<$from<T>> for $to<T> {
+                fn from(v: $from<T>) -> $to<T> {
+                    $to {
+                        x: v.x,
+                        y: v.y,
+                        z: v.z,
+                    }
+                }
+            }
+        )*)*
+    };
 }
 
-impl Sub for Vec3 {
-    type Output = Vec3;
+impl_from! {
+    Vector3 => Point3, Normal3, Color;
+    Point3 => Vector3, Normal3, Color;
+    Normal3 => Vector3, Point3, Color;
+    Color => Vector3, Point3, Normal3;
+}
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Vec3 {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
+/// Componentwise `op` of `$t` with `$rhs` taken by value, by reference or by
+/// mutable reference, giving `$out`; with `$assign` if `$out` is `$t`.
+macro_rules! impl_componentwise {
+    ($t:ident, $rhs:ident => $out:ident: $($op:ident $method:ident),*) => {
+        $(
+            impl_componentwise!(@op $op $method, $t, $out, $rhs<T>);
+            impl_componentwise!(@op $op $method, $t, $out, &$rhs<T>);
+            impl_componentwise!(@op $op $method, $t, $out, &mut $rhs<T>);
+        )*
+    };
+    ($t:ident, $rhs:ident: $($op:ident $method:ident, $assign:ident $assign_method:ident;)*) => {
+        $(
+            impl_componentwise!($t, $rhs => $t: $op $method);
+            impl_componentwise!(@assign $assign $assign_method, $t, $rhs<T>);
+            impl_componentwise!(@assign $assign $assign_method, $t, &$rhs<T>);
+            impl_componentwise!(@assign $assign $assign_method, $t, &mut $rhs<T>);
+        )*
+    };
+    (@op $op:ident $method:ident, $t:ident, $out:ident, $rhs:ty) => {
+        impl<T: Float> $op<$rhs> for $t<T> {
+            type Output = $out<T>;
+
+            fn $method(self, rhs: $rhs) -> Self::Output {
+                $out {
+                    x: self.x.$method(rhs.x),
+                    y: self.y.$method(rhs.y),
+                    z: self.z.$method(rhs.z),
+                }
+            }
         }
-    }
+    };
+    (@assign $assign:ident $assign_method:ident, $t:ident, $rhs:ty) => {
+        impl<T: Float> $assign<$rhs> for $t<T> {
+            fn $assign_method(&mut self, rhs: $rhs) {
+                self.x.$assign_method(rhs.x);
+                self.y.$assign_method(rhs.y);
+                self.z.$assign_method(rhs.z);
+            }
+        }
+    };
 }
 
-impl Sub<&Vec3> for Vec3 {
-    type Output = Vec3;
+impl_componentwise! {
+    Vector3, Vector3:
+    Add add, AddAssign add_assign;
+    Sub sub, SubAssign sub_assign;
+    Mul mul, MulAssign mul_assign;
+}
 
-    fn sub(self, rhs: &Self) -> Self::Output {
-        Vec3 {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
+impl_componentwise! {
+    Point3, Vector3:
+    Add add, AddAssign add_assign;
+    Sub sub, SubAssign sub_assign;
 }
 
-impl Sub<&mut Vec3> for Vec3 {
-    type Output = Vec3;
+impl_componentwise!(Point3, Point3 => Vector3: Sub sub);
 
-    fn sub(self, rhs: &mut Self) -> Self::Output {
-        Vec3 {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
+impl_componentwise! {
+    Point3, Point3:
+    Add add, AddAssign add_assign;
 }
 
-impl Mul for Vec3 {
-    type Output = Vec3;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        Vec3 {
-            x: self.x * rhs.x,
-            y: self.y * rhs.y,
-            z: self.z * rhs.z,
-        }
-    }
+impl_componentwise! {
+    Normal3, Normal3:
+    Add add, AddAssign add_assign;
+    Sub sub, SubAssign sub_assign;
 }
 
-impl Mul<&Vec3> for Vec3 {
-    type Output = Vec3;
+impl_componentwise! {
+    Color, Color:
+    Add add, AddAssign add_assign;
+    Sub sub, SubAssign sub_assign;
+    Mul mul, MulAssign mul_assign;
+    Div div, DivAssign div_assign;
+}
 
-    fn mul(self, rhs: &Self) -> Self::Output {
-        Vec3 {
-            x: self.x * rhs.x,
-            y: self.y * rhs.y,
-            z: self.z * rhs.z,
-        }
-    }
+/// Scaling by a scalar, with the scalar on either side. The orphan rule
+/// only allows the scalar on the left per concrete scalar type.
+macro_rules! impl_scale {
+    ($($t:ident),*) => {
+        $(
+            impl<T: Float> Mul<T> for $t<T> {
+                type Output = $t<T>;
+
+                fn mul(self, rhs: T) -> Self::Output {
+                    $t {
+                        x: self.x * rhs,
+                        y: self.y * rhs,
+                        z: self.z * rhs,
+                    }
+                }
+            }
+
+            impl<T: Float> Div<T> for $t<T> {
+                type Output = $t<T>;
+
+                fn div(self, rhs: T) -> Self::Output {
+                    $t {
+                        x: self.x / rhs,
+                        y: self.y / rhs,
+                        z: self.z / rhs,
+                    }
+                }
+            }
+
+            impl<T: Float> MulAssign<T> for $t<T> {
+                fn mul_assign(&mut self, rhs: T) {
+                    self.x *= rhs;
+                    self.y *= rhs;
+                    self.z *= rhs;
+                }
+            }
+
+            impl<T: Float> DivAssign<T> for $t<T> {
+                fn div_assign(&mut self, rhs: T) {
+                    self.x /= rhs;
+                    self.y /= rhs;
+                    self.z /= rhs;
+                }
+            }
+
+            impl_scale!(@left $t, f32, f64);
+        )*
+    };
+    (@left $t:ident, $($s:ty),*) => {
+        $(
+            impl Mul<$t<$s>> for $s {
+                type Output = $t<$s>;
+
+                fn mul(self, rhs: $t<$s>) -> Self::Output {
+                    rhs * self
+                }
+            }
+
+            impl Mul<&$t<$s>> for $s {
+                type Output = $t<$s>;
+
+                fn mul(self, rhs: &$t<$s>) -> Self::Output {
+                    *rhs * self
+                }
+            }
+        )*
+    };
 }
 
-impl Mul<&mut Vec3> for Vec3 {
-    type Output = Vec3;
+impl_scale!(Vector3, Point3, Normal3, Color);
 
-    fn mul(self, rhs: &mut Self) -> Self::Output {
-        Vec3 {
-            x: self.x * rhs.x,
-            y: self.y * rhs.y,
-            z: self.z * rhs.z,
-        }
-    }
+macro_rules! impl_neg {
+    ($($t:ident),*) => {
+        $(
+            impl<T: Float> Neg for $t<T> {
+                type Output = $t<T>;
+
+                fn neg(self) -> Self::Output {
+                    $t::new([-self.x, -self.y, -self.z])
+                }
+            }
+        )*
+    };
 }
 
-impl Mul<f64> for Vec3 {
-    type Output = Vec3;
+impl_neg!(Vector3, Normal3);
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        Vec3 {
-            x: self.x * rhs,
-            y: self.y * rhs,
-            z: self.z * rhs,
-        }
+impl<T: Float> Vector3<T> {
+    pub fn length_squared(&self) -> T {
+        self.dot(self)
     }
-}
-
-impl Mul<Vec3> for f64 {
-    type Output = Vec3;
 
-    fn mul(self, rhs: Vec3) -> Self::Output {
-        Vec3 {
-            x: self * rhs.x,
-            y: self * rhs.y,
-            z: self * rhs.z,
-        }
+    pub fn length(&self) -> T {
+        self.length_squared().sqrt()
     }
-}
 
-impl Mul<&Vec3> for f64 {
-    type Output = Vec3;
+    pub fn dot(&self, rhs: &Vector3<T>) -> T {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
 
-    fn mul(self, rhs: &Vec3) -> Self::Output {
-        Vec3 {
-            x: self * rhs.x,
-            y: self * rhs.y,
-            z: self * rhs.z,
+    pub fn cross(&self, rhs: &Vector3<T>) -> Vector3<T> {
+        Vector3 {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
         }
     }
-}
 
-impl Div<f64> for Vec3 {
-    type Output = Vec3;
-
-    fn div(self, rhs: f64) -> Self::Output {
-        Vec3 {
-            x: self.x / rhs,
-            y: self.y / rhs,
-            z: self.z / rhs,
-        }
+    pub fn unit_vector(&self) -> Vector3<T> {
+        *self / self.length()
     }
-}
 
-impl AddAssign for Vec3 {
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z;
+    /// Return true if the vector is close to zero in all dimensions.
+    pub fn near_zero(&self) -> bool {
+        let s = T::from_f64(1e-8);
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
     }
-}
 
-impl AddAssign<&Vec3> for Vec3 {
-    fn add_assign(&mut self, rhs: &Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z;
+    /// Mirrored at the plane of the unit normal `n`.
+    pub fn reflect(&self, n: &Normal3<T>) -> Vector3<T> {
+        let n = Vector3::from(*n);
+        *self - n * (T::from_f64(2.0) * self.dot(&n))
     }
-}
 
-impl AddAssign<&mut Vec3> for Vec3 {
-    fn add_assign(&mut self, rhs: &mut Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z;
+    /// Bent through the plane of the unit normal `n`, which points against
+    /// this unit vector, from a medium of index `etai` into one of `etat`.
+    pub fn refract(&self, n: &Normal3<T>, etai_over_etat: T) -> Vector3<T> {
+        let n = Vector3::from(*n);
+        let cos_theta = (-*self).dot(&n).min(T::ONE);
+        let r_out_perp = (*self + n * cos_theta) * etai_over_etat;
+        let r_out_parallel = n * -(T::ONE - r_out_perp.length_squared()).abs().sqrt();
+        r_out_perp + r_out_parallel
     }
 }
 
-impl MulAssign<f64> for Vec3 {
-    fn mul_assign(&mut self, rhs: f64) {
-        self.x *= rhs;
-        self.y *= rhs;
-        self.z *= rhs;
+impl<T: Float> Point3<T> {
+    pub fn distance(&self, rhs: &Point3<T>) -> T {
+        (*self - *rhs).length()
     }
 }
 
-impl DivAssign<f64> for Vec3 {
-    fn div_assign(&mut self, rhs: f64) {
-        self.x /= rhs;
-        self.y /= rhs;
-        self.z /= rhs;
+impl<T: Float> Normal3<T> {
+    pub fn length(&self) -> T {
+        Vector3::from(*self).length()
     }
-}
 
-impl Index<usize> for Vec3 {
-    type Output = f64;
+    pub fn dot(&self, rhs: &Vector3<T>) -> T {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
 
-    fn index(&self, index: usize) -> &Self::Output {
-        match index {
-            0 => &self.x,
-            1 => &self.y,
-            2 => &self.z,
-            _ => panic!("index out of range"),
-        }
+    pub fn unit_vector(&self) -> Normal3<T> {
+        *self / self.length()
     }
-}
 
-impl IndexMut<usize> for Vec3 {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        match index {
-            0 => &mut self.x,
-            1 => &mut self.y,
-            2 => &mut self.z,
-            _ => panic!("index out of range"),
+    /// This normal, flipped if need be to the side of `v`.
+    pub fn face_forward(&self, v: &Vector3<T>) -> Normal3<T> {
+        if self.dot(v) < T::ZERO {
+            -*self
+        } else {
+            *self
         }
     }
 }
 
-impl PartialEq<[f64; 3]> for Vec3 {
-    fn eq(&self, other: &[f64; 3]) -> bool {
-        self.x == other[0] && self.y == other[1] && self.z == other[2]
+impl<T: Float> Color<T> {
+    /// Whether all components are zero, so that nothing is carried.
+    pub fn is_black(&self) -> bool {
+        self.x == T::ZERO && self.y == T::ZERO && self.z == T::ZERO
     }
 
-    fn ne(&self, other: &[f64; 3]) -> bool {
-        !(self.x == other[0] && self.y == other[1] && self.z == other[2])
+    pub fn max_component(&self) -> T {
+        self.x.max(self.y).max(self.z)
     }
 }
 
@@ -327,16 +382,16 @@ impl PartialEq<[f64; 3]> for Vec3 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{color, point3, vec3};
+    use crate::{color, normal3, point3, vec3};
 
     #[test]
     fn test_constructor() {
-        let v0 = Vec3::new0();
+        let v0: Vector3 = Vector3::new0();
         assert_eq!(v0.x, 0.0);
         assert_eq!(v0.y, 0.0);
         assert_eq!(v0.z, 0.0);
 
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -345,7 +400,7 @@ mod tests {
         assert_eq!(v1.y, 2.0);
         assert_eq!(v1.z, 3.0);
 
-        let v2 = Vec3::new([3.0, 2.0, 1.0]);
+        let v2 = Vector3::new([3.0, 2.0, 1.0]);
         assert_eq!(v2.x, 3.0);
         assert_eq!(v2.y, 2.0);
         assert_eq!(v2.z, 1.0);
@@ -367,11 +422,14 @@ mod tests {
         assert_eq!(v2.x, 3.0);
         assert_eq!(v2.y, 2.0);
         assert_eq!(v2.z, 1.0);
+
+        assert_eq!(normal3![0, 1, 0], Normal3::new([0.0, 1.0, 0.0]));
+        assert_eq!(color![], Color::new0());
     }
 
     #[test]
     fn test_arr_eq() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -385,7 +443,7 @@ mod tests {
 
     #[test]
     fn test_neg() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -396,12 +454,12 @@ mod tests {
 
     #[test]
     fn test_add() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 3.0,
             y: 2.0,
             z: 1.0,
@@ -412,12 +470,12 @@ mod tests {
 
     #[test]
     fn test_sub() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 3.0,
             y: 2.0,
             z: 1.0,
@@ -428,12 +486,12 @@ mod tests {
 
     #[test]
     fn test_mul() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 3.0,
             y: 2.0,
             z: 1.0,
@@ -441,21 +499,21 @@ mod tests {
         let v3 = v1 * v2;
         assert_eq!(v3, [3.0, 4.0, 3.0]);
 
-        let v2 = Vec3::new([3.0, 2.0, 1.0]);
+        let v2 = Vector3::new([3.0, 2.0, 1.0]);
         let v3 = 0.5 * v2 * 3.0;
         assert_eq!(v3, [4.5, 3.0, 1.5]);
     }
 
     #[test]
     fn test_div() {
-        let v1 = Vec3::new([3.0, 2.0, 1.0]);
+        let v1 = Vector3::new([3.0, 2.0, 1.0]);
         let v2 = v1 / 2.0;
         assert_eq!(v2, [1.5, 1.0, 0.5]);
     }
 
     #[test]
     fn test_index() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -467,7 +525,7 @@ mod tests {
 
     #[test]
     fn test_index_mut() {
-        let mut v1 = Vec3 {
+        let mut v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -482,12 +540,12 @@ mod tests {
 
     #[test]
     fn test_add_assign() {
-        let mut v1 = Vec3 {
+        let mut v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 4.0,
             y: 5.0,
             z: 6.0,
@@ -498,7 +556,7 @@ mod tests {
 
     #[test]
     fn test_mul_assign() {
-        let mut v1 = Vec3 {
+        let mut v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -509,7 +567,7 @@ mod tests {
 
     #[test]
     fn test_div_assign() {
-        let mut v1 = Vec3 {
+        let mut v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -520,14 +578,14 @@ mod tests {
 
     #[test]
     fn test_length_squared() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
         assert_eq!(v1.length_squared(), 14.0);
 
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -539,7 +597,7 @@ mod tests {
 
     #[test]
     fn test_length() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
@@ -547,7 +605,7 @@ mod tests {
         assert_eq!(v1.length(), 14.0f64.sqrt());
 
         // 勾股
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 4.0,
             y: 0.0,
             z: 3.0,
@@ -557,12 +615,12 @@ mod tests {
 
     #[test]
     fn test_dot() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 3.0,
             y: 2.0,
             z: 1.0,
@@ -572,12 +630,12 @@ mod tests {
 
     #[test]
     fn test_cross() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 1.0,
             y: 2.0,
             z: 3.0,
         };
-        let v2 = Vec3 {
+        let v2 = Vector3 {
             x: 3.0,
             y: 2.0,
             z: 1.0,
@@ -588,7 +646,7 @@ mod tests {
 
     #[test]
     fn test_unit_vector() {
-        let v1 = Vec3 {
+        let v1 = Vector3 {
             x: 4.0,
             y: 0.0,
             z: 3.0,
@@ -596,4 +654,144 @@ mod tests {
         let v2 = v1.unit_vector();
         assert_eq!(v2, [0.8, 0.0, 0.6])
     }
+
+    #[test]
+    fn test_reflect_refract() {
+        let n = normal3![0, 1, 0];
+        let v = vec3![1, -1, 0].unit_vector();
+        assert!((v.reflect(&n) - vec3![v.x, -v.y, 0]).near_zero());
+        // Passing straight through at the same index.
+        assert!((v.refract(&n, 1.0) - v).near_zero());
+        let bent = v.refract(&n, 1.0 / 1.5);
+        assert!((bent.length() - 1.0).abs() < 1e-12);
+        assert!((bent.x - v.x / 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_points() {
+        let p = point3![1, 2, 3];
+        let q = point3![4, 6, 3];
+        let d: Vector3 = q - p;
+        assert_eq!(d, [3.0, 4.0, 0.0]);
+        assert_eq!(p + d, q);
+        assert_eq!(q - d, p);
+        assert_eq!(p.distance(&q), 5.0);
+        assert_eq!((p + q) * 0.5, [2.5, 4.0, 3.0]);
+        let mut r = p;
+        r += &d;
+        r -= vec3![1, 1, 1];
+        assert_eq!(r, [3.0, 5.0, 2.0]);
+    }
+
+    #[test]
+    fn test_normals() {
+        let n = Normal3::new([0.0, 3.0, 4.0]);
+        assert_eq!(n.length(), 5.0);
+        assert_eq!(n.unit_vector(), [0.0, 0.6, 0.8]);
+        assert_eq!(n.dot(&vec3![1, 1, 1]), 7.0);
+        assert_eq!(n.face_forward(&vec3![0, 0, -1]), -n);
+        assert_eq!(n.face_forward(&vec3![0, 1, 0]), n);
+        assert_eq!(n + n, 2.0 * n);
+    }
+
+    #[test]
+    fn test_colors() {
+        let a = color![0.5, 1.0, 2.0];
+        let b = color![2.0, 0.5, 0.25];
+        assert_eq!(a * b, [1.0, 0.5, 0.5]);
+        assert_eq!(a / b, [0.25, 2.0, 8.0]);
+        assert_eq!(a + b - b, a);
+        assert_eq!(a.max_component(), 2.0);
+        assert!(color![].is_black());
+        assert!(!a.is_black());
+        let mut c = a;
+        c *= &b;
+        c /= 2.0;
+        assert_eq!(c, [0.5, 0.25, 0.25]);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let v = vec3![1, -2, 3];
+        let p = Point3::from(v);
+        let n = Normal3::from(p);
+        let c = Color::from(n);
+        assert_eq!(Vector3::from(c), v);
+        assert_eq!(p, [1.0, -2.0, 3.0]);
+        assert_eq!(Point3::new0() + v, p);
+    }
+
+    #[test]
+    fn test_single_precision() {
+        let v1: Vector3<f32> = Vector3::new([4.0, 0.0, 3.0]);
+        assert_eq!(v1.length(), 5.0f32);
+        assert_eq!(2.0f32 * v1 - v1, v1);
+        let mut v2 = v1.cross(&Vector3::new([0.0, 1.0, 0.0]));
+        v2 -= &v1;
+        assert_eq!(v2, [-7.0f32, 0.0, 1.0]);
+        assert_eq!(Vector3::<f32>::new0(), [0.0f32; 3]);
+        assert_eq!(Vector3::<f32>::default(), Vector3::new0());
+    }
+
+    #[test]
+    fn test_single_precision_operators() {
+        let a: Vector3<f32> = Vector3::new([1.0, 2.0, 3.0]);
+        let b: Vector3<f32> = Vector3::new([3.0, 2.0, 1.0]);
+        assert_eq!(-a, [-1.0f32, -2.0, -3.0]);
+        assert_eq!(a + b, [4.0f32; 3]);
+        assert_eq!(a - b, [-2.0f32, 0.0, 2.0]);
+        assert_eq!(a * b, [3.0f32, 4.0, 3.0]);
+        assert_eq!(a * 2.0, [2.0f32, 4.0, 6.0]);
+        assert_eq!(2.0f32 * &a, [2.0f32, 4.0, 6.0]);
+        assert_eq!(a / 2.0, [0.5f32, 1.0, 1.5]);
+        assert_eq!(a.dot(&b), 10.0f32);
+        assert_eq!(a.cross(&b), [-4.0f32, 8.0, -4.0]);
+        assert_eq!(a.length_squared(), 14.0f32);
+        assert_eq!(
+            Vector3::new([4.0f32, 0.0, 3.0]).unit_vector(),
+            [0.8f32, 0.0, 0.6]
+        );
+        assert!(Vector3::new([1e-9f32, 0.0, -1e-9]).near_zero());
+        assert!(!a.near_zero());
+
+        let mut c = a;
+        c += b;
+        c *= &b;
+        c /= 4.0;
+        assert_eq!(c, [3.0f32, 2.0, 1.0]);
+        c[1] = 0.5;
+        assert_eq!((c[0], c[1], c[2]), (3.0f32, 0.5, 1.0));
+
+        let n: Normal3<f32> = Normal3::new([0.0, 1.0, 0.0]);
+        let v = Vector3::new([1.0f32, -1.0, 0.0]);
+        assert_eq!(v.reflect(&n), [1.0f32, 1.0, 0.0]);
+        let p: Point3<f32> = Point3::new([1.0, 1.0, 1.0]);
+        assert_eq!(p + v - p, v);
+        let red: Color<f32> = Color::new([1.0, 0.0, 0.0]);
+        assert_eq!(red * Color::new([0.5, 0.5, 0.5]), [0.5f32, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_cast() {
+        let v = vec3![0.1, 1e-50, 3.0];
+        let rounded: Vector3<f32> = v.cast();
+        assert_eq!(rounded, [0.1f32, 0.0, 3.0]);
+        assert_eq!(rounded.cast::<f64>(), [0.1f32 as f64, 0.0, 3.0]);
+        // Out of range of f32.
+        assert_eq!(
+            vec3![1e40, -1e40, 0].cast::<f32>(),
+            [f32::INFINITY, f32::NEG_INFINITY, 0.0]
+        );
+
+        // Exact both ways for values of single precision.
+        let exact: Point3<f32> = Point3::new([0.5, -2.25, 1024.0]);
+        let wide: Point3 = exact.cast();
+        assert_eq!(wide, [0.5, -2.25, 1024.0]);
+        assert_eq!(wide.cast::<f32>(), exact);
+        assert_eq!(
+            color![0.25, 0.5, 1].cast::<f32>().cast::<f64>(),
+            color![0.25, 0.5, 1]
+        );
+        assert_eq!(Normal3::new([0.1f64; 3]).cast::<f64>(), [0.1; 3]);
+    }
 }
@@ -0,0 +1,84 @@
+use crate::integrator::background;
+use crate::progress::{count, Counter};
+use crate::{HitRecord, Hittable, Integrator};
+use common::ray::Ray;
+use common::sampler::Sampler;
+use common::sampling::sample_cosine_hemisphere;
+use common::{color, Color};
+
+/// Clay render: the fraction of the cosine-weighted hemisphere around the
+/// hit normal that is not blocked within `max_distance`.
+pub struct AmbientOcclusionIntegrator {
+    pub samples: usize,
+    pub max_distance: f64,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(samples: usize, max_distance: f64) -> Self
+    where
+        Self: Sized,
+    {
+        AmbientOcclusionIntegrator {
+            samples: samples.max(1),
+            max_distance,
+        }
+    }
+
+    /// Parse `ao[:samples[:max_distance]]`, e.g. `ao:32:2.5`. The samples
+    /// must be positive and the distance must not be negative.
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(':');
+        if parts.next() != Some("ao") {
+            return Err(format!("not an ambient occlusion spec: {}", spec));
+        }
+        let samples = match parts.next() {
+            Some(s) => s
+                .parse::<usize>()
+                .map_err(|e| format!("invalid ao samples {:?}: {}", s, e))?,
+            None => 16,
+        };
+        if samples == 0 {
+            return Err(format!("ao needs at least 1 sample: {}", spec));
+        }
+        let max_distance = match parts.next() {
+            Some(s) => s
+                .parse::<f64>()
+                .map_err(|e| format!("invalid ao distance {:?}: {}", s, e))?,
+            None => f64::INFINITY,
+        };
+        if max_distance.is_nan() || max_distance < 0.0 {
+            return Err(format!("ao distance must not be negative: {}", spec));
+        }
+        if parts.next().is_some() {
+            return Err(format!(
+                "too many fields in ao spec {:?}, expected ao[:samples[:max_distance]]",
+                spec
+            ));
+        }
+        Ok(AmbientOcclusionIntegrator::new(samples, max_distance))
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::new();
+        if !world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
+            return background(ray);
+        }
+
+        let mut shadow_rec = HitRecord::new();
+        let mut unoccluded = 0;
+        let frame = rec.shading_frame();
+        for _ in 0..self.samples {
+            let direction = frame.to_world(&sample_cosine_hemisphere(sampler.get_2d()));
+            let shadow_ray = Ray::with_time(&rec.p, &direction, ray.time);
+            count(Counter::ShadowRay);
+            if !world.hit(&shadow_ray, 0.001, self.max_distance, &mut shadow_rec) {
+                unoccluded += 1;
+            }
+        }
+
+        let visibility = unoccluded as f64 / self.samples as f64;
+        color![visibility, visibility, visibility]
+    }
+}
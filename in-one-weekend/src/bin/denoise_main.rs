@@ -0,0 +1,168 @@
+use common::denoise::{denoise, DenoiseSettings, Guides, MAX_ITERATIONS};
+use common::exr::{read_exr, write_exr, ExrChannel, ExrImage};
+use common::pfm::read_pfm;
+use common::Image;
+use std::env;
+use std::fs::File;
+use std::io::{stdout, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: denoise INPUT.exr|INPUT.pfm [--albedo FILE] [--normal FILE] \
+[--iterations N] [--output FILE.ppm|FILE.pfm|FILE.exr]";
+
+/// Denoise a render written with `--aov-exr` or `--aov-dir`.
+///
+/// The guides are taken from the `albedo` and `normal` layers of an EXR, or
+/// from `albedo.pfm` and `normal.pfm` next to a PFM, unless given. Writes a
+/// PPM to stdout without `--output`.
+fn main() -> ExitCode {
+    match try_run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn try_run() -> Result<(), String> {
+    let mut input = None;
+    let mut albedo_path = None;
+    let mut normal_path = None;
+    let mut output = None;
+    let mut settings = DenoiseSettings::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", flag))
+        };
+        match arg.as_str() {
+            "--albedo" => albedo_path = Some(value(&arg)?),
+            "--normal" => normal_path = Some(value(&arg)?),
+            "--output" => output = Some(value(&arg)?),
+            "--iterations" => {
+                let v = value(&arg)?;
+                settings.iterations = v
+                    .parse()
+                    .map_err(|e| format!("invalid value {:?} for {}: {}", v, arg, e))?;
+                if settings.iterations > MAX_ITERATIONS {
+                    return Err(format!("{} must be at most {}", arg, MAX_ITERATIONS));
+                }
+            }
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option: {}\n{}", arg, USAGE))
+            }
+            _ => input = Some(arg),
+        }
+    }
+    let input = input.ok_or(USAGE)?;
+
+    let (color, mut albedo, mut normal) = if is_exr(&input) {
+        let exr = read_exr(&mut open(&input)?).map_err(|e| format!("{}: {}", input, e))?;
+        let color = exr_layer(&exr, "").ok_or(format!("{} has no R, G, B channels", input))?;
+        (
+            color,
+            exr_layer(&exr, "albedo."),
+            exr_layer(&exr, "normal."),
+        )
+    } else {
+        let color = load(&input)?;
+        // Guides written next to the beauty image by `--aov-dir`.
+        let dir = Path::new(&input).parent().unwrap_or(Path::new(""));
+        let sibling = |name: &str| {
+            let path = dir.join(name);
+            match path.exists() && path != Path::new(&input) {
+                true => load(&path.to_string_lossy()).map(Some),
+                false => Ok(None),
+            }
+        };
+        (color, sibling("albedo.pfm")?, sibling("normal.pfm")?)
+    };
+    if let Some(path) = albedo_path {
+        albedo = Some(load(&path)?);
+    }
+    if let Some(path) = normal_path {
+        normal = Some(load(&path)?);
+    }
+    for (name, guide) in [("albedo", &albedo), ("normal", &normal)] {
+        if guide
+            .as_ref()
+            .is_some_and(|guide| (guide.width, guide.height) != (color.width, color.height))
+        {
+            return Err(format!("the {} guide has a different size", name));
+        }
+    }
+
+    let guides = Guides {
+        albedo: albedo.as_ref(),
+        normal: normal.as_ref(),
+        variance: None,
+    };
+    let denoised = denoise(&color, &guides, &settings);
+
+    let write_error = |e: std::io::Error| format!("write output: {}", e);
+    match output {
+        Some(path) => {
+            let mut os =
+                BufWriter::new(File::create(&path).map_err(|e| format!("{}: {}", path, e))?);
+            if is_exr(&path) {
+                let data = denoised.to_data();
+                let channels: Vec<ExrChannel> = ["R", "G", "B"]
+                    .iter()
+                    .enumerate()
+                    .map(|(c, name)| ExrChannel {
+                        name: name.to_string(),
+                        data: data.iter().skip(c).step_by(3).copied().collect(),
+                    })
+                    .collect();
+                write_exr(&mut os, denoised.width, denoised.height, &channels)
+            } else if path.ends_with(".pfm") {
+                denoised.write_pfm(&mut os)
+            } else {
+                denoised.write_ppm(&mut os)
+            }
+            .and_then(|_| os.flush())
+            .map_err(write_error)
+        }
+        None => denoised.write_ppm(&mut stdout()).map_err(write_error),
+    }
+}
+
+fn is_exr(path: &str) -> bool {
+    path.ends_with(".exr")
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+/// An image from a PFM, or from the `R`, `G`, `B` channels of an EXR.
+fn load(path: &str) -> Result<Image, String> {
+    if is_exr(path) {
+        let exr = read_exr(&mut open(path)?).map_err(|e| format!("{}: {}", path, e))?;
+        return exr_layer(&exr, "").ok_or(format!("{} has no R, G, B channels", path));
+    }
+    let pfm = read_pfm(&mut open(path)?).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(Image::from_data(
+        pfm.width,
+        pfm.height,
+        pfm.components,
+        &pfm.data,
+    ))
+}
+
+/// The channels `{prefix}R`, `{prefix}G` and `{prefix}B` of `exr`.
+fn exr_layer(exr: &ExrImage, prefix: &str) -> Option<Image> {
+    let channels = ["R", "G", "B"].map(|c| exr.channel(&format!("{}{}", prefix, c)));
+    let [r, g, b] = channels;
+    let (r, g, b) = (r?, g?, b?);
+    let data: Vec<f32> = (0..exr.width * exr.height)
+        .flat_map(|p| [r[p], g[p], b[p]])
+        .collect();
+    Some(Image::from_data(exr.width, exr.height, 3, &data))
+}
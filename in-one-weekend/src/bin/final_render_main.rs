@@ -1,156 +1,137 @@
-use common::color::write_color;
-use common::ray::Ray;
-use common::rtweekend::{random_double, random_double_range};
-use common::{color, point3, vec3, Camera, Color};
+use common::camera::{Bokeh, CameraSetup};
+use common::rng::Rng;
+use common::{color, point3, vec3, Color};
 use in_one_weekend::material::{Dielectric, Lambertian, Metal};
-use in_one_weekend::{HitRecord, Hittable, HittableList, Material, Sphere};
-use std::cell::RefCell;
-use std::io::{stdout, Write};
-use std::rc::Rc;
-
-fn main() {
-    // Image
-    const ASPECT_RATIO: f64 = 3.0 / 2.0;
-    const IMAGE_WIDTH: usize = 800;
-    const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
-    const SAMPLES_PER_PIXEL: usize = 30;
-    const MAX_DEPTH: i32 = 10;
-
-    // World
-    let world = random_scene();
-
+use in_one_weekend::run::{run, Defaults};
+use in_one_weekend::{HittableList, Material, Sphere, SphereList};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const ASPECT_RATIO: f64 = 3.0 / 2.0;
+const IMAGE_WIDTH: usize = 800;
+
+const DEFAULTS: Defaults = Defaults {
+    program: env!("CARGO_BIN_NAME"),
+    image_width: IMAGE_WIDTH,
+    image_height: (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize,
+    samples_per_pixel: 30,
+    max_depth: 10,
+};
+
+fn main() -> ExitCode {
     // Camera
     let lookfrom = point3![12, 2, 3];
     let lookat = point3![0, 0, -1];
     let vup = vec3![0, 1, 0];
-    let camera = Camera::new(lookfrom, lookat, vup, 20.0, ASPECT_RATIO, 0.1, 10.0);
-
-    let mut os = stdout();
-
-    os.write_all(format!("P3\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT).as_bytes())
-        .expect("Error: write ppm header");
-
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprintln!("Scanlines remaining: {}", j);
-        for i in 0..IMAGE_WIDTH {
-            let mut pixel_color = color![];
-            for _ in 0..SAMPLES_PER_PIXEL {
-                let u = (i as f64 + random_double()) / (IMAGE_WIDTH - 1) as f64;
-                let v = (j as f64 + random_double()) / (IMAGE_HEIGHT - 1) as f64;
-                let ray = camera.get_ray(u, v);
-                pixel_color += ray_color(&ray, &world, MAX_DEPTH);
-            }
-            write_color(&mut os, &pixel_color, SAMPLES_PER_PIXEL)
-                .expect("Error: write pixel color");
-        }
-    }
-    eprintln!("\nDone.");
+    let setup = CameraSetup {
+        lookfrom,
+        lookat,
+        vup,
+        vfov: 20.0,
+        aspect_ratio: ASPECT_RATIO,
+        aperture: 0.1,
+        focus_dist: 10.0,
+        bokeh: Bokeh::default(),
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+    };
+
+    run(&DEFAULTS, |options, _| {
+        (random_scene(options.seed), setup.clone())
+    })
 }
 
-fn ray_color(ray: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-    let mut rec = HitRecord::new();
-
-    // If we've exceeded the ray bounce limit, no more light is gathered.
-    if depth <= 0 {
-        return color!(0, 0, 0);
-    }
-
-    if world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
-        let mut scattered = Ray::new0();
-        let mut attenuation = color![];
-        if rec.mat_ptr.is_some() {
-            let mat_ptr = rec.mat_ptr.as_ref().unwrap().borrow();
-            if mat_ptr.scatter(ray, &rec, &mut attenuation, &mut scattered) {
-                return attenuation * ray_color(&scattered, world, depth - 1);
-            }
-        }
-        return color![];
-    }
-    let unit_direction = ray.direction.unit_vector();
-    let t = 0.5 * (unit_direction.y + 1.0);
-    color![1.0, 1.0, 1.0] * (1.0 - t) + color![0.5, 0.7, 1.0] * t
+/// A color with every component random in [min,max).
+fn random_color(rng: &mut Rng, min: f64, max: f64) -> Color {
+    let mut random = || min + (max - min) * rng.next_f64();
+    color![random(), random(), random()]
 }
 
-fn random_scene() -> HittableList {
+fn random_scene(seed: u64) -> HittableList {
+    let mut rng = Rng::new(seed);
     let mut world = HittableList::new();
 
-    let ground_material = Rc::new(RefCell::new(Lambertian {
+    let ground_material = Arc::new(Lambertian {
         albedo: color![0.5, 0.5, 0.5],
-    }));
-    world.add(Rc::new(RefCell::new(Sphere {
+    });
+    world.add(Arc::new(Sphere {
         center: point3![0.0, -1000.0, 0.0],
         radius: 1000.0,
         mat_ptr: Some(ground_material.clone()),
-    })));
+    }));
 
+    // The small spheres, tested four at a time.
+    let mut small = SphereList::new();
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_double();
+            let choose_mat = rng.next_f64();
             let center = point3![
-                a as f64 + 0.9 * random_double(),
+                a as f64 + 0.9 * rng.next_f64(),
                 0.2,
-                b as f64 + 0.9 * random_double()
+                b as f64 + 0.9 * rng.next_f64()
             ];
 
             if (center - point3![4.0, 0.2, 0.0]).length() > 0.9 {
-                let sphere_material: Rc<RefCell<dyn Material>>;
+                let sphere_material: Arc<dyn Material>;
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = Color::random() * Color::random();
-                    sphere_material = Rc::new(RefCell::new(Lambertian { albedo }));
-                    world.add(Rc::new(RefCell::new(Sphere {
+                    let albedo =
+                        random_color(&mut rng, 0.0, 1.0) * random_color(&mut rng, 0.0, 1.0);
+                    sphere_material = Arc::new(Lambertian { albedo });
+                    small.add(Arc::new(Sphere {
                         center,
                         radius: 0.2,
                         mat_ptr: Some(sphere_material.clone()),
-                    })));
+                    }));
                 } else if choose_mat < 0.95 {
                     // metal
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_double_range(0.0, 0.5);
-                    sphere_material = Rc::new(RefCell::new(Metal { albedo, fuzz }));
-                    world.add(Rc::new(RefCell::new(Sphere {
+                    let albedo = random_color(&mut rng, 0.5, 1.0);
+                    let fuzz = 0.5 * rng.next_f64();
+                    sphere_material = Arc::new(Metal { albedo, fuzz });
+                    small.add(Arc::new(Sphere {
                         center,
                         radius: 0.2,
                         mat_ptr: Some(sphere_material.clone()),
-                    })));
+                    }));
                 } else {
                     // glass
-                    sphere_material = Rc::new(RefCell::new(Dielectric { ir: 1.5 }));
-                    world.add(Rc::new(RefCell::new(Sphere {
+                    sphere_material = Arc::new(Dielectric { ir: 1.5 });
+                    small.add(Arc::new(Sphere {
                         center,
                         radius: 0.2,
                         mat_ptr: Some(sphere_material.clone()),
-                    })));
+                    }));
                 }
             }
         }
     }
+    world.add(Arc::new(small));
 
-    let material1 = Rc::new(RefCell::new(Dielectric { ir: 1.5 }));
-    world.add(Rc::new(RefCell::new(Sphere {
+    let material1 = Arc::new(Dielectric { ir: 1.5 });
+    world.add(Arc::new(Sphere {
         center: point3![0.0, 1.0, 0.0],
         radius: 1.0,
         mat_ptr: Some(material1.clone()),
-    })));
+    }));
 
-    let material2 = Rc::new(RefCell::new(Lambertian {
+    let material2 = Arc::new(Lambertian {
         albedo: color![0.4, 0.2, 0.1],
-    }));
-    world.add(Rc::new(RefCell::new(Sphere {
+    });
+    world.add(Arc::new(Sphere {
         center: point3![-4.0, 1.0, 0.0],
         radius: 1.0,
         mat_ptr: Some(material2.clone()),
-    })));
+    }));
 
-    let material3 = Rc::new(RefCell::new(Metal {
+    let material3 = Arc::new(Metal {
         albedo: color![0.7, 0.6, 0.5],
         fuzz: 0.0,
-    }));
-    world.add(Rc::new(RefCell::new(Sphere {
+    });
+    world.add(Arc::new(Sphere {
         center: point3![4.0, 1.0, 0.0],
         radius: 1.0,
         mat_ptr: Some(material3.clone()),
-    })));
+    }));
 
     world
 }
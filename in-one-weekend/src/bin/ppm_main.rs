@@ -1,119 +1,116 @@
-use common::color::write_color;
-use common::ray::Ray;
-use common::rtweekend::random_double;
-use common::{color, point3, vec3, Camera, Color};
+use common::animation::{CameraAnimation, Interpolation, ObjectAnimation, Track};
+use common::camera::{Bokeh, CameraSetup};
+use common::{color, point3, vec3};
 use in_one_weekend::material::{Dielectric, Lambertian, Metal};
-use in_one_weekend::{HitRecord, Hittable, HittableList, Sphere};
-use std::cell::RefCell;
-use std::io::{stdout, Write};
-use std::rc::Rc;
+use in_one_weekend::run::{run, Defaults};
+use in_one_weekend::{HittableList, Instance, Sphere};
+use std::process::ExitCode;
+use std::sync::Arc;
 
-fn main() {
-    // Image
-    const ASPECT_RATIO: f64 = 16.0 / 9.0;
-    const IMAGE_WIDTH: usize = 400;
-    const IMAGE_HEIGHT: usize = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize;
-    const SAMPLES_PER_PIXEL: usize = 100;
-    const MAX_DEPTH: i32 = 50;
+const ASPECT_RATIO: f64 = 16.0 / 9.0;
+const IMAGE_WIDTH: usize = 400;
 
-    // World
+const DEFAULTS: Defaults = Defaults {
+    program: env!("CARGO_BIN_NAME"),
+    image_width: IMAGE_WIDTH,
+    image_height: (IMAGE_WIDTH as f64 / ASPECT_RATIO) as usize,
+    samples_per_pixel: 100,
+    max_depth: 50,
+};
+
+fn main() -> ExitCode {
+    // Camera
+    let lookfrom = point3![3, 3, 2];
+    let lookat = point3![0, 0, -1];
+    let vup = vec3![0, 1, 0];
+    let dist_to_focus = (lookfrom - lookat).length();
+    let setup = CameraSetup {
+        lookfrom,
+        lookat,
+        vup,
+        vfov: 40.0,
+        aspect_ratio: ASPECT_RATIO,
+        aperture: 3.0,
+        focus_dist: dist_to_focus,
+        bokeh: Bokeh::default(),
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+    };
+    // Swings around the spheres in 4 seconds.
+    let orbit = [
+        (3.0, 3.0, 2.0),
+        (0.0, 2.0, 4.6),
+        (-3.0, 3.0, 2.0),
+        (0.0, 4.0, 4.6),
+        (3.0, 3.0, 2.0),
+    ];
+    let keys = orbit.iter().enumerate();
+    let animation = CameraAnimation::new(
+        Track::new(
+            Interpolation::Spline,
+            keys.map(|(i, &(x, y, z))| (i as f64, point3![x, y, z]))
+                .collect(),
+        ),
+        Track::constant(lookat),
+    );
+
+    run(&DEFAULTS, |_, time| {
+        (world(time), animation.setup_at(&setup, time))
+    })
+}
+
+/// The spheres at `time` seconds: the one in the middle bounces twice a
+/// second.
+fn world(time: f64) -> HittableList {
     let mut world = HittableList::new();
 
-    let material_ground = Rc::new(RefCell::new(Lambertian {
+    let material_ground = Arc::new(Lambertian {
         albedo: color![0.8, 0.8, 0.0],
-    }));
-    let material_center = Rc::new(RefCell::new(Lambertian {
+    });
+    let material_center = Arc::new(Lambertian {
         albedo: color![0.1, 0.2, 0.5],
-    }));
-    let material_left = Rc::new(RefCell::new(Dielectric { ir: 1.5 }));
-    let material_right = Rc::new(RefCell::new(Metal {
+    });
+    let material_left = Arc::new(Dielectric { ir: 1.5 });
+    let material_right = Arc::new(Metal {
         albedo: color![0.8, 0.6, 0.2],
         fuzz: 0.0,
-    }));
+    });
 
-    world.add(Rc::new(RefCell::new(Sphere {
+    let bounce = ObjectAnimation {
+        translation: Track::new(
+            Interpolation::Linear,
+            (0..=8)
+                .map(|i| (i as f64 / 4.0, vec3![0.0, 0.6 * (i % 2) as f64, 0.0]))
+                .collect(),
+        ),
+        ..Default::default()
+    };
+    let center = Arc::new(Sphere {
         center: point3![0.0, 0.0, -1.0],
         radius: 0.5,
         mat_ptr: Some(material_center.clone()),
-    })));
-    world.add(Rc::new(RefCell::new(Sphere {
+    });
+    world.add(Arc::new(Instance::animated(center, &bounce, time)));
+    world.add(Arc::new(Sphere {
         center: point3![0.0, -100.5, -1.0],
         radius: 100.0,
         mat_ptr: Some(material_ground.clone()),
-    })));
-    world.add(Rc::new(RefCell::new(Sphere {
+    }));
+    world.add(Arc::new(Sphere {
         center: point3![-1.0, 0.0, -1.0],
         radius: 0.5,
         mat_ptr: Some(material_left.clone()),
-    })));
-    world.add(Rc::new(RefCell::new(Sphere {
+    }));
+    world.add(Arc::new(Sphere {
         center: point3![-1.0, 0.0, -1.0],
         radius: -0.4,
         mat_ptr: Some(material_left.clone()),
-    })));
-    world.add(Rc::new(RefCell::new(Sphere {
+    }));
+    world.add(Arc::new(Sphere {
         center: point3![1.0, 0.0, -1.0],
         radius: 0.5,
         mat_ptr: Some(material_right.clone()),
-    })));
-
-    // Camera
-    let lookfrom = point3![3, 3, 2];
-    let lookat = point3![0, 0, -1];
-    let vup = vec3![0, 1, 0];
-    let dist_to_focus = (lookfrom - lookat).length();
-    let camera = Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        40.0,
-        ASPECT_RATIO,
-        3.0,
-        dist_to_focus,
-    );
-
-    let mut os = stdout();
-
-    os.write_all(format!("P3\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT).as_bytes())
-        .expect("Error: write ppm header");
-
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprintln!("Scanlines remaining: {}", j);
-        for i in 0..IMAGE_WIDTH {
-            let mut pixel_color = color![];
-            for _ in 0..SAMPLES_PER_PIXEL {
-                let u = (i as f64 + random_double()) / (IMAGE_WIDTH - 1) as f64;
-                let v = (j as f64 + random_double()) / (IMAGE_HEIGHT - 1) as f64;
-                let ray = camera.get_ray(u, v);
-                pixel_color += ray_color(&ray, &world, MAX_DEPTH);
-            }
-            write_color(&mut os, &pixel_color, SAMPLES_PER_PIXEL)
-                .expect("Error: write pixel color");
-        }
-    }
-    eprintln!("\nDone.");
-}
-
-fn ray_color(ray: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-    let mut rec = HitRecord::new();
-
-    // If we've exceeded the ray bounce limit, no more light is gathered.
-    if depth <= 0 {
-        return color!(0, 0, 0);
-    }
+    }));
 
-    if world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
-        let mut scattered = Ray::new0();
-        let mut attenuation = color![];
-        if rec.mat_ptr.is_some() {
-            let mat_ptr = rec.mat_ptr.as_ref().unwrap().borrow();
-            if mat_ptr.scatter(ray, &rec, &mut attenuation, &mut scattered) {
-                return attenuation * ray_color(&scattered, world, depth - 1);
-            }
-        }
-        return color![];
-    }
-    let unit_direction = ray.direction.unit_vector();
-    let t = 0.5 * (unit_direction.y + 1.0);
-    color![1.0, 1.0, 1.0] * (1.0 - t) + color![0.5, 0.7, 1.0] * t
+    world
 }
@@ -0,0 +1,174 @@
+use crate::progress::RenderStats;
+use crate::{Hittable, RenderSettings, Renderer};
+use common::binary::{read_u64, write_u64};
+use common::hash::{write_f64s, Fnv1a};
+use common::Film;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"RTCKPT\x00\x02";
+
+/// Hash of the geometry and materials of `world`.
+pub fn scene_hash(world: &dyn Hittable) -> u64 {
+    let mut hasher = Fnv1a::new();
+    world.fingerprint(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of `settings` and `config`, a description of everything else that
+/// affects the image, e.g. the camera, sampler and integrator. The number of
+/// threads is left out, as the image does not depend on it.
+pub fn settings_hash(settings: &RenderSettings, config: &str) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(config.as_bytes());
+    hasher.write_usize(settings.image_width);
+    hasher.write_usize(settings.image_height);
+    hasher.write_usize(settings.samples_per_pixel);
+    hasher.write_u8(settings.aovs as u8);
+    if let Some(adaptive) = settings.adaptive {
+        write_f64s(&mut hasher, &[adaptive.threshold]);
+        hasher.write_usize(adaptive.min_samples);
+        hasher.write_usize(adaptive.max_samples);
+    }
+    hasher.write_u64(settings.filter.radius().to_bits());
+    hasher.finish()
+}
+
+/// Writes the state of a progressive render to a file between passes, and
+/// restores it to continue the render later.
+///
+/// A checkpoint holds the film (accumulated pixels, sample counts and
+/// statistics), the number of samples per pixel done, the [`RenderStats`]
+/// so far and hashes of the scene and of the settings. The samplers derive
+/// every sample from the seed, the pixel and the sample index alone, so this
+/// is all their state.
+pub struct Checkpointer {
+    pub path: PathBuf,
+    /// Least time between checkpoints; only on interruption if `None`.
+    pub interval: Option<Duration>,
+    scene_hash: u64,
+    /// See [`settings_hash`].
+    config: String,
+    last: Instant,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Checkpointer {
+    pub fn new(path: impl Into<PathBuf>, scene_hash: u64, config: &str) -> Self {
+        Checkpointer {
+            path: path.into(),
+            interval: None,
+            scene_hash,
+            config: config.to_string(),
+            last: Instant::now(),
+            interrupted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Set to stop the render with a checkpoint after the current pass.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.interrupted.clone()
+    }
+
+    /// Make Ctrl-C stop the render after the current pass; pressing it again
+    /// exits right away. The checkpoint written is reported through the
+    /// renderer's progress reporter.
+    pub fn handle_ctrl_c(&self) -> Result<(), String> {
+        let interrupted = self.interrupt_flag();
+        ctrlc::set_handler(move || {
+            if interrupted.swap(true, Ordering::SeqCst) {
+                exit(130);
+            }
+        })
+        .map_err(|e| format!("cannot handle Ctrl-C: {}", e))
+    }
+
+    /// Called after every pass: write a checkpoint if one is due or the
+    /// render was interrupted, and tell `renderer.progress`. Returns whether
+    /// to stop.
+    pub fn after_pass(&mut self, renderer: &mut Renderer) -> bool {
+        let interrupted = self.interrupted.load(Ordering::SeqCst);
+        if interrupted || self.interval.is_some_and(|i| self.last.elapsed() >= i) {
+            let message = match self.write(renderer) {
+                Ok(()) if interrupted => {
+                    format!("Interrupted, checkpoint written to {}", self.path.display())
+                }
+                Ok(()) => format!("Checkpoint written to {}", self.path.display()),
+                // Rather keep rendering than lose the work done so far.
+                Err(e) => format!("Error: write checkpoint: {}", e),
+            };
+            renderer.progress.message(&message);
+            self.last = Instant::now();
+        }
+        interrupted
+    }
+
+    /// Replace the checkpoint file, going through a temporary file so that
+    /// it always holds a complete checkpoint.
+    pub fn write(&self, renderer: &Renderer) -> io::Result<()> {
+        let mut partial = self.path.as_os_str().to_owned();
+        partial.push(".partial");
+        {
+            let mut os = BufWriter::new(File::create(&partial)?);
+            os.write_all(MAGIC)?;
+            write_u64(&mut os, self.scene_hash)?;
+            write_u64(&mut os, settings_hash(&renderer.settings, &self.config))?;
+            write_u64(&mut os, renderer.samples_done as u64)?;
+            renderer.stats.write(&mut os)?;
+            renderer.film.write_state(&mut os)?;
+            os.flush()?;
+        }
+        fs::rename(&partial, &self.path)
+    }
+
+    /// Continue the render from the checkpoint file, refusing if it was made
+    /// for another scene or other settings.
+    pub fn resume(&self, renderer: &mut Renderer) -> Result<(), String> {
+        let error = |e: io::Error| format!("cannot read checkpoint {}: {}", self.path.display(), e);
+        let mut is = BufReader::new(File::open(&self.path).map_err(error)?);
+        let mut magic = [0; 8];
+        is.read_exact(&mut magic).map_err(error)?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a checkpoint", self.path.display()));
+        }
+        if read_u64(&mut is).map_err(error)? != self.scene_hash {
+            return Err(format!(
+                "checkpoint {} was made for a different scene",
+                self.path.display()
+            ));
+        }
+        if read_u64(&mut is).map_err(error)? != settings_hash(&renderer.settings, &self.config) {
+            return Err(format!(
+                "checkpoint {} was made with different settings",
+                self.path.display()
+            ));
+        }
+        let samples_done = read_u64(&mut is).map_err(error)?;
+        if samples_done > renderer.settings.max_samples_per_pixel() as u64 {
+            return Err(format!(
+                "checkpoint {} has more samples than the render",
+                self.path.display()
+            ));
+        }
+        let stats = RenderStats::read(&mut is).map_err(error)?;
+        let settings = &renderer.settings;
+        let film = Film::read_state(
+            &mut is,
+            settings.image_width,
+            settings.image_height,
+            settings.filter.clone(),
+        )
+        .map_err(error)?;
+
+        renderer.film = film;
+        renderer.samples_done = samples_done as usize;
+        renderer.stats = stats;
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use crate::integrator::background;
+use crate::progress::{count, Counter};
+use crate::{HitRecord, Hittable, Integrator};
+use common::hash::Fnv1a;
+use common::ray::Ray;
+use common::rng::mix_bits;
+use common::rtweekend::clamp;
+use common::sampler::Sampler;
+use common::{color, Color, Vector3};
+use std::hash::Hasher;
+use std::str::FromStr;
+
+/// Non-physical render modes for inspecting a scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugMode {
+    ShadingNormal,
+    GeometricNormal,
+    /// Tangent of the shading frame.
+    Tangent,
+    /// Hit distance, white at the camera and black at `max_distance`.
+    Depth {
+        max_distance: f64,
+    },
+    Albedo,
+    Uv,
+    MaterialId,
+    ObjectId,
+    /// Number of bounces before the path escaped or was absorbed.
+    Bounces {
+        max_depth: i32,
+    },
+}
+
+impl FromStr for DebugMode {
+    type Err = String;
+
+    /// A mode name, with `depth[:max_distance]` taking the distance shown
+    /// black, 20 if not given.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("depth", max_distance)) => {
+                let max_distance = max_distance
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid depth distance {:?}: {}", max_distance, e))?;
+                if max_distance.is_nan() || max_distance <= 0.0 {
+                    return Err(format!("depth distance must be positive: {}", s));
+                }
+                return Ok(DebugMode::Depth { max_distance });
+            }
+            Some(_) => return Err(format!("unknown debug mode: {}", s)),
+            None => {}
+        }
+        match s {
+            "normal" | "shading-normal" => Ok(DebugMode::ShadingNormal),
+            "geometric-normal" => Ok(DebugMode::GeometricNormal),
+            "tangent" => Ok(DebugMode::Tangent),
+            "depth" => Ok(DebugMode::Depth { max_distance: 20.0 }),
+            "albedo" => Ok(DebugMode::Albedo),
+            "uv" => Ok(DebugMode::Uv),
+            "material-id" => Ok(DebugMode::MaterialId),
+            "object-id" => Ok(DebugMode::ObjectId),
+            "bounces" => Ok(DebugMode::Bounces { max_depth: 50 }),
+            _ => Err(format!("unknown debug mode: {}", s)),
+        }
+    }
+}
+
+pub struct DebugIntegrator {
+    pub mode: DebugMode,
+}
+
+impl DebugIntegrator {
+    pub fn new(mode: DebugMode) -> Self
+    where
+        Self: Sized,
+    {
+        DebugIntegrator { mode }
+    }
+
+    /// Bounces of the path continuing from `rec`, the first hit of `ray`.
+    fn bounces(
+        ray: &Ray,
+        rec: &mut HitRecord,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        max_depth: i32,
+    ) -> i32 {
+        let mut ray = Ray::with_time(&ray.origin, &ray.direction, ray.time);
+        let mut depth = 0;
+
+        while depth < max_depth {
+            let mut scattered = Ray::new0();
+            let mut attenuation = color![];
+            let scatters = match &rec.mat_ptr {
+                Some(mat_ptr) => {
+                    mat_ptr.scatter(&ray, rec, &mut attenuation, &mut scattered, sampler)
+                }
+                None => false,
+            };
+            if !scatters {
+                break;
+            }
+            ray = scattered;
+            depth += 1;
+            if depth == max_depth {
+                break;
+            }
+            count(Counter::SecondaryRay);
+            if !world.hit(&ray, 0.001, f64::INFINITY, rec) {
+                break;
+            }
+        }
+        depth
+    }
+}
+
+/// Map a unit vector to [0,1]^3.
+fn normal_color(n: &Vector3) -> Color {
+    0.5 * (Color::from(*n) + color![1.0, 1.0, 1.0])
+}
+
+/// Pseudo random but stable color for an integer id.
+pub fn id_color(id: u64) -> Color {
+    let z = mix_bits(id.wrapping_add(0x9e3779b97f4a7c15));
+    color![
+        (z & 0xff) as f64 / 255.0,
+        ((z >> 8) & 0xff) as f64 / 255.0,
+        ((z >> 16) & 0xff) as f64 / 255.0
+    ]
+}
+
+/// Blue -> green -> red ramp for t in [0,1].
+pub fn heatmap(t: f64) -> Color {
+    let t = clamp(t, 0.0, 1.0);
+    if t < 0.5 {
+        let s = t * 2.0;
+        color![0.0, s, 1.0 - s]
+    } else {
+        let s = (t - 0.5) * 2.0;
+        color![s, 1.0 - s, 0.0]
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::new();
+        if !world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
+            return match self.mode {
+                DebugMode::Albedo => background(ray),
+                DebugMode::Bounces { .. } => heatmap(0.0),
+                _ => color![],
+            };
+        }
+
+        match self.mode {
+            DebugMode::ShadingNormal => normal_color(&rec.normal.into()),
+            DebugMode::GeometricNormal => normal_color(&rec.geometric_normal.into()),
+            DebugMode::Tangent => normal_color(&rec.tangent),
+            DebugMode::Depth { max_distance } => {
+                let distance = rec.t * ray.direction.length();
+                let d = 1.0 - clamp(distance / max_distance, 0.0, 1.0);
+                color![d, d, d]
+            }
+            DebugMode::Albedo => match &rec.mat_ptr {
+                Some(mat_ptr) => mat_ptr.albedo(&rec),
+                None => color![],
+            },
+            DebugMode::Uv => color![rec.u, rec.v, 0.0],
+            DebugMode::MaterialId => match &rec.mat_ptr {
+                Some(mat_ptr) => {
+                    // Hash the parameters rather than take the address, which
+                    // differs between runs and processes.
+                    let mut hasher = Fnv1a::new();
+                    mat_ptr.fingerprint(&mut hasher);
+                    id_color(hasher.finish())
+                }
+                None => color![],
+            },
+            DebugMode::ObjectId => id_color(rec.object_id as u64),
+            DebugMode::Bounces { max_depth } => {
+                let bounces = DebugIntegrator::bounces(ray, &mut rec, world, sampler, max_depth);
+                heatmap(bounces as f64 / max_depth.max(1) as f64)
+            }
+        }
+    }
+}
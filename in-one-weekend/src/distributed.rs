@@ -0,0 +1,272 @@
+use crate::progress::RenderStats;
+use crate::renderer::{Tile, TileMerger};
+use crate::{Hittable, Integrator, Renderer};
+use common::binary::{read_u64, write_u64};
+use common::Film;
+use std::collections::VecDeque;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const MAGIC: &[u8; 8] = b"RTDIST\x00\x01";
+
+/// Job index telling a worker that there is nothing left to do.
+const SHUTDOWN: u64 = u64::MAX;
+
+/// How often idle threads look for work or connections.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Default of [`Coordinator::timeout`].
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+/// What the connection threads tell the thread merging the tiles.
+enum Event {
+    Tile(usize, Film, RenderStats),
+    /// For the progress reporter.
+    Message(String),
+}
+
+/// Renders the tiles of an image on worker processes.
+///
+/// Workers connect over TCP, one connection per worker thread. Both sides
+/// build the scene and settings themselves; the coordinator only sends tile
+/// indices and the worker answers with the tile's film and statistics. A
+/// connection whose hashes of the scene and settings do not match is turned
+/// away. If a worker disconnects or times out, its tile goes back to the
+/// queue for another worker.
+pub struct Coordinator {
+    /// Longest wait for the handshake of a worker, the answer to a tile or
+    /// a write. A worker taking longer is dropped as if it had disconnected.
+    pub timeout: Duration,
+    listener: TcpListener,
+    scene_hash: u64,
+    settings_hash: u64,
+}
+
+impl Coordinator {
+    pub fn bind(addr: impl ToSocketAddrs, scene_hash: u64, settings_hash: u64) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Coordinator {
+            timeout: TIMEOUT,
+            listener,
+            scene_hash,
+            settings_hash,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Render every tile of `renderer` on the workers into its film. Returns
+    /// once all tiles are merged, waiting for workers as long as needed.
+    pub fn render(&self, renderer: &mut Renderer, integrator: &dyn Integrator) {
+        renderer.enable_aovs(integrator);
+        let tiles = renderer.tile_regions();
+        renderer.stats = RenderStats::default();
+        let queue = Mutex::new((0..tiles.len()).collect::<VecDeque<usize>>());
+        let done = AtomicBool::new(false);
+        let template = renderer.film.new_like(0, 0);
+        let film = &mut renderer.film;
+        let (progress, stats) = (&mut renderer.progress, &mut renderer.stats);
+
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            let (queue, done, tiles, template) = (&queue, &done, &tiles, &template);
+            scope.spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    let (stream, addr) = match self.listener.accept() {
+                        Ok(connection) => connection,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => {
+                            let _ = tx.send(Event::Message(format!("Error: accept worker: {}", e)));
+                            continue;
+                        }
+                    };
+                    let tx = tx.clone();
+                    scope.spawn(move || {
+                        let connection = Connection {
+                            queue,
+                            done,
+                            tiles,
+                            template,
+                        };
+                        if let Err(e) = connection.serve(stream, self, &tx) {
+                            let _ =
+                                tx.send(Event::Message(format!("Worker {} failed: {}", addr, e)));
+                        }
+                    });
+                }
+            });
+
+            progress.start(tiles.len());
+            if let Ok(addr) = self.local_addr() {
+                progress.message(&format!("Waiting for workers on {}", addr));
+            }
+            let mut merger = TileMerger::new(tiles);
+            while merger.remaining() > 0 {
+                match rx.recv().unwrap() {
+                    Event::Tile(index, tile_film, tile_stats) => {
+                        merger.add(film, index, tile_film);
+                        *stats += tile_stats;
+                        progress.tile_done(stats);
+                    }
+                    Event::Message(message) => progress.message(&message),
+                }
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+        renderer.progress.finish(&renderer.stats, true);
+    }
+}
+
+/// The coordinator's side of one worker connection.
+struct Connection<'a> {
+    queue: &'a Mutex<VecDeque<usize>>,
+    done: &'a AtomicBool,
+    tiles: &'a [(Tile, Tile)],
+    template: &'a Film,
+}
+
+impl Connection<'_> {
+    fn serve(
+        &self,
+        stream: TcpStream,
+        coordinator: &Coordinator,
+        tx: &mpsc::Sender<Event>,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(coordinator.timeout))?;
+        stream.set_write_timeout(Some(coordinator.timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        writer.write_all(MAGIC)?;
+        write_u64(&mut writer, coordinator.scene_hash)?;
+        write_u64(&mut writer, coordinator.settings_hash)?;
+        writer.flush()?;
+        if read_u64(&mut reader)? != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "worker has a different scene or settings",
+            ));
+        }
+
+        loop {
+            let index = match self.queue.lock().unwrap().pop_front() {
+                Some(index) => index,
+                None if self.done.load(Ordering::SeqCst) => {
+                    write_u64(&mut writer, SHUTDOWN)?;
+                    return writer.flush();
+                }
+                // Other workers may still fail and hand their tiles back.
+                None => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+            };
+            match self.run_job(&mut reader, &mut writer, index) {
+                Ok((tile_film, stats)) => {
+                    let _ = tx.send(Event::Tile(index, tile_film, stats));
+                }
+                Err(e) => {
+                    let _ = tx.send(Event::Message(format!("Reassigning tile {}", index)));
+                    self.queue.lock().unwrap().push_back(index);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn run_job(
+        &self,
+        reader: &mut dyn Read,
+        writer: &mut BufWriter<TcpStream>,
+        index: usize,
+    ) -> io::Result<(Film, RenderStats)> {
+        write_u64(writer, index as u64)?;
+        writer.flush()?;
+        let region = self.tiles[index].1;
+        let tile_film = Film::read_state(
+            reader,
+            region.width(),
+            region.height(),
+            self.template.filter().clone(),
+        )?;
+        Ok((tile_film, RenderStats::read(reader)?))
+    }
+}
+
+/// Render tiles for the coordinator at `addr` until it has no more, with
+/// `renderer.settings.threads` connections in parallel.
+pub fn run_worker(
+    addr: &str,
+    renderer: &mut Renderer,
+    world: &dyn Hittable,
+    integrator: &dyn Integrator,
+    scene_hash: u64,
+    settings_hash: u64,
+) -> io::Result<()> {
+    renderer.enable_aovs(integrator);
+    let tiles = renderer.tile_regions();
+    let samples = 0..renderer.settings.max_samples_per_pixel();
+    let pass = renderer.pass(world, integrator, samples, None);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..renderer.settings.threads.max(1))
+            .map(|_| {
+                let mut sampler = renderer.sampler.clone_sampler();
+                let (tiles, pass) = (&tiles, &pass);
+                scope.spawn(move || -> io::Result<()> {
+                    let stream = TcpStream::connect(addr)?;
+                    stream.set_read_timeout(Some(TIMEOUT))?;
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    let mut reader = BufReader::new(stream.try_clone()?);
+                    let mut writer = BufWriter::new(stream);
+
+                    let mut magic = [0; 8];
+                    reader.read_exact(&mut magic)?;
+                    let matches = &magic == MAGIC
+                        && read_u64(&mut reader)? == scene_hash
+                        && read_u64(&mut reader)? == settings_hash;
+                    write_u64(&mut writer, matches as u64)?;
+                    writer.flush()?;
+                    if !matches {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "coordinator renders a different scene or settings",
+                        ));
+                    }
+                    // The coordinator may keep a worker waiting for a job
+                    // until the other workers have finished theirs.
+                    reader.get_ref().set_read_timeout(None)?;
+
+                    loop {
+                        let index = read_u64(&mut reader)?;
+                        if index == SHUTDOWN {
+                            return Ok(());
+                        }
+                        let (tile, region) = *tiles.get(index as usize).ok_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidData, "no such tile")
+                        })?;
+                        let (tile_film, stats) = pass.render_tile(sampler.as_mut(), &tile, &region);
+                        tile_film.write_state(&mut writer)?;
+                        stats.write(&mut writer)?;
+                        writer.flush()?;
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<io::Result<Vec<()>>>()
+            .map(|_| ())
+    })
+}
@@ -1,16 +1,28 @@
 use crate::Material;
 use common::ray::Ray;
-use common::{point3, vec3, Point3, Vec3};
-use std::cell::RefCell;
-use std::rc::Rc;
+use common::{normal3, point3, vec3, Normal3, Onb, Point3, Vector3};
+use std::hash::Hasher;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
-    pub normal: Vec3,
-    pub mat_ptr: Option<Rc<RefCell<dyn Material>>>,
+    /// Shading normal, always facing against the incoming ray.
+    pub normal: Normal3,
+    /// Outward surface normal as computed by the shape.
+    pub geometric_normal: Normal3,
+    /// Unit tangent and bitangent completing `normal` to the right-handed
+    /// shading frame, see [`HitRecord::shading_frame`].
+    pub tangent: Vector3,
+    pub bitangent: Vector3,
+    pub mat_ptr: Option<Arc<dyn Material>>,
     pub t: f64,
+    /// Surface coordinates of the hit point.
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    /// Index of the object in the top-level `HittableList`.
+    pub object_id: usize,
 }
 
 impl HitRecord {
@@ -20,23 +32,63 @@ impl HitRecord {
     {
         HitRecord {
             p: point3![0.0, 0.0, 0.0],
-            normal: vec3![0.0, 0.0, 0.0],
+            normal: normal3![0.0, 0.0, 0.0],
+            geometric_normal: normal3![0.0, 0.0, 0.0],
+            tangent: vec3![0.0, 0.0, 0.0],
+            bitangent: vec3![0.0, 0.0, 0.0],
             mat_ptr: None,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
+            object_id: 0,
         }
     }
 
-    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Vec3) {
-        self.front_face = ray.direction.dot(outward_normal) < 0.0;
+    pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: &Normal3) {
+        self.geometric_normal = *outward_normal;
+        self.front_face = ray.direction.dot(&Vector3::from(*outward_normal)) < 0.0;
         self.normal = if self.front_face {
             *outward_normal
         } else {
             -*outward_normal
         };
+        self.set_frame(Onb::from_w(&self.normal.into()));
+    }
+
+    /// Turn the shading frame about the normal so that the tangent points
+    /// along `tangent`, e.g. the direction of increasing `u`. Call after
+    /// [`HitRecord::set_face_normal`].
+    pub fn set_tangent(&mut self, tangent: &Vector3) {
+        self.set_frame(Onb::from_w_u(&self.normal.into(), tangent));
+    }
+
+    /// The tangent, bitangent and normal as the `u`, `v` and `w` of a basis,
+    /// for sampling and evaluating BSDFs in local coordinates.
+    pub fn shading_frame(&self) -> Onb {
+        Onb {
+            u: self.tangent,
+            v: self.bitangent,
+            w: self.normal.into(),
+        }
+    }
+
+    fn set_frame(&mut self, frame: Onb) {
+        self.tangent = frame.u;
+        self.bitangent = frame.v;
     }
 }
 
-pub trait Hittable {
+impl Default for HitRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+
+    /// Feed everything that affects the rendered image into `hasher`, see
+    /// [`crate::checkpoint::scene_hash`].
+    fn fingerprint(&self, hasher: &mut dyn Hasher);
 }
@@ -1,10 +1,11 @@
+use crate::progress::{count, Counter};
 use crate::{HitRecord, Hittable};
 use common::ray::Ray;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::hash::Hasher;
+use std::sync::Arc;
 
 pub struct HittableList {
-    pub objects: Vec<Rc<RefCell<dyn Hittable>>>,
+    pub objects: Vec<Arc<dyn Hittable>>,
 }
 
 impl HittableList {
@@ -21,28 +22,41 @@ impl HittableList {
         self.objects.clear();
     }
 
-    pub fn add(&mut self, object: Rc<RefCell<dyn Hittable>>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
         self.objects.push(object);
     }
 }
 
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        count(Counter::IntersectionTest);
         let mut temp_rec = HitRecord::new();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
-        for object in &self.objects {
-            if object
-                .borrow()
-                .hit(ray, t_min, closest_so_far, &mut temp_rec)
-            {
+        for (id, object) in self.objects.iter().enumerate() {
+            if object.hit(ray, t_min, closest_so_far, &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
+                temp_rec.object_id = id;
                 *rec = temp_rec.clone();
             }
         }
 
         hit_anything
     }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"list");
+        hasher.write_usize(self.objects.len());
+        for object in &self.objects {
+            object.fingerprint(hasher);
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use crate::progress::{count, Counter};
+use crate::{HitRecord, Hittable};
+use common::animation::ObjectAnimation;
+use common::hash::write_f64s;
+use common::ray::Ray;
+use common::Transform;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// An object placed in the scene by a transform from its own space.
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    transform: Transform,
+    to_object: Transform,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        Instance {
+            object,
+            transform,
+            to_object: transform.inverse(),
+        }
+    }
+
+    /// `object` placed by `animation` at `time`.
+    pub fn animated(object: Arc<dyn Hittable>, animation: &ObjectAnimation, time: f64) -> Self {
+        Instance::new(object, animation.transform_at(time))
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        count(Counter::IntersectionTest);
+        if self.transform.is_identity() {
+            return self.object.hit(ray, t_min, t_max, rec);
+        }
+        // The transform is affine, so distances along the ray stay the same
+        // in the space of the object.
+        if !self.object.hit(&self.to_object.ray(ray), t_min, t_max, rec) {
+            return false;
+        }
+
+        // Which side the ray comes from does not change.
+        rec.p = self.transform.point(&rec.p);
+        rec.normal = self.transform.normal(&rec.normal).unit_vector();
+        rec.geometric_normal = self.transform.normal(&rec.geometric_normal).unit_vector();
+        let tangent = self.transform.vector(&rec.tangent);
+        rec.set_tangent(&tangent);
+        true
+    }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"instance");
+        for row in &self.transform.matrix().m {
+            write_f64s(hasher, row);
+        }
+        self.object.fingerprint(hasher);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::ambient_occlusion::AmbientOcclusionIntegrator;
+use crate::debug_integrator::{DebugIntegrator, DebugMode};
+use crate::progress::{count, Counter};
+use crate::{HitRecord, Hittable};
+use common::film::AovSample;
+use common::ray::Ray;
+use common::sampler::Sampler;
+use common::{color, Color};
+
+/// Computes the radiance arriving along a ray from the scene.
+pub trait Integrator: Sync {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color;
+
+    /// Names and component counts of the AOVs written by `radiance_with_aovs`.
+    fn aovs(&self) -> Vec<(&'static str, usize)> {
+        Vec::new()
+    }
+
+    /// Like `radiance`, additionally filling in the AOVs of this sample.
+    fn radiance_with_aovs(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        _aovs: &mut AovSample,
+    ) -> Color {
+        self.radiance(ray, world, sampler)
+    }
+}
+
+/// Sky gradient seen by rays that escape the scene.
+pub fn background(ray: &Ray) -> Color {
+    let unit_direction = ray.direction.unit_vector();
+    let t = 0.5 * (unit_direction.y + 1.0);
+    color![1.0, 1.0, 1.0] * (1.0 - t) + color![0.5, 0.7, 1.0] * t
+}
+
+/// Recursive path tracer of the book.
+pub struct PathIntegrator {
+    pub max_depth: i32,
+}
+
+impl PathIntegrator {
+    pub fn new(max_depth: i32) -> Self
+    where
+        Self: Sized,
+    {
+        PathIntegrator { max_depth }
+    }
+
+    fn ray_color(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        depth: i32,
+    ) -> Color {
+        let mut rec = HitRecord::new();
+
+        // If we've exceeded the ray bounce limit, no more light is gathered.
+        if depth <= 0 {
+            return color!(0, 0, 0);
+        }
+
+        if depth < self.max_depth {
+            count(Counter::SecondaryRay);
+        }
+        if world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
+            let mut scattered = Ray::new0();
+            let mut attenuation = color![];
+            if let Some(mat_ptr) = &rec.mat_ptr {
+                if mat_ptr.scatter(ray, &rec, &mut attenuation, &mut scattered, sampler) {
+                    return attenuation * self.ray_color(&scattered, world, sampler, depth - 1);
+                }
+            }
+            return color![];
+        }
+        background(ray)
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(&self, ray: &Ray, world: &dyn Hittable, sampler: &mut dyn Sampler) -> Color {
+        self.ray_color(ray, world, sampler, self.max_depth)
+    }
+
+    fn aovs(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("albedo", 3),
+            ("normal", 3),
+            ("depth", 1),
+            ("diffuse_direct", 3),
+            ("diffuse_indirect", 3),
+            ("specular", 3),
+        ]
+    }
+
+    /// The first bounce is traced here so its contribution can be split by
+    /// lobe (diffuse or specular) and by whether the scattered ray directly
+    /// reaches the sky (direct) or bounces further (indirect).
+    fn radiance_with_aovs(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        aovs: &mut AovSample,
+    ) -> Color {
+        let mut rec = HitRecord::new();
+        if self.max_depth <= 0 {
+            return color![];
+        }
+        if !world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
+            return background(ray);
+        }
+
+        aovs.set("normal", rec.normal.into());
+        let distance = rec.t * ray.direction.length();
+        aovs.set("depth", color![distance, distance, distance]);
+
+        let mat_ptr = match &rec.mat_ptr {
+            Some(mat_ptr) => mat_ptr,
+            None => return color![],
+        };
+        aovs.set("albedo", mat_ptr.albedo(&rec));
+
+        let mut scattered = Ray::new0();
+        let mut attenuation = color![];
+        if !mat_ptr.scatter(ray, &rec, &mut attenuation, &mut scattered, sampler) {
+            return color![];
+        }
+
+        // Traced once more by `ray_color`, this is only a visibility test.
+        count(Counter::ShadowRay);
+        let mut next_rec = HitRecord::new();
+        let direct = !world.hit(&scattered, 0.001, f64::INFINITY, &mut next_rec);
+        let color = attenuation * self.ray_color(&scattered, world, sampler, self.max_depth - 1);
+        let lobe = if mat_ptr.is_specular() {
+            "specular"
+        } else if direct {
+            "diffuse_direct"
+        } else {
+            "diffuse_indirect"
+        };
+        aovs.set(lobe, color);
+        color
+    }
+}
+
+/// AOVs splitting the radiance by lobe, which add up to the beauty image.
+pub const LIGHTING_AOVS: [&str; 3] = ["diffuse_direct", "diffuse_indirect", "specular"];
+
+/// Select an integrator by name: `path`, `ao[:samples[:max_distance]]` or
+/// one of the [`DebugMode`] names.
+pub fn from_name(name: &str, max_depth: i32) -> Result<Box<dyn Integrator>, String> {
+    match name {
+        "path" => Ok(Box::new(PathIntegrator::new(max_depth))),
+        _ if name == "ao" || name.starts_with("ao:") => {
+            Ok(Box::new(AmbientOcclusionIntegrator::from_spec(name)?))
+        }
+        _ => {
+            let mode = match name.parse::<DebugMode>()? {
+                DebugMode::Bounces { .. } => DebugMode::Bounces { max_depth },
+                mode => mode,
+            };
+            Ok(Box::new(DebugIntegrator::new(mode)))
+        }
+    }
+}
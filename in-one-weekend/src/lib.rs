@@ -1,12 +1,27 @@
+pub mod ambient_occlusion;
+pub mod checkpoint;
+pub mod debug_integrator;
+pub mod distributed;
 pub mod hittable;
 pub mod hittable_list;
+pub mod instance;
+pub mod integrator;
 pub mod material;
+pub mod options;
+pub mod progress;
+pub mod renderer;
+pub mod run;
 pub mod sphere;
+pub mod sphere_list;
 
 pub use hittable::HitRecord;
 pub use hittable::Hittable;
 pub use hittable_list::HittableList;
+pub use instance::Instance;
 
+pub use integrator::Integrator;
 pub use material::Material;
+pub use renderer::{RenderSettings, Renderer};
 
 pub use sphere::Sphere;
+pub use sphere_list::SphereList;
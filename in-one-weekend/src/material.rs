@@ -1,16 +1,31 @@
 use crate::HitRecord;
+use common::hash::write_f64s;
 use common::ray::Ray;
-use common::rtweekend::{random_double, random_in_unit_sphere, random_unit_vector};
-use common::{color, vec3, Color};
+use common::sampler::Sampler;
+use common::sampling::{sample_cosine_hemisphere, sample_unit_ball};
+use common::{color, Color};
+use std::hash::Hasher;
 
-pub trait Material {
+pub trait Material: Send + Sync {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool;
+
+    /// Surface reflectance used by the debug integrators.
+    fn albedo(&self, rec: &HitRecord) -> Color;
+
+    /// Whether scattering is a mirror-like lobe rather than diffuse.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Feed the type and parameters of the material into `hasher`.
+    fn fingerprint(&self, hasher: &mut dyn Hasher);
 }
 
 pub struct Lambertian {
@@ -22,32 +37,36 @@ impl Lambertian {
     where
         Self: Sized,
     {
-        Lambertian {
-            albedo: albedo.clone(),
-        }
+        Lambertian { albedo: *albedo }
     }
 }
 
 impl Material for Lambertian {
     fn scatter(
         &self,
-        _r_in: &Ray,
+        r_in: &Ray,
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_direction = rec.normal + random_unit_vector();
-
-        // Catch degenerate scatter direction
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-
-        *scattered = Ray::new(&rec.p, &scatter_direction);
+        let scatter_direction = rec
+            .shading_frame()
+            .to_world(&sample_cosine_hemisphere(sampler.get_2d()));
+        *scattered = Ray::with_time(&rec.p, &scatter_direction, r_in.time);
         *attenuation = self.albedo;
 
         true
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"lambertian");
+        write_f64s(hasher, &[self.albedo.x, self.albedo.y, self.albedo.z]);
+    }
 }
 
 pub struct Metal {
@@ -61,7 +80,7 @@ impl Metal {
         Self: Sized,
     {
         Metal {
-            albedo: albedo.clone(),
+            albedo: *albedo,
             fuzz: if f < 1.0 { f } else { 1.0 },
         }
     }
@@ -74,12 +93,30 @@ impl Material for Metal {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         let reflected = r_in.direction.unit_vector().reflect(&rec.normal);
-        *scattered = Ray::new(&rec.p, &(reflected + self.fuzz * random_in_unit_sphere()));
+        let fuzz = self.fuzz * sample_unit_ball(sampler.get_2d(), sampler.get_1d());
+        *scattered = Ray::with_time(&rec.p, &(reflected + fuzz), r_in.time);
         *attenuation = self.albedo;
 
-        scattered.direction.dot(&rec.normal) > 0.0
+        rec.normal.dot(&scattered.direction) > 0.0
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"metal");
+        write_f64s(
+            hasher,
+            &[self.albedo.x, self.albedo.y, self.albedo.z, self.fuzz],
+        );
     }
 }
 
@@ -113,6 +150,7 @@ impl Material for Dielectric {
         rec: &HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         *attenuation = color![1.0, 1.0, 1.0];
         let refraction_ratio = if rec.front_face {
@@ -122,20 +160,33 @@ impl Material for Dielectric {
         };
 
         let unit_direction = r_in.direction.unit_vector();
-        let cos_theta = (-unit_direction).dot(&rec.normal).min(1.0);
+        let cos_theta = rec.normal.dot(&-unit_direction).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
         let direction = if cannot_refract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double()
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
         {
             unit_direction.reflect(&rec.normal)
         } else {
             unit_direction.refract(&rec.normal, refraction_ratio)
         };
 
-        *scattered = Ray::new(&rec.p, &direction);
+        *scattered = Ray::with_time(&rec.p, &direction, r_in.time);
 
         true
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        color![1.0, 1.0, 1.0]
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"dielectric");
+        write_f64s(hasher, &[self.ir]);
+    }
 }
@@ -0,0 +1,458 @@
+use crate::renderer::{AdaptiveSettings, ProgressiveSettings};
+use common::animation::{self, CameraAnimation, Interpolation};
+use common::camera::{
+    self, Bokeh, Camera, CameraSetup, Convergence, StereoCamera, StereoLayout, StereoRig,
+};
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Command line options shared by the render binaries.
+///
+/// `<binary> [integrator] [--sampler NAME] [--aov-exr FILE] [--aov-dir DIR] [--seed N]
+/// [--threads N] [--adaptive THRESHOLD [--min-spp N] [--max-spp N]] [--spp-heatmap FILE]
+/// [--filter NAME[:RADIUS]] [--camera NAME[:PARAM...]] [--aperture-shape SHAPE]
+/// [--cat-eye AMOUNT] [--anamorphic SQUEEZE] [--lens-shift X:Y] [--lens-tilt TILT[:SWING]]
+/// [--stereo EYE_DISTANCE [--convergence DISTANCE] [--toe-in] [--stereo-layout LAYOUT]]
+/// [--camera-keys FILE] [--interpolation NAME] [--frames FIRST:LAST [--fps N] [--frame-output PATTERN]]
+/// [--progressive N [--snapshot FILE] [--snapshot-interval SECONDS]
+/// [--checkpoint FILE [--checkpoint-interval SECONDS] [--resume]]]
+/// [--coordinator ADDR | --worker ADDR] [--denoise]`
+pub struct Options {
+    /// `path`, `ao[:samples[:max_distance]]` or a debug mode such as `normal`
+    /// or `depth:8`.
+    pub integrator: String,
+    /// One of [`common::sampler::SAMPLER_NAMES`].
+    pub sampler: String,
+    /// Multi-layer EXR receiving the beauty image and all AOVs.
+    pub aov_exr: Option<String>,
+    /// Directory receiving `beauty.pfm` and one PFM per AOV.
+    pub aov_dir: Option<String>,
+    /// Seed of all random numbers; equal seeds give identical images.
+    pub seed: u64,
+    /// Worker threads, all available cores if not given.
+    pub threads: Option<usize>,
+    /// Error threshold of adaptive sampling; uniform sampling if not given.
+    pub adaptive: Option<f64>,
+    pub min_spp: Option<usize>,
+    pub max_spp: Option<usize>,
+    /// PPM receiving a heatmap of the samples taken per pixel.
+    pub spp_heatmap: Option<String>,
+    /// Reconstruction filter, one of [`common::filter::FILTER_NAMES`] with an
+    /// optional radius.
+    pub filter: String,
+    /// Camera, one of [`common::camera::CAMERA_NAMES`] with its parameters
+    /// separated by `:`.
+    pub camera: String,
+    /// `disk`, `polygon:BLADES[:ROTATION]` or a PFM mask, see
+    /// [`common::camera::shape_from_name`].
+    pub aperture_shape: String,
+    /// Cat's eye vignetting, see [`common::camera::Bokeh`].
+    pub cat_eye: f64,
+    /// Squeeze factor of an anamorphic lens.
+    pub anamorphic: f64,
+    /// Lens shift in image widths and heights.
+    pub lens_shift: (f64, f64),
+    /// Tilt and swing of the plane in focus in degrees.
+    pub lens_tilt: (f64, f64),
+    /// Distance between the eyes of a stereo rig; a single camera if not
+    /// given.
+    pub stereo: Option<f64>,
+    /// Distance at which the eyes converge; the focus distance if not given.
+    pub convergence: Option<f64>,
+    /// Turn the eyes toward each other rather than shifting their lenses.
+    pub toe_in: bool,
+    /// `side-by-side`, `over-under` or `separate:LEFT:RIGHT`, which renders
+    /// side by side and writes the eyes to the PPMs `LEFT` and `RIGHT`
+    /// instead of stdout.
+    pub stereo_layout: StereoLayout,
+    pub stereo_files: Option<(String, String)>,
+    /// Keyframes of the camera replacing the animation of the binary, see
+    /// [`common::animation::parse_camera_keys`].
+    pub camera_keys: Option<String>,
+    /// Interpolation of `camera_keys`, one of
+    /// [`common::animation::INTERPOLATION_NAMES`].
+    pub interpolation: Interpolation,
+    /// Render the frames `FIRST..=LAST` of the animation to numbered files
+    /// instead of the still at time 0 to stdout.
+    pub frames: Option<(usize, usize)>,
+    /// Frames per second; 24 if not given.
+    pub fps: Option<f64>,
+    /// Files receiving the frames, the run of `#` replaced by the zero-padded
+    /// frame number; `frame_####.ppm` if not given.
+    pub frame_output: Option<String>,
+    /// Samples per pixel of each pass of a progressive render.
+    pub progressive: Option<usize>,
+    /// PPM rewritten with the image so far during a progressive render.
+    pub snapshot: Option<String>,
+    /// Least seconds between snapshots; after every pass if not given.
+    pub snapshot_interval: Option<f64>,
+    /// File receiving the state of a progressive render on Ctrl-C and, if
+    /// `checkpoint_interval` is given, periodically.
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: Option<f64>,
+    /// Continue the render saved in `checkpoint`.
+    pub resume: bool,
+    /// Address to accept workers on, e.g. `127.0.0.1:7878`; the tiles are
+    /// rendered by the workers.
+    pub coordinator: Option<String>,
+    /// Address of a coordinator to render tiles for; writes no image.
+    pub worker: Option<String>,
+    /// Denoise the image written to stdout and the snapshots.
+    pub denoise: bool,
+}
+
+impl Options {
+    pub fn from_env() -> Result<Self, String> {
+        Options::parse(env::args().skip(1))
+    }
+
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            integrator: "path".to_string(),
+            sampler: "random".to_string(),
+            aov_exr: None,
+            aov_dir: None,
+            seed: 0,
+            threads: None,
+            adaptive: None,
+            min_spp: None,
+            max_spp: None,
+            spp_heatmap: None,
+            filter: "box".to_string(),
+            camera: "perspective".to_string(),
+            aperture_shape: "disk".to_string(),
+            cat_eye: 0.0,
+            anamorphic: 1.0,
+            lens_shift: (0.0, 0.0),
+            lens_tilt: (0.0, 0.0),
+            stereo: None,
+            convergence: None,
+            toe_in: false,
+            stereo_layout: StereoLayout::SideBySide,
+            stereo_files: None,
+            camera_keys: None,
+            interpolation: Interpolation::Spline,
+            frames: None,
+            fps: None,
+            frame_output: None,
+            progressive: None,
+            snapshot: None,
+            snapshot_interval: None,
+            checkpoint: None,
+            checkpoint_interval: None,
+            resume: false,
+            coordinator: None,
+            worker: None,
+            denoise: false,
+        };
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", flag))
+            };
+            match arg.as_str() {
+                "--sampler" => options.sampler = value(&arg)?,
+                "--aov-exr" => options.aov_exr = Some(value(&arg)?),
+                "--aov-dir" => options.aov_dir = Some(value(&arg)?),
+                "--seed" => options.seed = parse(&arg, value(&arg)?)?,
+                "--threads" => options.threads = Some(parse(&arg, value(&arg)?)?),
+                "--adaptive" => options.adaptive = Some(parse(&arg, value(&arg)?)?),
+                "--min-spp" => options.min_spp = Some(parse(&arg, value(&arg)?)?),
+                "--max-spp" => options.max_spp = Some(parse(&arg, value(&arg)?)?),
+                "--spp-heatmap" => options.spp_heatmap = Some(value(&arg)?),
+                "--filter" => options.filter = value(&arg)?,
+                "--camera" => options.camera = value(&arg)?,
+                "--aperture-shape" => options.aperture_shape = value(&arg)?,
+                "--cat-eye" => options.cat_eye = parse(&arg, value(&arg)?)?,
+                "--anamorphic" => options.anamorphic = parse(&arg, value(&arg)?)?,
+                "--lens-shift" => options.lens_shift = parse_pair(&arg, value(&arg)?, None)?,
+                "--lens-tilt" => options.lens_tilt = parse_pair(&arg, value(&arg)?, Some(0.0))?,
+                "--stereo" => options.stereo = Some(parse(&arg, value(&arg)?)?),
+                "--convergence" => options.convergence = Some(parse(&arg, value(&arg)?)?),
+                "--toe-in" => options.toe_in = true,
+                "--stereo-layout" => {
+                    (options.stereo_layout, options.stereo_files) =
+                        parse_stereo_layout(&value(&arg)?)?
+                }
+                "--camera-keys" => options.camera_keys = Some(value(&arg)?),
+                "--interpolation" => {
+                    options.interpolation = animation::interpolation_from_name(&value(&arg)?)?
+                }
+                "--frames" => options.frames = Some(parse_frames(&arg, value(&arg)?)?),
+                "--fps" => options.fps = Some(parse(&arg, value(&arg)?)?),
+                "--frame-output" => options.frame_output = Some(value(&arg)?),
+                "--progressive" => options.progressive = Some(parse(&arg, value(&arg)?)?),
+                "--snapshot" => options.snapshot = Some(value(&arg)?),
+                "--snapshot-interval" => {
+                    options.snapshot_interval = Some(parse(&arg, value(&arg)?)?)
+                }
+                "--checkpoint" => options.checkpoint = Some(value(&arg)?),
+                "--checkpoint-interval" => {
+                    options.checkpoint_interval = Some(parse(&arg, value(&arg)?)?)
+                }
+                "--resume" => options.resume = true,
+                "--coordinator" => options.coordinator = Some(value(&arg)?),
+                "--worker" => options.worker = Some(value(&arg)?),
+                "--denoise" => options.denoise = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+                _ => options.integrator = arg,
+            }
+        }
+        if options.adaptive.is_none() && (options.min_spp.is_some() || options.max_spp.is_some()) {
+            return Err("--min-spp and --max-spp need --adaptive".to_string());
+        }
+        if options.progressive == Some(0) {
+            return Err("--progressive needs at least 1 sample".to_string());
+        }
+        if options.progressive.is_none()
+            && (options.snapshot.is_some() || options.checkpoint.is_some())
+        {
+            return Err("--snapshot and --checkpoint need --progressive".to_string());
+        }
+        if options.checkpoint.is_none() && (options.resume || options.checkpoint_interval.is_some())
+        {
+            return Err("--resume and --checkpoint-interval need --checkpoint".to_string());
+        }
+        if options.coordinator.is_some() && options.worker.is_some() {
+            return Err("--coordinator and --worker exclude each other".to_string());
+        }
+        if (options.coordinator.is_some() || options.worker.is_some())
+            && options.progressive.is_some()
+        {
+            return Err("--coordinator and --worker do not support --progressive".to_string());
+        }
+        if options.stereo.is_none()
+            && (options.convergence.is_some()
+                || options.toe_in
+                || options.stereo_layout != StereoLayout::SideBySide
+                || options.stereo_files.is_some())
+        {
+            return Err("--convergence, --toe-in and --stereo-layout need --stereo".to_string());
+        }
+        if options.stereo_files.is_some() && options.snapshot.is_some() {
+            return Err("--stereo-layout separate does not support --snapshot".to_string());
+        }
+        for (flag, distance) in [
+            ("--stereo", options.stereo),
+            ("--convergence", options.convergence),
+        ] {
+            if distance.is_some_and(|d| d.is_nan() || d <= 0.0) {
+                return Err(format!("{} must be positive", flag));
+            }
+        }
+        if options.frames.is_none() && (options.fps.is_some() || options.frame_output.is_some()) {
+            return Err("--fps and --frame-output need --frames".to_string());
+        }
+        if options.frames.is_some()
+            && (options.progressive.is_some()
+                || options.coordinator.is_some()
+                || options.worker.is_some())
+        {
+            return Err(
+                "--frames does not support --progressive, --coordinator or --worker".to_string(),
+            );
+        }
+        if options.frames.is_some()
+            && (options.aov_exr.is_some()
+                || options.aov_dir.is_some()
+                || options.spp_heatmap.is_some()
+                || options.stereo_files.is_some())
+        {
+            return Err(
+                "--frames does not support --aov-exr, --aov-dir, --spp-heatmap or \
+                        --stereo-layout separate"
+                    .to_string(),
+            );
+        }
+        if options.fps.is_some_and(|fps| fps.is_nan() || fps <= 0.0) {
+            return Err("--fps must be positive".to_string());
+        }
+        if options
+            .frame_output
+            .as_ref()
+            .is_some_and(|pattern| !pattern.contains('#'))
+        {
+            return Err("--frame-output needs a '#' for the frame number".to_string());
+        }
+        if options.cat_eye.is_nan() || options.cat_eye < 0.0 {
+            return Err("--cat-eye must not be negative".to_string());
+        }
+        if options.anamorphic.is_nan() || options.anamorphic <= 0.0 {
+            return Err("--anamorphic must be positive".to_string());
+        }
+        for (flag, interval) in [
+            ("--snapshot-interval", options.snapshot_interval),
+            ("--checkpoint-interval", options.checkpoint_interval),
+        ] {
+            if interval.is_some_and(|t| t.is_nan() || t < 0.0) {
+                return Err(format!("{} must not be negative", flag));
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether to record AOVs, for the outputs or to guide the denoiser.
+    pub fn wants_aovs(&self) -> bool {
+        self.aov_exr.is_some() || self.aov_dir.is_some() || self.denoise
+    }
+
+    pub fn adaptive_settings(&self) -> Option<AdaptiveSettings> {
+        let mut adaptive = AdaptiveSettings::new(self.adaptive?);
+        if let Some(min_spp) = self.min_spp {
+            adaptive.min_samples = min_spp;
+        }
+        if let Some(max_spp) = self.max_spp {
+            adaptive.max_samples = max_spp;
+        }
+        adaptive.min_samples = adaptive.min_samples.min(adaptive.max_samples);
+        Some(adaptive)
+    }
+
+    pub fn progressive_settings(&self) -> Option<ProgressiveSettings> {
+        let mut progressive = ProgressiveSettings::new(self.progressive?);
+        progressive.interval = self.snapshot_interval.map(Duration::from_secs_f64);
+        Some(progressive)
+    }
+
+    pub fn bokeh(&self) -> Result<Bokeh, String> {
+        Ok(Bokeh {
+            shape: camera::shape_from_name(&self.aperture_shape)?,
+            cat_eye: self.cat_eye,
+            anamorphic_squeeze: self.anamorphic,
+        })
+    }
+
+    /// The camera of `setup`, or with `--stereo` a rig of two placed
+    /// around it.
+    pub fn camera(&self, setup: &CameraSetup) -> Result<Box<dyn Camera>, String> {
+        let eye_distance = match self.stereo {
+            Some(eye_distance) => eye_distance,
+            None => return camera::from_name(&self.camera, setup),
+        };
+        let rig = StereoRig {
+            eye_distance,
+            convergence_distance: self.convergence.unwrap_or(setup.focus_dist),
+            convergence: if self.toe_in {
+                Convergence::ToeIn
+            } else {
+                Convergence::OffAxis
+            },
+        };
+        Ok(Box::new(StereoCamera::from_name(
+            &self.camera,
+            setup,
+            &rig,
+            self.stereo_layout,
+        )?))
+    }
+
+    /// Size of the film holding the image of `width` x `height` once per
+    /// eye with `--stereo`.
+    pub fn image_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match (self.stereo, self.stereo_layout) {
+            (None, _) => (width, height),
+            (Some(_), StereoLayout::SideBySide) => (2 * width, height),
+            (Some(_), StereoLayout::OverUnder) => (width, 2 * height),
+        }
+    }
+
+    /// The camera animation of `--camera-keys`, if given.
+    pub fn camera_animation(&self) -> Result<Option<CameraAnimation>, String> {
+        let path = match &self.camera_keys {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        animation::parse_camera_keys(&text, self.interpolation)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Everything but the scene and [`crate::RenderSettings`] that affects
+    /// the image, for [`crate::checkpoint::settings_hash`]. `program`
+    /// identifies the camera placement and everything else fixed in the binary.
+    /// The camera keys count by their values, wherever they are read from.
+    pub fn config(&self, program: &str) -> Result<String, String> {
+        Ok(format!(
+            "{} {} {} {} {} {} {} {} {} {:?} {:?} {:?} {:?} {} {:?} {:?} {:?}",
+            program,
+            self.integrator,
+            self.sampler,
+            self.seed,
+            self.filter,
+            self.camera,
+            self.aperture_shape,
+            self.cat_eye,
+            self.anamorphic,
+            self.lens_shift,
+            self.lens_tilt,
+            self.stereo,
+            self.convergence,
+            self.toe_in,
+            self.stereo_layout,
+            self.camera_animation()?,
+            self.progressive
+        ))
+    }
+}
+
+fn parse<T: FromStr>(flag: &str, value: String) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value {:?} for {}: {}", value, flag, e))
+}
+
+/// `A:B`, or `A` with `B` defaulting to `default` if given.
+fn parse_pair(flag: &str, value: String, default: Option<f64>) -> Result<(f64, f64), String> {
+    let (a, b) = match (value.split_once(':'), default) {
+        (Some((a, b)), _) => (a.to_string(), b.to_string()),
+        (None, Some(b)) => (value.clone(), b.to_string()),
+        (None, None) => return Err(format!("{} expects two values separated by ':'", flag)),
+    };
+    Ok((parse(flag, a)?, parse(flag, b)?))
+}
+
+/// `FIRST:LAST` with `FIRST <= LAST`.
+fn parse_frames(flag: &str, value: String) -> Result<(usize, usize), String> {
+    let (first, last) = value
+        .split_once(':')
+        .ok_or_else(|| format!("{} expects FIRST:LAST", flag))?;
+    let (first, last) = (
+        parse(flag, first.to_string())?,
+        parse(flag, last.to_string())?,
+    );
+    if first > last {
+        return Err(format!(
+            "{} {}: the last frame comes before the first",
+            flag, value
+        ));
+    }
+    Ok((first, last))
+}
+
+/// `side-by-side`, `over-under` or `separate:LEFT:RIGHT`.
+fn parse_stereo_layout(value: &str) -> Result<(StereoLayout, Option<(String, String)>), String> {
+    match value.split_once(':') {
+        None if value == "side-by-side" => Ok((StereoLayout::SideBySide, None)),
+        None if value == "over-under" => Ok((StereoLayout::OverUnder, None)),
+        Some(("separate", files)) => match files.split_once(':') {
+            Some((left, right)) if !left.is_empty() && !right.is_empty() => Ok((
+                StereoLayout::SideBySide,
+                Some((left.to_string(), right.to_string())),
+            )),
+            _ => Err("--stereo-layout separate expects two files: separate:LEFT:RIGHT".to_string()),
+        },
+        _ => Err(format!(
+            "unknown stereo layout: {} (expected side-by-side, over-under or separate:LEFT:RIGHT)",
+            value
+        )),
+    }
+}
@@ -0,0 +1,273 @@
+use common::binary::{read_u64, write_u64};
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+/// Counts of the work done by a render.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Rays from the camera, one per sample.
+    pub primary_rays: u64,
+    /// Rays continuing a path after a bounce.
+    pub secondary_rays: u64,
+    /// Rays that only test visibility, e.g. of ambient occlusion.
+    pub shadow_rays: u64,
+    /// Ray-object intersection tests, of shapes as well as of the instances
+    /// and lists holding them.
+    pub intersection_tests: u64,
+}
+
+impl RenderStats {
+    pub fn rays(&self) -> u64 {
+        self.primary_rays + self.secondary_rays + self.shadow_rays
+    }
+
+    /// Average number of segments of a path from the camera.
+    pub fn average_path_length(&self) -> f64 {
+        if self.primary_rays == 0 {
+            return 0.0;
+        }
+        (self.primary_rays + self.secondary_rays) as f64 / self.primary_rays as f64
+    }
+
+    pub fn intersection_tests_per_ray(&self) -> f64 {
+        if self.rays() == 0 {
+            return 0.0;
+        }
+        self.intersection_tests as f64 / self.rays() as f64
+    }
+
+    /// Write the counts, so that [`RenderStats::read`] can restore them.
+    pub fn write(&self, os: &mut dyn Write) -> io::Result<()> {
+        for count in [
+            self.primary_rays,
+            self.secondary_rays,
+            self.shadow_rays,
+            self.intersection_tests,
+        ] {
+            write_u64(os, count)?;
+        }
+        Ok(())
+    }
+
+    pub fn read(is: &mut dyn Read) -> io::Result<RenderStats> {
+        Ok(RenderStats {
+            primary_rays: read_u64(is)?,
+            secondary_rays: read_u64(is)?,
+            shadow_rays: read_u64(is)?,
+            intersection_tests: read_u64(is)?,
+        })
+    }
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.primary_rays += other.primary_rays;
+        self.secondary_rays += other.secondary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.intersection_tests += other.intersection_tests;
+    }
+}
+
+/// What [`count`] counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    PrimaryRay,
+    SecondaryRay,
+    ShadowRay,
+    IntersectionTest,
+}
+
+thread_local! {
+    static THREAD_STATS: Cell<RenderStats> = const {
+        Cell::new(RenderStats {
+            primary_rays: 0,
+            secondary_rays: 0,
+            shadow_rays: 0,
+            intersection_tests: 0,
+        })
+    };
+}
+
+/// Count one event on this thread. The counts are per thread so that
+/// counting stays cheap in the innermost loops; the renderer collects them
+/// with [`take_thread_stats`] after every tile.
+pub fn count(counter: Counter) {
+    THREAD_STATS.with(|stats| {
+        let mut s = stats.get();
+        match counter {
+            Counter::PrimaryRay => s.primary_rays += 1,
+            Counter::SecondaryRay => s.secondary_rays += 1,
+            Counter::ShadowRay => s.shadow_rays += 1,
+            Counter::IntersectionTest => s.intersection_tests += 1,
+        }
+        stats.set(s);
+    });
+}
+
+/// The counts of this thread since the last call.
+pub fn take_thread_stats() -> RenderStats {
+    THREAD_STATS.with(Cell::take)
+}
+
+/// Told about the progress of a render, which finishes its tiles in any
+/// order on several threads. Called from a single thread.
+pub trait ProgressReporter: Send {
+    /// A render of `tiles` tiles starts; a progressive render counts the
+    /// tiles of all passes to do.
+    fn start(&mut self, tiles: usize);
+
+    /// Another tile is finished. `stats` counts the work of the render so far.
+    fn tile_done(&mut self, stats: &RenderStats);
+
+    /// The render is over; `finished` is false if it was stopped before its
+    /// last pass, e.g. by a [`Checkpointer`](crate::checkpoint::Checkpointer).
+    fn finish(&mut self, stats: &RenderStats, finished: bool);
+
+    /// A note for the user on the way, e.g. a checkpoint written or a
+    /// worker lost.
+    fn message(&mut self, message: &str);
+}
+
+/// Reports nothing.
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn start(&mut self, _tiles: usize) {}
+
+    fn tile_done(&mut self, _stats: &RenderStats) {}
+
+    fn finish(&mut self, _stats: &RenderStats, _finished: bool) {}
+
+    fn message(&mut self, _message: &str) {}
+}
+
+/// Rewrites a line on stderr with the percentage done, elapsed time,
+/// estimated time left and rays per second, prints messages above it and a
+/// summary of the statistics at the end.
+pub struct TerminalProgress {
+    /// Least time between updates of the line.
+    pub interval: Duration,
+    tiles: usize,
+    done: usize,
+    started: Instant,
+    last_update: Option<Instant>,
+    /// Length of the line last written, cleared by a message.
+    shown: usize,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        TerminalProgress {
+            interval: Duration::from_millis(100),
+            tiles: 0,
+            done: 0,
+            started: Instant::now(),
+            last_update: None,
+            shown: 0,
+        }
+    }
+
+    fn line(&self, stats: &RenderStats) -> String {
+        let elapsed = self.started.elapsed();
+        let fraction = self.done as f64 / self.tiles.max(1) as f64;
+        let eta = if self.done > 0 {
+            format_duration(elapsed.mul_f64((1.0 - fraction) / fraction))
+        } else {
+            "--:--".to_string()
+        };
+        format!(
+            "{:5.1}% {} elapsed, ETA {}, {} rays/s",
+            100.0 * fraction,
+            format_duration(elapsed),
+            eta,
+            format_count(stats.rays() as f64 / elapsed.as_secs_f64().max(1e-9)),
+        )
+    }
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for TerminalProgress {
+    fn start(&mut self, tiles: usize) {
+        self.tiles = tiles;
+        self.done = 0;
+        self.started = Instant::now();
+        self.last_update = None;
+        self.shown = 0;
+    }
+
+    fn tile_done(&mut self, stats: &RenderStats) {
+        self.done += 1;
+        // `finish` prints the last line.
+        if self.done >= self.tiles
+            || self
+                .last_update
+                .is_some_and(|last| last.elapsed() < self.interval)
+        {
+            return;
+        }
+        let line = self.line(stats);
+        eprint!("\r{}", line);
+        self.shown = line.len();
+        self.last_update = Some(Instant::now());
+    }
+
+    fn finish(&mut self, stats: &RenderStats, finished: bool) {
+        eprintln!("\r{}", self.line(stats));
+        self.shown = 0;
+        eprintln!("{}", if finished { "Done." } else { "Stopped." });
+        eprintln!("Primary rays:       {}", stats.primary_rays);
+        eprintln!("Secondary rays:     {}", stats.secondary_rays);
+        eprintln!("Shadow rays:        {}", stats.shadow_rays);
+        eprintln!(
+            "Average path:       {:.2} segments",
+            stats.average_path_length()
+        );
+        eprintln!(
+            "Intersection tests: {} ({:.1} per ray)",
+            stats.intersection_tests,
+            stats.intersection_tests_per_ray()
+        );
+    }
+
+    fn message(&mut self, message: &str) {
+        // The progress line comes back with the next tile.
+        eprintln!("\r{:<width$}", message, width = self.shown);
+        self.shown = 0;
+        self.last_update = None;
+    }
+}
+
+/// `mm:ss`, or `h:mm:ss` from an hour on.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// A count with a metric prefix, e.g. `12.3M`.
+fn format_count(count: f64) -> String {
+    if count >= 1e9 {
+        format!("{:.1}G", count / 1e9)
+    } else if count >= 1e6 {
+        format!("{:.1}M", count / 1e6)
+    } else if count >= 1e3 {
+        format!("{:.1}k", count / 1e3)
+    } else {
+        format!("{:.0}", count)
+    }
+}
@@ -0,0 +1,472 @@
+use crate::checkpoint::Checkpointer;
+use crate::integrator::LIGHTING_AOVS;
+use crate::progress::{self, Counter, ProgressReporter, RenderStats, TerminalProgress};
+use crate::{Hittable, Integrator};
+use common::film::AovSample;
+use common::filter::{BoxFilter, Filter};
+use common::{color, Camera, Film, Sampler};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Edge length in pixels of the square tiles handed to the worker threads.
+pub const TILE_SIZE: usize = 16;
+
+/// Per-pixel sample counts driven by the variance of the pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSettings {
+    /// A pixel is done once [`Film::pixel_error`] is at most this.
+    pub threshold: f64,
+    /// Samples taken before the error estimate is trusted.
+    pub min_samples: usize,
+    pub max_samples: usize,
+}
+
+impl AdaptiveSettings {
+    pub fn new(threshold: f64) -> Self {
+        AdaptiveSettings {
+            threshold,
+            min_samples: 16,
+            max_samples: 1024,
+        }
+    }
+
+    /// Whether pixel `(i, j)` of `film` needs no more samples.
+    pub fn is_done(&self, film: &Film, i: usize, j: usize) -> bool {
+        let samples = film.samples(i, j);
+        samples >= self.max_samples
+            || (samples >= self.min_samples && film.pixel_error(i, j) <= self.threshold)
+    }
+}
+
+/// Rendering in passes over the whole frame, see
+/// [`Renderer::render_progressive`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressiveSettings {
+    /// Samples per pixel added by each pass.
+    pub pass_samples: usize,
+    /// Least time between snapshots; a snapshot after every pass if `None`.
+    pub interval: Option<Duration>,
+}
+
+impl ProgressiveSettings {
+    pub fn new(pass_samples: usize) -> Self {
+        ProgressiveSettings {
+            pass_samples,
+            interval: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RenderSettings {
+    pub image_width: usize,
+    pub image_height: usize,
+    /// Samples of every pixel, unless `adaptive` is set.
+    pub samples_per_pixel: usize,
+    pub adaptive: Option<AdaptiveSettings>,
+    /// Record the integrator's AOVs into the film next to the beauty image.
+    pub aovs: bool,
+    /// Number of worker threads; the image does not depend on it.
+    pub threads: usize,
+    /// Pixel reconstruction filter of the film.
+    pub filter: Arc<dyn Filter>,
+}
+
+impl RenderSettings {
+    pub fn new(image_width: usize, image_height: usize, samples_per_pixel: usize) -> Self {
+        RenderSettings {
+            image_width,
+            image_height,
+            samples_per_pixel,
+            adaptive: None,
+            aovs: false,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            filter: Arc::new(BoxFilter::new(0.5)),
+        }
+    }
+
+    /// Largest number of samples any pixel can get.
+    pub fn max_samples_per_pixel(&self) -> usize {
+        match self.adaptive {
+            Some(adaptive) => adaptive.max_samples,
+            None => self.samples_per_pixel,
+        }
+    }
+}
+
+/// The pixels `[x0, x1) x [y0, y1)` of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Tile {
+    pub fn width(&self) -> usize {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> usize {
+        self.y1 - self.y0
+    }
+
+    /// The tile grown by `margin` pixels on every side, clipped to the image.
+    pub fn expand(&self, margin: usize, width: usize, height: usize) -> Tile {
+        Tile {
+            x0: self.x0.saturating_sub(margin),
+            y0: self.y0.saturating_sub(margin),
+            x1: (self.x1 + margin).min(width),
+            y1: (self.y1 + margin).min(height),
+        }
+    }
+}
+
+/// Split the image into tiles of at most `size` x `size`, top rows first.
+pub fn tiles(width: usize, height: usize, size: usize) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y0 in (0..height).step_by(size).rev() {
+        for x0 in (0..width).step_by(size) {
+            tiles.push(Tile {
+                x0,
+                y0,
+                x1: (x0 + size).min(width),
+                y1: (y0 + size).min(height),
+            });
+        }
+    }
+    tiles
+}
+
+/// Drives the pixel loop: generates camera rays, asks the integrator for
+/// their radiance and accumulates the result into the film.
+pub struct Renderer {
+    pub camera: Box<dyn Camera>,
+    pub sampler: Box<dyn Sampler>,
+    pub film: Film,
+    pub settings: RenderSettings,
+    /// Samples per pixel taken by the passes of a progressive render so far.
+    pub samples_done: usize,
+    pub progress: Box<dyn ProgressReporter>,
+    /// Work done by the last render, including the passes before a
+    /// checkpoint it was resumed from.
+    pub stats: RenderStats,
+}
+
+impl Renderer {
+    pub fn new(
+        camera: Box<dyn Camera>,
+        sampler: Box<dyn Sampler>,
+        settings: RenderSettings,
+    ) -> Self {
+        Renderer {
+            camera,
+            sampler,
+            film: Film::with_filter(
+                settings.image_width,
+                settings.image_height,
+                settings.filter.clone(),
+            ),
+            settings,
+            samples_done: 0,
+            progress: Box::new(TerminalProgress::new()),
+            stats: RenderStats::default(),
+        }
+    }
+
+    pub fn render(&mut self, world: &dyn Hittable, integrator: &dyn Integrator) {
+        self.enable_aovs(integrator);
+        let samples = 0..self.settings.max_samples_per_pixel();
+        self.stats = RenderStats::default();
+        self.progress.start(self.tile_regions().len());
+        self.render_pass(world, integrator, samples, None);
+        self.progress.finish(&self.stats, true);
+    }
+
+    /// Render the frame in passes of `progressive.pass_samples` samples per
+    /// pixel, accumulating into the film, and hand the film to `snapshot`
+    /// after a pass once `progressive.interval` has elapsed since the last
+    /// snapshot, and after the last pass. In adaptive mode, pixels that have
+    /// converged are left out of later passes.
+    ///
+    /// The render starts after `samples_done` samples, e.g. as restored by
+    /// [`Checkpointer::resume`], and `checkpoint` is given the renderer after
+    /// every pass. Returns false if the checkpointer stopped the render.
+    pub fn render_progressive(
+        &mut self,
+        world: &dyn Hittable,
+        integrator: &dyn Integrator,
+        progressive: &ProgressiveSettings,
+        snapshot: &mut dyn FnMut(&Film),
+        mut checkpoint: Option<&mut Checkpointer>,
+    ) -> bool {
+        self.enable_aovs(integrator);
+        let total = self.settings.max_samples_per_pixel();
+        let pass_samples = progressive.pass_samples.max(1);
+        let passes = total.div_ceil(pass_samples);
+        let mut last_snapshot = Instant::now();
+        let mut unsaved = false;
+        let mut finished = true;
+        let first_pass = self.samples_done.div_ceil(pass_samples);
+        // A resumed render keeps counting from the stats of its checkpoint.
+        if self.samples_done == 0 {
+            self.stats = RenderStats::default();
+        }
+        self.progress
+            .start(self.tile_regions().len() * passes.saturating_sub(first_pass));
+
+        for pass in first_pass..passes {
+            let samples = self.samples_done..((pass + 1) * pass_samples).min(total);
+            let skip = self.settings.adaptive.map(|adaptive| {
+                let film = &self.film;
+                (0..film.height)
+                    .flat_map(|j| (0..film.width).map(move |i| adaptive.is_done(film, i, j)))
+                    .collect::<Vec<bool>>()
+            });
+            if skip
+                .as_ref()
+                .is_some_and(|skip| skip.iter().all(|&done| done))
+            {
+                break;
+            }
+
+            self.render_pass(world, integrator, samples.clone(), skip.as_deref());
+            self.samples_done = samples.end;
+            unsaved = true;
+            let stop = checkpoint
+                .as_deref_mut()
+                .is_some_and(|checkpoint| checkpoint.after_pass(self));
+            if progressive
+                .interval
+                .is_none_or(|interval| last_snapshot.elapsed() >= interval)
+            {
+                snapshot(&self.film);
+                last_snapshot = Instant::now();
+                unsaved = false;
+            }
+            if stop {
+                finished = false;
+                break;
+            }
+        }
+        if unsaved {
+            snapshot(&self.film);
+        }
+        self.progress.finish(&self.stats, finished);
+        finished
+    }
+
+    /// Record the AOVs of `integrator` in the film if the settings ask for
+    /// them.
+    pub fn enable_aovs(&mut self, integrator: &dyn Integrator) {
+        if self.settings.aovs {
+            for (name, components) in integrator.aovs() {
+                self.film.enable_aov(name, components);
+            }
+        }
+    }
+
+    /// Add the samples `samples` of every pixel not marked in `skip`.
+    fn render_pass(
+        &mut self,
+        world: &dyn Hittable,
+        integrator: &dyn Integrator,
+        samples: Range<usize>,
+        skip: Option<&[bool]>,
+    ) {
+        let tiles = self.tile_regions();
+        let next_tile = AtomicUsize::new(0);
+        let pass = Pass {
+            camera: self.camera.as_ref(),
+            settings: &self.settings,
+            world,
+            integrator,
+            samples,
+            skip,
+            template: self.film.new_like(0, 0),
+        };
+        let threads = self.settings.threads.max(1);
+        let film = &mut self.film;
+        let (progress, stats) = (&mut self.progress, &mut self.stats);
+
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            for _ in 0..threads {
+                let tx = tx.clone();
+                let mut sampler = self.sampler.clone_sampler();
+                let (tiles, next_tile, pass) = (&tiles, &next_tile, &pass);
+                scope.spawn(move || loop {
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    if index >= tiles.len() {
+                        break;
+                    }
+                    let (tile, region) = tiles[index];
+                    let result = pass.render_tile(sampler.as_mut(), &tile, &region);
+                    if tx.send((index, result)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+
+            let mut merger = TileMerger::new(&tiles);
+            for (index, (tile_film, tile_stats)) in rx.iter() {
+                merger.add(film, index, tile_film);
+                *stats += tile_stats;
+                progress.tile_done(stats);
+            }
+        });
+    }
+
+    /// The tiles of the image, each with the region its film covers: the
+    /// tile's film also receives the splats of its samples on the pixels
+    /// around it.
+    pub fn tile_regions(&self) -> Vec<(Tile, Tile)> {
+        let (width, height) = (self.settings.image_width, self.settings.image_height);
+        let margin = self.film.filter_margin();
+        tiles(width, height, TILE_SIZE)
+            .into_iter()
+            .map(|tile| (tile, tile.expand(margin, width, height)))
+            .collect()
+    }
+
+    pub(crate) fn pass<'a>(
+        &'a self,
+        world: &'a dyn Hittable,
+        integrator: &'a dyn Integrator,
+        samples: Range<usize>,
+        skip: Option<&'a [bool]>,
+    ) -> Pass<'a> {
+        Pass {
+            camera: self.camera.as_ref(),
+            settings: &self.settings,
+            world,
+            integrator,
+            samples,
+            skip,
+            template: self.film.new_like(0, 0),
+        }
+    }
+}
+
+/// Merges tile films into the film in tile order: where the films of
+/// neighboring tiles overlap, the sums must not depend on which tile
+/// finished first.
+pub(crate) struct TileMerger {
+    regions: Vec<Tile>,
+    finished: Vec<Option<Film>>,
+    next: usize,
+    received: usize,
+}
+
+impl TileMerger {
+    pub(crate) fn new(tiles: &[(Tile, Tile)]) -> Self {
+        TileMerger {
+            regions: tiles.iter().map(|&(_, region)| region).collect(),
+            finished: tiles.iter().map(|_| None).collect(),
+            next: 0,
+            received: 0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, film: &mut Film, index: usize, tile_film: Film) {
+        self.finished[index] = Some(tile_film);
+        self.received += 1;
+        while let Some(Some(tile_film)) = self.finished.get_mut(self.next).map(Option::take) {
+            let region = self.regions[self.next];
+            film.merge_tile(&tile_film, region.x0, region.y0);
+            self.next += 1;
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.regions.len() - self.received
+    }
+}
+
+/// What the worker threads share while rendering one pass.
+pub(crate) struct Pass<'a> {
+    camera: &'a dyn Camera,
+    settings: &'a RenderSettings,
+    world: &'a dyn Hittable,
+    integrator: &'a dyn Integrator,
+    /// Sample indices taken in every pixel.
+    samples: Range<usize>,
+    /// Pixels left out, row by row from the bottom. Without it, in adaptive
+    /// mode a pixel stops receiving samples once its estimated error is
+    /// small enough.
+    skip: Option<&'a [bool]>,
+    /// Empty film with the filter and AOVs of the render.
+    template: Film,
+}
+
+impl Pass<'_> {
+    /// Render the pixels in `tile` into a film covering `region`, with the
+    /// work it took.
+    pub(crate) fn render_tile(
+        &self,
+        sampler: &mut dyn Sampler,
+        tile: &Tile,
+        region: &Tile,
+    ) -> (Film, RenderStats) {
+        progress::take_thread_stats();
+        let mut film = self.template.new_like(region.width(), region.height());
+        let settings = self.settings;
+        let width = settings.image_width;
+        let height = settings.image_height;
+        let mut aovs = AovSample::new();
+        let exposure = self.camera.exposure();
+
+        for j in tile.y0..tile.y1 {
+            for i in tile.x0..tile.x1 {
+                if self.skip.is_some_and(|skip| skip[j * width + i]) {
+                    continue;
+                }
+                let (x, y) = (i - region.x0, j - region.y0);
+                for s in self.samples.clone() {
+                    if let (Some(adaptive), None) = (settings.adaptive, self.skip) {
+                        if s >= adaptive.min_samples && film.pixel_error(x, y) <= adaptive.threshold
+                        {
+                            break;
+                        }
+                    }
+                    sampler.start_pixel_sample(i, j, s);
+
+                    let (du, dv) = sampler.get_pixel_2d();
+                    let u = (i as f64 + du) / (width - 1) as f64;
+                    let v = (j as f64 + dv) / (height - 1) as f64;
+                    let (fx, fy) = (x as f64 + du, y as f64 + dv);
+                    let Some((ray, weight)) = self.camera.get_weighted_ray(u, v, sampler) else {
+                        // Outside the image of the camera: black, no AOVs.
+                        film.add_sample(fx, fy, &color![]);
+                        continue;
+                    };
+                    progress::count(Counter::PrimaryRay);
+                    if settings.aovs {
+                        aovs.clear();
+                        let color = self
+                            .integrator
+                            .radiance_with_aovs(&ray, self.world, sampler, &mut aovs);
+                        // Scaled like the beauty image so that they still
+                        // add up to it.
+                        for name in LIGHTING_AOVS {
+                            if let Some(value) = aovs.get(name) {
+                                aovs.set(name, exposure * (weight * value));
+                            }
+                        }
+                        film.add_sample_with_aovs(fx, fy, &(exposure * (weight * color)), &aovs);
+                    } else {
+                        let color = self.integrator.radiance(&ray, self.world, sampler);
+                        film.add_sample(fx, fy, &(exposure * (weight * color)));
+                    }
+                }
+            }
+        }
+        (film, progress::take_thread_stats())
+    }
+}
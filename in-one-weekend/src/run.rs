@@ -0,0 +1,318 @@
+use crate::checkpoint::{scene_hash, settings_hash, Checkpointer};
+use crate::debug_integrator::heatmap;
+use crate::distributed::{run_worker, Coordinator};
+use crate::options::Options;
+use crate::{integrator, Hittable, HittableList, Integrator, RenderSettings, Renderer};
+use common::camera::CameraSetup;
+use common::denoise::{denoise_film, DenoiseSettings};
+use common::{filter, sampler, Film, Image};
+use std::fs::{self, File};
+use std::io::{self, stdout, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// What a binary renders unless its options say otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Defaults {
+    /// Identifies the binary, see [`Options::config`].
+    pub program: &'static str,
+    pub image_width: usize,
+    pub image_height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: i32,
+}
+
+/// Entry point of the render binaries: parse the command line, render the
+/// scene as it asks and write the outputs. `scene` gives the world and the
+/// camera setup at a time in seconds; `--camera-keys` replaces the camera
+/// animation and the lens options the bokeh, shift and tilt of the setup.
+/// Returns the exit code, after printing the error if there is one.
+pub fn run(
+    defaults: &Defaults,
+    scene: impl Fn(&Options, f64) -> (HittableList, CameraSetup),
+) -> ExitCode {
+    match try_run(defaults, &scene) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn try_run(
+    defaults: &Defaults,
+    scene: &dyn Fn(&Options, f64) -> (HittableList, CameraSetup),
+) -> Result<ExitCode, String> {
+    let options = Options::from_env()?;
+    let bokeh = options.bokeh()?;
+    let animation = options.camera_animation()?;
+    let scene = |time: f64| {
+        let (world, mut setup) = scene(&options, time);
+        if let Some(animation) = &animation {
+            setup = animation.setup_at(&setup, time);
+        }
+        setup.bokeh = bokeh.clone();
+        setup.shift = options.lens_shift;
+        setup.tilt = options.lens_tilt;
+        (world, setup)
+    };
+
+    let (width, height) = options.image_size(defaults.image_width, defaults.image_height);
+    let mut settings = RenderSettings::new(width, height, defaults.samples_per_pixel);
+    settings.aovs = options.wants_aovs();
+    settings.adaptive = options.adaptive_settings();
+    settings.filter = filter::from_name(&options.filter)?;
+    if let Some(threads) = options.threads {
+        settings.threads = threads;
+    }
+    let integrator = integrator::from_name(&options.integrator, defaults.max_depth)?;
+    if render_frames(&options, &settings, integrator.as_ref(), &scene)? {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let (world, setup) = scene(0.0);
+    let sampler = sampler::from_name(
+        &options.sampler,
+        settings.max_samples_per_pixel(),
+        options.seed,
+    )?;
+    let mut renderer = Renderer::new(options.camera(&setup)?, sampler, settings);
+    let mut checkpointer = checkpointer(&options, defaults.program, &world, &mut renderer)?;
+    let distributed = render_distributed(
+        &options,
+        defaults.program,
+        &world,
+        integrator.as_ref(),
+        &mut renderer,
+    )?;
+    if options.worker.is_some() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    match options.progressive_settings() {
+        Some(progressive) => {
+            let mut snapshot_error = None;
+            let finished = renderer.render_progressive(
+                &world,
+                integrator.as_ref(),
+                &progressive,
+                &mut |film| {
+                    if let Err(e) = write_snapshot(&options, film) {
+                        snapshot_error.get_or_insert(e);
+                    }
+                },
+                checkpointer.as_mut(),
+            );
+            if let Some(e) = snapshot_error {
+                return Err(format!("cannot write snapshot: {}", e));
+            }
+            if !finished {
+                eprintln!("Continue with --resume.");
+                return Ok(ExitCode::from(130));
+            }
+        }
+        None if distributed => {}
+        None => renderer.render(&world, integrator.as_ref()),
+    }
+
+    write_image(&options, &renderer.film, &mut stdout())
+        .map_err(|e| format!("cannot write image: {}", e))?;
+    write_aovs(&options, &renderer.film).map_err(|e| format!("cannot write AOVs: {}", e))?;
+    write_sample_heatmap(
+        &options,
+        &renderer.film,
+        renderer.settings.max_samples_per_pixel(),
+    )
+    .map_err(|e| format!("cannot write sample heatmap: {}", e))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// With `--frames`, render every frame of the animation that `scene` gives
+/// the world and camera setup of at a time in seconds, and write it to its
+/// numbered file. Returns whether it did.
+pub fn render_frames(
+    options: &Options,
+    settings: &RenderSettings,
+    integrator: &dyn Integrator,
+    scene: &dyn Fn(f64) -> (HittableList, CameraSetup),
+) -> Result<bool, String> {
+    let (first, last) = match options.frames {
+        Some(frames) => frames,
+        None => return Ok(false),
+    };
+    let fps = options.fps.unwrap_or(24.0);
+    let pattern = options.frame_output.as_deref().unwrap_or("frame_####.ppm");
+    for frame in first..=last {
+        let (world, setup) = scene(frame as f64 / fps);
+        let sampler = sampler::from_name(
+            &options.sampler,
+            settings.max_samples_per_pixel(),
+            options.seed,
+        )?;
+        let mut renderer = Renderer::new(options.camera(&setup)?, sampler, settings.clone());
+        renderer.render(&world, integrator);
+
+        let path = frame_path(pattern, frame);
+        renderer
+            .progress
+            .message(&format!("Frame {} -> {}", frame, path));
+        let mut os = BufWriter::new(
+            File::create(&path).map_err(|e| format!("cannot create {}: {}", path, e))?,
+        );
+        write_image(options, &renderer.film, &mut os)
+            .and_then(|()| os.flush())
+            .map_err(|e| format!("cannot write {}: {}", path, e))?;
+    }
+    Ok(true)
+}
+
+/// `pattern` with its first run of `#` replaced by `frame`, padded with
+/// zeros to the length of the run.
+pub fn frame_path(pattern: &str, frame: usize) -> String {
+    let start = pattern.find('#').unwrap_or(pattern.len());
+    let width = pattern[start..].chars().take_while(|&c| c == '#').count();
+    format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        frame,
+        &pattern[start + width..],
+        width = width
+    )
+}
+
+/// Checkpointer for `--checkpoint`, handling Ctrl-C. With `--resume`,
+/// `renderer` continues from the checkpoint. See [`Options::config`] for
+/// `program`.
+pub fn checkpointer(
+    options: &Options,
+    program: &str,
+    world: &dyn Hittable,
+    renderer: &mut Renderer,
+) -> Result<Option<Checkpointer>, String> {
+    let path = match &options.checkpoint {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let mut checkpointer = Checkpointer::new(path, scene_hash(world), &options.config(program)?);
+    checkpointer.interval = options.checkpoint_interval.map(Duration::from_secs_f64);
+    if options.resume {
+        checkpointer.resume(renderer)?;
+        let message = format!(
+            "Resuming {} after {} samples per pixel",
+            path, renderer.samples_done
+        );
+        renderer.progress.message(&message);
+    }
+    checkpointer.handle_ctrl_c()?;
+    Ok(Some(checkpointer))
+}
+
+/// With `--coordinator`, render the image of `renderer` on workers; with
+/// `--worker`, render tiles for a coordinator, leaving `renderer` empty.
+/// Returns whether either happened. See [`Options::config`] for `program`.
+pub fn render_distributed(
+    options: &Options,
+    program: &str,
+    world: &dyn Hittable,
+    integrator: &dyn Integrator,
+    renderer: &mut Renderer,
+) -> Result<bool, String> {
+    let scene_hash = scene_hash(world);
+    let settings_hash = settings_hash(&renderer.settings, &options.config(program)?);
+    if let Some(addr) = &options.coordinator {
+        let coordinator = Coordinator::bind(addr.as_str(), scene_hash, settings_hash)
+            .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+        coordinator.render(renderer, integrator);
+        return Ok(true);
+    }
+    if let Some(addr) = &options.worker {
+        run_worker(addr, renderer, world, integrator, scene_hash, settings_hash)
+            .map_err(|e| format!("worker for {}: {}", addr, e))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Write the image of `film` as a PPM, denoised if requested. With
+/// `--stereo-layout separate`, the eyes go to their files instead of `os`.
+pub fn write_image(options: &Options, film: &Film, os: &mut dyn Write) -> io::Result<()> {
+    let (left, right) = match &options.stereo_files {
+        Some(files) => files,
+        None if options.denoise => {
+            return denoise_film(film, &DenoiseSettings::default()).write_ppm(os)
+        }
+        None => return film.write_ppm(os),
+    };
+    let image = if options.denoise {
+        denoise_film(film, &DenoiseSettings::default())
+    } else {
+        let (components, data) = film.channel_data("beauty").expect("beauty channel");
+        Image::from_data(film.width, film.height, components, &data)
+    };
+    let width = image.width / 2;
+    for (x, path) in [(0, left), (width, right)] {
+        let mut os = BufWriter::new(File::create(path)?);
+        image.crop(x, 0, width, image.height).write_ppm(&mut os)?;
+        os.flush()?;
+    }
+    Ok(())
+}
+
+/// Write the beauty image and AOVs of `film` to the outputs requested.
+pub fn write_aovs(options: &Options, film: &Film) -> io::Result<()> {
+    if let Some(path) = &options.aov_exr {
+        film.write_exr(&mut BufWriter::new(File::create(path)?))?;
+    }
+    if let Some(dir) = &options.aov_dir {
+        fs::create_dir_all(dir)?;
+        let mut names = vec!["beauty"];
+        names.extend(film.aov_names());
+        for name in names {
+            let path = Path::new(dir).join(format!("{}.pfm", name));
+            film.write_pfm(name, &mut BufWriter::new(File::create(path)?))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the samples taken per pixel as a heatmap PPM, red for
+/// `max_samples`, if requested.
+pub fn write_sample_heatmap(options: &Options, film: &Film, max_samples: usize) -> io::Result<()> {
+    let path = match &options.spp_heatmap {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let mut os = BufWriter::new(File::create(path)?);
+    writeln!(os, "P3\n{} {}\n255", film.width, film.height)?;
+    for j in (0..film.height).rev() {
+        for i in 0..film.width {
+            let c = heatmap(film.samples(i, j) as f64 / max_samples.max(1) as f64);
+            writeln!(
+                os,
+                "{} {} {}",
+                (255.0 * c.x) as u8,
+                (255.0 * c.y) as u8,
+                (255.0 * c.z) as u8
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the snapshot file with the image of `film`. The image is written
+/// next to it first, so the file never holds a partial image.
+pub fn write_snapshot(options: &Options, film: &Film) -> io::Result<()> {
+    let path = match &options.snapshot {
+        Some(path) => Path::new(path),
+        None => return Ok(()),
+    };
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    {
+        let mut os = BufWriter::new(File::create(&partial)?);
+        write_image(options, film, &mut os)?;
+        os.flush()?;
+    }
+    fs::rename(&partial, path)
+}
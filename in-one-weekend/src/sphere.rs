@@ -1,30 +1,44 @@
+use crate::progress::{count, Counter};
 use crate::{HitRecord, Hittable, Material};
+use common::hash::write_f64s;
 use common::ray::Ray;
-use common::Point3;
-use std::cell::RefCell;
-use std::rc::Rc;
+use common::{vec3, Point3, Vector3};
+use std::f64::consts::PI;
+use std::hash::Hasher;
+use std::sync::Arc;
 
 pub struct Sphere {
     pub center: Point3,
     pub radius: f64,
-    pub mat_ptr: Option<Rc<RefCell<dyn Material>>>,
+    pub mat_ptr: Option<Arc<dyn Material>>,
 }
 
 impl Sphere {
-    pub fn new(center: &Point3, radius: f64, material: Option<Rc<RefCell<dyn Material>>>) -> Self
+    pub fn new(center: &Point3, radius: f64, material: Option<Arc<dyn Material>>) -> Self
     where
         Self: Sized,
     {
         Sphere {
-            center: center.clone(),
+            center: *center,
             radius,
             mat_ptr: material,
         }
     }
+
+    /// p: a given point on the sphere of radius one, centered at the origin.
+    /// u: returned value [0,1] of angle around the Y axis from X=-1.
+    /// v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    pub fn get_sphere_uv(p: &Vector3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        count(Counter::IntersectionTest);
         let oc = ray.origin - self.center;
         let a = ray.direction.length_squared();
         let half_b = oc.dot(&ray.direction);
@@ -49,9 +63,24 @@ impl Hittable for Sphere {
         rec.t = root;
         rec.p = ray.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
-        rec.set_face_normal(ray, &outward_normal);
+        rec.set_face_normal(ray, &outward_normal.into());
+        // dp/du: along the lines of latitude, vanishing at the poles.
+        rec.set_tangent(&vec3![outward_normal.z, 0.0, -outward_normal.x]);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&outward_normal);
         rec.mat_ptr = self.mat_ptr.clone();
 
         true
     }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"sphere");
+        write_f64s(
+            hasher,
+            &[self.center.x, self.center.y, self.center.z, self.radius],
+        );
+        match &self.mat_ptr {
+            Some(mat_ptr) => mat_ptr.fingerprint(hasher),
+            None => hasher.write(b"none"),
+        }
+    }
 }
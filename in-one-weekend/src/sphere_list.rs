@@ -0,0 +1,89 @@
+use crate::progress::{count, Counter};
+use crate::{HitRecord, Hittable, Sphere};
+use common::ray::Ray;
+use common::simd::{hit_spheres, F64x4, Lanes, Vector3x4};
+use common::Vector3;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// Spheres tested four at a time, for scenes of many small spheres. Only
+/// those the packed test hits are tested again one by one for the record,
+/// so it finds the same hits as a `HittableList` of the spheres. Each test
+/// of four counts as one intersection test.
+pub struct SphereList {
+    spheres: Vec<Arc<Sphere>>,
+    /// Centers and radii of the spheres by four, the last group padded with
+    /// its first sphere.
+    groups: Vec<(Vector3x4<F64x4>, F64x4)>,
+}
+
+impl SphereList {
+    pub fn new() -> Self
+    where
+        Self: Sized,
+    {
+        SphereList {
+            spheres: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, sphere: Arc<Sphere>) {
+        self.spheres.push(sphere);
+        let first = (self.spheres.len() - 1) / 4 * 4;
+        let group = &self.spheres[first..];
+        let sphere = |i: usize| &group[if i < group.len() { i } else { 0 }];
+        let packed = (
+            Vector3x4::new(std::array::from_fn(|i| sphere(i).center.into())),
+            F64x4::new(std::array::from_fn(|i| sphere(i).radius)),
+        );
+        if group.len() == 1 {
+            self.groups.push(packed);
+        } else {
+            *self.groups.last_mut().unwrap() = packed;
+        }
+    }
+}
+
+impl Default for SphereList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hittable for SphereList {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        count(Counter::IntersectionTest);
+        let origin = Vector3::from(ray.origin);
+        let mut hit_anything = false;
+        let mut closest_so_far = t_max;
+
+        for ((centers, radii), group) in self.groups.iter().zip(self.spheres.chunks(4)) {
+            count(Counter::IntersectionTest);
+            let (mask, _) = hit_spheres(
+                centers,
+                *radii,
+                origin,
+                ray.direction,
+                t_min,
+                closest_so_far,
+            );
+            for (lane, sphere) in group.iter().enumerate() {
+                if mask & 1 << lane != 0 && sphere.hit(ray, t_min, closest_so_far, rec) {
+                    hit_anything = true;
+                    closest_so_far = rec.t;
+                }
+            }
+        }
+
+        hit_anything
+    }
+
+    fn fingerprint(&self, hasher: &mut dyn Hasher) {
+        hasher.write(b"spheres");
+        hasher.write_usize(self.spheres.len());
+        for sphere in &self.spheres {
+            sphere.fingerprint(hasher);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use fixture::{renderer_with, scene};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::Dielectric;
+use in_one_weekend::renderer::AdaptiveSettings;
+use in_one_weekend::{RenderSettings, Renderer};
+use std::sync::Arc;
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 20;
+
+/// A glass ball on diffuse ground below an empty sky.
+fn render(threads: usize) -> Renderer {
+    let world = scene(Arc::new(Dielectric::new(1.5)));
+    let mut settings = RenderSettings::new(WIDTH, HEIGHT, 1);
+    settings.threads = threads;
+    settings.adaptive = Some(AdaptiveSettings {
+        threshold: 0.01,
+        min_samples: 8,
+        max_samples: 256,
+    });
+    let mut renderer = renderer_with(settings, 3);
+    renderer.render(&world, &PathIntegrator::new(10));
+    renderer
+}
+
+#[test]
+fn test_noisy_pixels_get_more_samples() {
+    let renderer = render(4);
+    let film = &renderer.film;
+
+    let sky = film.samples(WIDTH / 2, HEIGHT - 1);
+    let ball = film.samples(WIDTH / 2, HEIGHT / 2);
+    assert_eq!(sky, 8);
+    assert!(ball > 4 * sky, "sky {} ball {}", sky, ball);
+
+    for j in 0..HEIGHT {
+        for i in 0..WIDTH {
+            let samples = film.samples(i, j);
+            assert!((8..=256).contains(&samples));
+            assert!(samples == 256 || film.pixel_error(i, j) <= 0.01);
+        }
+    }
+}
+
+#[test]
+fn test_adaptive_is_deterministic() {
+    let a = render(1).film;
+    let b = render(5).film;
+    assert_eq!(a.channel_data("beauty"), b.channel_data("beauty"));
+    for j in 0..HEIGHT {
+        for i in 0..WIDTH {
+            assert_eq!(a.samples(i, j), b.samples(i, j));
+        }
+    }
+}
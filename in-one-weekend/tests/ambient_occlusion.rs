@@ -0,0 +1,65 @@
+use common::ray::Ray;
+use common::sampler::{RandomSampler, Sampler};
+use common::{color, point3, vec3, Color};
+use in_one_weekend::ambient_occlusion::AmbientOcclusionIntegrator;
+use in_one_weekend::{HittableList, Integrator, Sphere};
+use std::sync::Arc;
+
+/// Ambient occlusion seen from (0, 0, 5) at the top of a unit sphere,
+/// inside a sphere of radius 10 if `enclosed`.
+fn occlusion(integrator: &AmbientOcclusionIntegrator, enclosed: bool) -> Color {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(&point3![0, 0, 0], 1.0, None)));
+    if enclosed {
+        world.add(Arc::new(Sphere::new(&point3![0, 0, 0], 10.0, None)));
+    }
+    let ray = Ray::new(&point3![0, 0, 5], &vec3![0, 0, -1]);
+    let mut sampler = RandomSampler::new(3);
+    sampler.start_pixel_sample(0, 0, 0);
+    integrator.radiance(&ray, &world, &mut sampler)
+}
+
+#[test]
+fn test_ambient_occlusion() {
+    // Nothing on a lone convex sphere blocks its hemisphere.
+    let ao = AmbientOcclusionIntegrator::new(16, f64::INFINITY);
+    assert_eq!(occlusion(&ao, false), color![1, 1, 1]);
+    // Every direction hits the enclosing sphere...
+    assert_eq!(occlusion(&ao, true), color![0, 0, 0]);
+    // ...which lies beyond the distance of 0.5.
+    let ao = AmbientOcclusionIntegrator::new(16, 0.5);
+    assert_eq!(occlusion(&ao, true), color![1, 1, 1]);
+}
+
+#[test]
+fn test_parse_ao_spec() {
+    let ao = AmbientOcclusionIntegrator::from_spec("ao").unwrap();
+    assert_eq!((ao.samples, ao.max_distance), (16, f64::INFINITY));
+    let ao = AmbientOcclusionIntegrator::from_spec("ao:32").unwrap();
+    assert_eq!((ao.samples, ao.max_distance), (32, f64::INFINITY));
+    let ao = AmbientOcclusionIntegrator::from_spec("ao:32:2.5").unwrap();
+    assert_eq!((ao.samples, ao.max_distance), (32, 2.5));
+    let ao = AmbientOcclusionIntegrator::from_spec("ao:1:0").unwrap();
+    assert_eq!((ao.samples, ao.max_distance), (1, 0.0));
+
+    for spec in [
+        "ao:",
+        "ao:32:",
+        "ao:32:2.5:",
+        "ao:32:2.5:1",
+        "ao:0",
+        "ao:-1",
+        "ao:x",
+        "ao:8:-1",
+        "ao:8:NaN",
+        "ao:8:x",
+        "aox",
+        "path",
+    ] {
+        assert!(
+            AmbientOcclusionIntegrator::from_spec(spec).is_err(),
+            "{}",
+            spec
+        );
+    }
+}
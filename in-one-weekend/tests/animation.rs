@@ -0,0 +1,138 @@
+use common::animation::{CameraAnimation, Interpolation, ObjectAnimation, Track};
+use common::camera::{Bokeh, CameraSetup};
+use common::ray::Ray;
+use common::{color, normal3, point3, vec3, Transform};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::options::Options;
+use in_one_weekend::run::{frame_path, render_frames};
+use in_one_weekend::{HitRecord, Hittable, HittableList, Instance, RenderSettings, Sphere};
+use std::env;
+use std::fs;
+use std::sync::Arc;
+
+#[test]
+fn test_instance_moves_object() {
+    let sphere = Arc::new(Sphere::new(&point3![1, 0, 0], 1.0, None));
+    // Turned to +z, doubled and lifted: the sphere of radius 2 around
+    // (0, 3, 2).
+    let transform = Transform::translate(&vec3![0, 3, 0])
+        * Transform::rotate_y(-90.0)
+        * Transform::scale(2.0, 2.0, 2.0);
+    let instance = Instance::new(sphere, transform);
+    let ray = Ray::new(&point3![0, 3, 10], &vec3![0, 0, -1]);
+    let mut rec = HitRecord::new();
+    assert!(instance.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert!((rec.t - 6.0).abs() < 1e-9, "{}", rec.t);
+    assert!((rec.p - point3![0, 3, 4]).length() < 1e-9);
+    assert!((rec.normal - normal3![0, 0, 1]).length() < 1e-9);
+    assert!(rec.front_face);
+
+    // Stretched along z, the normal still points straight out at the tip.
+    let sphere = Arc::new(Sphere::new(&point3![0, 0, 0], 1.0, None));
+    let stretched = Instance::new(sphere, Transform::scale(1.0, 1.0, 3.0));
+    let ray = Ray::new(&point3![0, 0, 10], &vec3![0, 0, -1]);
+    assert!(stretched.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert!((rec.t - 7.0).abs() < 1e-9, "{}", rec.t);
+    assert!((rec.normal - normal3![0, 0, 1]).length() < 1e-9);
+
+    let animation = ObjectAnimation {
+        translation: Track::new(
+            Interpolation::Linear,
+            vec![(0.0, vec3![0, 0, 0]), (1.0, vec3![0, 10, 0])],
+        ),
+        ..Default::default()
+    };
+    let sphere = Arc::new(Sphere::new(&point3![0, 0, 0], 1.0, None));
+    let moved = Instance::animated(sphere, &animation, 0.5);
+    let ray = Ray::new(&point3![0, 0, 10], &vec3![0, 0, -1]);
+    assert!(!moved.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+}
+
+#[test]
+fn test_frame_path() {
+    assert_eq!(frame_path("frame_####.ppm", 7), "frame_0007.ppm");
+    assert_eq!(frame_path("out/#.ppm", 12), "out/12.ppm");
+    assert_eq!(frame_path("a_###_b##.ppm", 3), "a_003_b##.ppm");
+}
+
+#[test]
+fn test_render_frames() {
+    let dir = env::temp_dir().join(format!("frames-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let pattern = dir.join("f##.ppm").to_string_lossy().into_owned();
+    let args = ["--frames", "1:2", "--fps", "1", "--frame-output", &pattern];
+    let options = Options::parse(args.iter().map(|a| a.to_string())).unwrap();
+
+    // The camera passes the sphere: in front of it at 1 s, beside it at 2 s.
+    let animation = CameraAnimation::new(
+        Track::new(
+            Interpolation::Spline,
+            vec![(1.0, point3![0, 0, 3]), (2.0, point3![10, 0, 3])],
+        ),
+        Track::new(
+            Interpolation::Spline,
+            vec![(1.0, point3![0, 0, 0]), (2.0, point3![10, 0, 0])],
+        ),
+    );
+    let setup = CameraSetup {
+        lookfrom: point3![0, 0, 3],
+        lookat: point3![0, 0, 0],
+        vup: vec3![0, 1, 0],
+        vfov: 60.0,
+        aspect_ratio: 1.0,
+        aperture: 0.0,
+        focus_dist: 3.0,
+        bokeh: Bokeh::default(),
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+    };
+    let scene = |time: f64| {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            &point3![0, 0, 0],
+            1.0,
+            Some(Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]))),
+        )));
+        (world, animation.setup_at(&setup, time))
+    };
+    let settings = RenderSettings::new(8, 8, 1);
+    assert!(render_frames(&options, &settings, &PathIntegrator::new(2), &scene).unwrap());
+
+    let frames: Vec<_> = [1, 2]
+        .iter()
+        .map(|frame| fs::read_to_string(dir.join(format!("f{:02}.ppm", frame))).unwrap())
+        .collect();
+    assert!(frames[0].starts_with("P3\n8 8\n"));
+    assert_ne!(frames[0], frames[1]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Without --frames nothing is rendered.
+    let options = Options::parse(std::iter::empty()).unwrap();
+    assert!(!render_frames(&options, &settings, &PathIntegrator::new(2), &scene).unwrap());
+    assert!(Options::parse(["--fps", "30"].iter().map(|a| a.to_string())).is_err());
+    assert!(Options::parse(["--frames", "3:1"].iter().map(|a| a.to_string())).is_err());
+}
+
+#[test]
+fn test_config_follows_camera_keys() {
+    let dir = env::temp_dir().join(format!("camera-keys-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = |name: &str, keys: &str| {
+        let path = dir.join(name);
+        fs::write(&path, keys).unwrap();
+        let args = ["--camera-keys", path.to_str().unwrap()];
+        let options = Options::parse(args.iter().map(|a| a.to_string())).unwrap();
+        options.config("test").unwrap()
+    };
+
+    let keys = "0 0 0 3 0 0 0\n1 10 0 3 10 0 0\n";
+    let original = config("a.keys", keys);
+    // Moved keys change the image, a moved file does not.
+    assert_ne!(
+        original,
+        config("a.keys", "0 0 0 3 0 0 0\n1 10 0 4 10 0 0\n")
+    );
+    assert_eq!(original, config("b.keys", keys));
+    fs::remove_dir_all(&dir).unwrap();
+}
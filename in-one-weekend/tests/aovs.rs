@@ -0,0 +1,67 @@
+use common::film::AovSample;
+use common::ray::Ray;
+use common::sampler::{RandomSampler, Sampler};
+use common::{color, point3, vec3, Color};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::{Lambertian, Metal};
+use in_one_weekend::{HittableList, Integrator, Material, Sphere};
+use std::sync::Arc;
+
+const LOBES: [&str; 3] = ["diffuse_direct", "diffuse_indirect", "specular"];
+
+/// The radiance and AOVs seen from (0, 0, 5) at the top of a unit sphere of
+/// `material`, inside a diffuse sphere of radius 10 if `enclosed`.
+fn trace(material: Arc<dyn Material>, enclosed: bool, index: usize) -> (Color, AovSample) {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![0, 0, 0],
+        1.0,
+        Some(material),
+    )));
+    if enclosed {
+        let wall = Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]));
+        world.add(Arc::new(Sphere::new(&point3![0, 0, 0], 10.0, Some(wall))));
+    }
+    let ray = Ray::new(&point3![0, 0, 5], &vec3![0, 0, -1]);
+    let mut sampler = RandomSampler::new(11);
+    sampler.start_pixel_sample(0, 0, index);
+    let mut aovs = AovSample::new();
+    let color = PathIntegrator::new(8).radiance_with_aovs(&ray, &world, &mut sampler, &mut aovs);
+    (color, aovs)
+}
+
+/// The only lobe set is `lobe`, holding the whole radiance.
+fn assert_lobe(color: Color, aovs: &AovSample, lobe: &str) {
+    for name in LOBES {
+        if name == lobe {
+            assert_eq!(aovs.get(name), Some(color), "{}", name);
+        } else {
+            assert_eq!(aovs.get(name), None, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_lobe_split() {
+    let diffuse: Arc<dyn Material> = Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]));
+    let metal: Arc<dyn Material> = Arc::new(Metal::new(&color![0.8, 0.8, 0.8], 0.0));
+    for index in 0..16 {
+        // Off a lone convex sphere every diffuse bounce reaches the sky.
+        let (color, aovs) = trace(diffuse.clone(), false, index);
+        assert!(!color.is_black());
+        assert_lobe(color, &aovs, "diffuse_direct");
+        assert_eq!(aovs.get("normal"), Some(color![0, 0, 1]));
+        assert_eq!(aovs.get("depth"), Some(color![4, 4, 4]));
+        assert_eq!(aovs.get("albedo"), Some(color![0.5, 0.5, 0.5]));
+
+        // Inside the enclosing sphere every one hits it first.
+        let (color, aovs) = trace(diffuse.clone(), true, index);
+        assert_lobe(color, &aovs, "diffuse_indirect");
+
+        // A mirror is specular whatever it reflects.
+        let (color, aovs) = trace(metal.clone(), false, index);
+        assert_lobe(color, &aovs, "specular");
+        let (color, aovs) = trace(metal.clone(), true, index);
+        assert_lobe(color, &aovs, "specular");
+    }
+}
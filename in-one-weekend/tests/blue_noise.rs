@@ -0,0 +1,117 @@
+use common::sampler::{
+    BlueNoiseMask, BlueNoiseMask2D, BlueNoiseSampler, RandomSampler, Sampler, SobolSampler,
+};
+use common::{color, point3, vec3, PerspectiveCamera};
+use in_one_weekend::ambient_occlusion::AmbientOcclusionIntegrator;
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::{HittableList, RenderSettings, Renderer, Sphere};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+const SIZE: usize = 64;
+
+/// Size of the blue noise masks, smaller than those of the renderer to keep
+/// the test fast; they tile the image.
+const MASK_SIZE: usize = 32;
+
+/// Ambient occlusion around the contact of a ball with the ground: partially
+/// occluded, smoothly varying everywhere in the frame.
+fn render(sampler: Box<dyn Sampler>, samples_per_pixel: usize) -> Vec<f64> {
+    let material = Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![0.0, -1000.0, 0.0],
+        1000.0,
+        Some(material.clone()),
+    )));
+    world.add(Arc::new(Sphere::new(
+        &point3![0.0, 1.0, 0.0],
+        1.0,
+        Some(material),
+    )));
+
+    let camera = Box::new(PerspectiveCamera::new(
+        point3![0.0, 6.0, 0.1],
+        point3![0.0, 0.0, 0.0],
+        vec3![0, 1, 0],
+        30.0,
+        1.0,
+        0.0,
+        1.0,
+    ));
+    let settings = RenderSettings::new(SIZE, SIZE, samples_per_pixel);
+    let mut renderer = Renderer::new(camera, sampler, settings);
+    renderer.render(&world, &AmbientOcclusionIntegrator::new(1, 3.0));
+
+    let (_, data) = renderer.film.channel_data("beauty").unwrap();
+    data.iter().step_by(3).map(|&v| v as f64).collect()
+}
+
+/// Fraction of the power spectrum of `error` (DC excluded) at frequencies
+/// below `SIZE / 8` cycles per image.
+fn low_frequency_fraction(error: &[f64]) -> f64 {
+    let mean = error.iter().sum::<f64>() / error.len() as f64;
+    // exp(-2 pi i k / SIZE)
+    let twiddles: Vec<(f64, f64)> = (0..SIZE)
+        .map(|k| (-2.0 * PI * k as f64 / SIZE as f64).sin_cos())
+        .map(|(sin, cos)| (cos, sin))
+        .collect();
+    // Transform the rows, then the columns of the result.
+    let mut rows = vec![(0.0, 0.0); SIZE * SIZE];
+    for y in 0..SIZE {
+        for kx in 0..SIZE {
+            let (mut re, mut im) = (0.0, 0.0);
+            for x in 0..SIZE {
+                let (cos, sin) = twiddles[kx * x % SIZE];
+                let e = error[y * SIZE + x] - mean;
+                re += e * cos;
+                im += e * sin;
+            }
+            rows[y * SIZE + kx] = (re, im);
+        }
+    }
+    let (mut low, mut total) = (0.0, 0.0);
+    for ky in 0..SIZE {
+        for kx in 0..SIZE {
+            if kx == 0 && ky == 0 {
+                continue;
+            }
+            let (mut re, mut im) = (0.0, 0.0);
+            for y in 0..SIZE {
+                let (cos, sin) = twiddles[ky * y % SIZE];
+                let (r, i) = rows[y * SIZE + kx];
+                re += r * cos - i * sin;
+                im += r * sin + i * cos;
+            }
+            let power = re * re + im * im;
+            let fx = kx.min(SIZE - kx) as f64;
+            let fy = ky.min(SIZE - ky) as f64;
+            if (fx * fx + fy * fy).sqrt() <= (SIZE / 8) as f64 {
+                low += power;
+            }
+            total += power;
+        }
+    }
+    low / total
+}
+
+#[test]
+fn test_blue_noise_error_is_high_frequency() {
+    let reference = render(Box::new(SobolSampler::new(7)), 64);
+    let error = |sampler: Box<dyn Sampler>| -> Vec<f64> {
+        render(sampler, 1)
+            .iter()
+            .zip(&reference)
+            .map(|(a, b)| a - b)
+            .collect()
+    };
+
+    let white = low_frequency_fraction(&error(Box::new(RandomSampler::new(7))));
+    let blue_noise = BlueNoiseSampler::with_masks(
+        Arc::new(BlueNoiseMask::generate(MASK_SIZE, 7)),
+        Arc::new(BlueNoiseMask2D::generate(MASK_SIZE, 7)),
+        7,
+    );
+    let blue = low_frequency_fraction(&error(Box::new(blue_noise)));
+    assert!(blue < 0.5 * white, "white {} blue {}", white, blue);
+}
@@ -0,0 +1,177 @@
+use common::camera::{
+    self, Bokeh, Camera, CameraSetup, Convergence, StereoCamera, StereoLayout, StereoRig,
+};
+use common::sampler::RandomSampler;
+use common::{color, point3, vec3};
+use in_one_weekend::integrator::{PathIntegrator, LIGHTING_AOVS};
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::{HittableList, RenderSettings, Renderer, Sphere};
+use std::sync::Arc;
+
+/// Looking at the origin from `distance` away, with a 2:1 image.
+fn setup(distance: f64) -> CameraSetup {
+    CameraSetup {
+        lookfrom: point3![0, 0, distance],
+        lookat: point3![0, 0, 0],
+        vup: vec3![0, 1, 0],
+        vfov: 60.0,
+        aspect_ratio: 2.0,
+        aperture: 0.0,
+        focus_dist: distance,
+        bokeh: Bokeh::default(),
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+    }
+}
+
+/// Render a unit sphere at the origin into a `width` x `height` image.
+fn render_camera(camera: Box<dyn Camera>, width: usize, height: usize) -> Renderer {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![0, 0, 0],
+        1.0,
+        Some(Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]))),
+    )));
+
+    let mut settings = RenderSettings::new(width, height, 1);
+    settings.aovs = true;
+    let mut renderer = Renderer::new(camera, Box::new(RandomSampler::new(1)), settings);
+    renderer.render(&world, &PathIntegrator::new(2));
+    renderer
+}
+
+/// Render the sphere seen from `distance` away into a 64 x 32 image.
+fn render(projection: &str, distance: f64) -> Renderer {
+    let camera = camera::from_name(projection, &setup(distance)).unwrap();
+    render_camera(camera, 64, 32)
+}
+
+/// Pixels covered by the sphere.
+fn coverage(projection: &str, distance: f64) -> usize {
+    let renderer = render(projection, distance);
+    // The normal AOV is zero where nothing was hit.
+    let (_, normal) = renderer.film.channel_data("normal").unwrap();
+    normal
+        .chunks_exact(3)
+        .filter(|n| n.iter().any(|&c| c != 0.0))
+        .count()
+}
+
+#[test]
+fn test_orthographic_size_does_not_depend_on_distance() {
+    let near = coverage("orthographic:4", 3.0);
+    let far = coverage("orthographic:4", 30.0);
+    assert!(near > 100, "{}", near);
+    assert_eq!(near, far);
+
+    // A perspective camera sees it shrink.
+    assert!(coverage("perspective", 30.0) < coverage("perspective", 3.0) / 10);
+}
+
+#[test]
+fn test_camera_names() {
+    let setup = CameraSetup {
+        lookfrom: point3![0, 0, 1],
+        lookat: point3![0, 0, 0],
+        vup: vec3![0, 1, 0],
+        vfov: 90.0,
+        aspect_ratio: 1.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        bokeh: Bokeh::default(),
+        shift: (0.0, 0.0),
+        tilt: (0.0, 0.0),
+    };
+    for name in camera::CAMERA_NAMES {
+        assert!(camera::from_name(name, &setup).is_ok(), "{}", name);
+    }
+    assert!(camera::from_name("orthographic:0", &setup).is_err());
+    assert!(camera::from_name("orthographic:wide", &setup).is_err());
+    assert!(camera::from_name("fisheye:400", &setup).is_err());
+    assert!(camera::from_name("cubemap:2", &setup).is_err());
+    assert!(camera::from_name("pinhole", &setup).is_err());
+}
+
+#[test]
+fn test_fisheye_corners_are_black() {
+    let renderer = render("fisheye:180", 3.0);
+    assert!(renderer.stats.primary_rays < 64 * 32);
+    for (i, j) in [(0, 0), (63, 0), (0, 31), (63, 31), (2, 16)] {
+        assert_eq!(renderer.film.pixel_value(i, j), color![], "{} {}", i, j);
+    }
+    // The sphere is in the middle, the sky around it.
+    assert!(!renderer.film.pixel_value(32, 16).is_black());
+    assert!(!renderer.film.pixel_value(32, 30).is_black());
+}
+
+#[test]
+fn test_physical_exposure() {
+    let mean = |spec: &str| {
+        let renderer = render(spec, 3.0);
+        let (_, beauty) = renderer.film.channel_data("beauty").unwrap();
+        beauty.iter().map(|&c| c as f64).sum::<f64>() / beauty.len() as f64
+    };
+    // Defaults: 50 mm, f/2, 1 s, ISO 400.
+    let base = mean("physical");
+    assert!(base > 0.1, "{}", base);
+    // Same exposure by shutter and ISO: the same image.
+    assert!((mean("physical:50:2:0.5:800") - base).abs() < 1e-6 * base);
+    // Two stops less light.
+    assert!((mean("physical:50:2:0.25") - base / 4.0).abs() < 1e-6 * base);
+}
+
+#[test]
+fn test_physical_lighting_aovs_add_up_to_beauty() {
+    // Close enough for the sphere to fill the image.
+    let renderer = render("physical:50:2:0.25", 2.0);
+    let (_, beauty) = renderer.film.channel_data("beauty").unwrap();
+    let lobes = LIGHTING_AOVS.map(|name| renderer.film.channel_data(name).unwrap().1);
+    for (k, &value) in beauty.iter().enumerate() {
+        let sum: f32 = lobes.iter().map(|lobe| lobe[k]).sum();
+        assert!(
+            (sum - value).abs() <= 1e-5 * value.max(1.0),
+            "{} {}",
+            sum,
+            value
+        );
+    }
+}
+
+#[test]
+fn test_stereo_parallax() {
+    // Mean column of the sphere in the left and the right half.
+    let centers = |convergence_distance: f64, convergence: Convergence| {
+        let rig = StereoRig {
+            eye_distance: 0.5,
+            convergence_distance,
+            convergence,
+        };
+        let camera =
+            StereoCamera::from_name("perspective", &setup(3.0), &rig, StereoLayout::SideBySide)
+                .unwrap();
+        let renderer = render_camera(Box::new(camera), 128, 32);
+        let (_, normal) = renderer.film.channel_data("normal").unwrap();
+        let mut sums = [(0.0, 0.0); 2];
+        for (k, n) in normal.chunks_exact(3).enumerate() {
+            if n.iter().any(|&c| c != 0.0) {
+                let column = k % 128;
+                let sum = &mut sums[column / 64];
+                *sum = (sum.0 + (column % 64) as f64, sum.1 + 1.0);
+            }
+        }
+        sums.map(|(sum, count)| sum / count)
+    };
+
+    for convergence in [Convergence::OffAxis, Convergence::ToeIn] {
+        // Converged on the sphere, it is in about the same place for both
+        // eyes; its outline differs a little as they see it from different
+        // sides.
+        let [left, right] = centers(3.0, convergence);
+        assert!((left - right).abs() < 1.5, "{} {}", left, right);
+        assert!((left - 31.5).abs() < 1.0 && (right - 31.5).abs() < 1.5);
+        // Converged behind it, it comes out of the screen: the left eye
+        // sees it further right.
+        let [left, right] = centers(30.0, convergence);
+        assert!(left > right + 4.0, "{} {}", left, right);
+    }
+}
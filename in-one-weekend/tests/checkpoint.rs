@@ -0,0 +1,124 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use fixture::renderer_with;
+use in_one_weekend::checkpoint::{scene_hash, Checkpointer};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::Dielectric;
+use in_one_weekend::renderer::ProgressiveSettings;
+use in_one_weekend::{HittableList, RenderSettings, Renderer};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+fn scene(ir: f64) -> HittableList {
+    fixture::scene(Arc::new(Dielectric::new(ir)))
+}
+
+fn renderer(samples_per_pixel: usize) -> Renderer {
+    let mut settings = RenderSettings::new(20, 10, samples_per_pixel);
+    settings.aovs = true;
+    renderer_with(settings, 4)
+}
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("checkpoint-{}-{}.bin", name, std::process::id()))
+}
+
+fn render(
+    renderer: &mut Renderer,
+    world: &HittableList,
+    checkpointer: Option<&mut Checkpointer>,
+) -> bool {
+    renderer.render_progressive(
+        world,
+        &PathIntegrator::new(10),
+        &ProgressiveSettings::new(2),
+        &mut |_| (),
+        checkpointer,
+    )
+}
+
+#[test]
+fn test_resumed_render_matches_uninterrupted() {
+    let world = scene(1.5);
+    let mut uninterrupted = renderer(6);
+    assert!(render(&mut uninterrupted, &world, None));
+
+    let path = checkpoint_path("resume");
+    let mut first = renderer(6);
+    let mut checkpointer = Checkpointer::new(&path, scene_hash(&world), "test");
+    checkpointer.interrupt_flag().store(true, Ordering::SeqCst);
+    assert!(!render(&mut first, &world, Some(&mut checkpointer)));
+    assert_eq!(first.samples_done, 2);
+
+    let mut resumed = renderer(6);
+    let mut checkpointer = Checkpointer::new(&path, scene_hash(&world), "test");
+    checkpointer.resume(&mut resumed).unwrap();
+    assert_eq!(resumed.samples_done, 2);
+    assert!(render(&mut resumed, &world, Some(&mut checkpointer)));
+    fs::remove_file(&path).unwrap();
+
+    for name in ["beauty", "albedo", "normal"] {
+        assert_eq!(
+            uninterrupted.film.channel_data(name),
+            resumed.film.channel_data(name)
+        );
+    }
+    assert_eq!(resumed.film.samples(3, 3), 6);
+    assert_eq!(resumed.stats, uninterrupted.stats);
+}
+
+#[test]
+fn test_refuse_changed_scene_or_settings() {
+    let world = scene(1.5);
+    let path = checkpoint_path("refuse");
+    let mut renderer6 = renderer(6);
+    let checkpointer = Checkpointer::new(&path, scene_hash(&world), "test");
+    checkpointer.write(&renderer6).unwrap();
+
+    let other_scene = Checkpointer::new(&path, scene_hash(&scene(1.3)), "test");
+    let error = other_scene.resume(&mut renderer6).unwrap_err();
+    assert!(error.contains("different scene"), "{}", error);
+
+    let other_config = Checkpointer::new(&path, scene_hash(&world), "other");
+    let error = other_config.resume(&mut renderer6).unwrap_err();
+    assert!(error.contains("different settings"), "{}", error);
+
+    let error = checkpointer.resume(&mut renderer(8)).unwrap_err();
+    assert!(error.contains("different settings"), "{}", error);
+
+    assert!(checkpointer.resume(&mut renderer6).is_ok());
+    fs::remove_file(&path).unwrap();
+    assert!(checkpointer.resume(&mut renderer6).is_err());
+}
+
+#[test]
+fn test_corrupt_checkpoint() {
+    let world = scene(1.5);
+    let path = checkpoint_path("corrupt");
+    let checkpointer = Checkpointer::new(&path, scene_hash(&world), "test");
+    checkpointer.write(&renderer(6)).unwrap();
+    let bytes = fs::read(&path).unwrap();
+
+    // Magic, the two hashes, the samples done and four counts of the stats,
+    // then the film's magic and size.
+    let samples_done = 24;
+    let film_width = 72;
+    for (at, value) in [
+        (samples_done, u64::MAX),
+        (film_width, u64::MAX),
+        (film_width, 1 << 40),
+    ] {
+        let mut corrupt = bytes.clone();
+        corrupt[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        fs::write(&path, corrupt).unwrap();
+        assert!(checkpointer.resume(&mut renderer(6)).is_err());
+    }
+    fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    let error = checkpointer.resume(&mut renderer(6)).unwrap_err();
+    assert!(error.contains("cannot read checkpoint"), "{}", error);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,63 @@
+//! Scene and camera shared by the render tests. The test files include this
+//! as `fixture`, since `common` is the name of the crate they test with.
+#![allow(dead_code)]
+
+use common::sampler::RandomSampler;
+use common::{color, point3, vec3, Camera, PerspectiveCamera};
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::{HittableList, Material, RenderSettings, Renderer, Sphere};
+use std::sync::Arc;
+
+/// A huge yellow diffuse sphere whose top serves as ground below the origin.
+pub fn ground() -> HittableList {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![0.0, -100.5, -1.0],
+        100.0,
+        Some(Arc::new(Lambertian::new(&color![0.8, 0.8, 0.0]))),
+    )));
+    world
+}
+
+/// A ball of `material` with radius 0.5 at (0, 0, -1) on the [`ground`].
+pub fn scene(material: Arc<dyn Material>) -> HittableList {
+    let mut world = ground();
+    world.add(Arc::new(Sphere::new(
+        &point3![0.0, 0.0, -1.0],
+        0.5,
+        Some(material),
+    )));
+    world
+}
+
+/// The [`scene`] with a blue diffuse ball.
+pub fn diffuse_scene() -> HittableList {
+    scene(Arc::new(Lambertian::new(&color![0.1, 0.2, 0.5])))
+}
+
+/// Looking from (0, 0, 1) at the ball of the [`scene`], in focus, with a
+/// field of view of 60° for an image twice as wide as high.
+pub fn camera(aperture: f64) -> Box<dyn Camera> {
+    Box::new(PerspectiveCamera::new(
+        point3![0, 0, 1],
+        point3![0, 0, -1],
+        vec3![0, 1, 0],
+        60.0,
+        2.0,
+        aperture,
+        2.0,
+    ))
+}
+
+/// A renderer of the pinhole [`camera`] sampling with `seed`.
+pub fn renderer_with(settings: RenderSettings, seed: u64) -> Renderer {
+    Renderer::new(camera(0.0), Box::new(RandomSampler::new(seed)), settings)
+}
+
+/// A renderer of 40 x 20 pixels, several tiles, at 4 samples on `threads`
+/// threads.
+pub fn renderer(threads: usize) -> Renderer {
+    let mut settings = RenderSettings::new(40, 20, 4);
+    settings.threads = threads;
+    renderer_with(settings, 4)
+}
@@ -0,0 +1,124 @@
+use common::ray::Ray;
+use common::sampler::{RandomSampler, Sampler};
+use common::{color, point3, vec3, Color, Vector3};
+use in_one_weekend::debug_integrator::{heatmap, id_color, DebugIntegrator, DebugMode};
+use in_one_weekend::integrator;
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::{HittableList, Integrator, Sphere};
+use std::sync::Arc;
+
+/// A unit sphere at the origin behind a smaller one at (3, 0, 0).
+fn world() -> HittableList {
+    let material = Arc::new(Lambertian::new(&color![0.5, 0.5, 0.5]));
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![3, 0, 0],
+        0.5,
+        Some(material.clone()),
+    )));
+    world.add(Arc::new(Sphere::new(
+        &point3![0, 0, 0],
+        1.0,
+        Some(material),
+    )));
+    world
+}
+
+/// The color of `mode` for the ray from (0, 0, 5) straight at the unit
+/// sphere, hitting it at (0, 0, 1) after a distance of 4.
+fn shade(mode: DebugMode) -> Color {
+    let ray = Ray::new(&point3![0, 0, 5], &vec3![0, 0, -1]);
+    let mut sampler = RandomSampler::new(7);
+    sampler.start_pixel_sample(0, 0, 0);
+    DebugIntegrator::new(mode).radiance(&ray, &world(), &mut sampler)
+}
+
+fn assert_color(actual: Color, expected: Color) {
+    assert!(
+        Vector3::from(actual - expected).length() < 1e-9,
+        "{:?}",
+        actual
+    );
+}
+
+#[test]
+fn test_debug_modes() {
+    assert_color(shade(DebugMode::ShadingNormal), color![0.5, 0.5, 1.0]);
+    assert_color(shade(DebugMode::GeometricNormal), color![0.5, 0.5, 1.0]);
+    assert_color(shade(DebugMode::Tangent), color![1.0, 0.5, 0.5]);
+    assert_color(
+        shade(DebugMode::Depth { max_distance: 8.0 }),
+        color![0.5, 0.5, 0.5],
+    );
+    assert_color(
+        shade(DebugMode::Depth { max_distance: 2.0 }),
+        color![0.0, 0.0, 0.0],
+    );
+    assert_color(shade(DebugMode::Uv), color![0.25, 0.5, 0.0]);
+    assert_color(shade(DebugMode::Albedo), color![0.5, 0.5, 0.5]);
+    // The unit sphere is the second object of the list.
+    assert_color(shade(DebugMode::ObjectId), id_color(1));
+    // A diffuse bounce off a lone convex sphere always escapes.
+    assert_color(shade(DebugMode::Bounces { max_depth: 4 }), heatmap(0.25));
+
+    let miss = Ray::new(&point3![0, 5, 5], &vec3![0, 0, -1]);
+    let mut sampler = RandomSampler::new(7);
+    let bounces = DebugIntegrator::new(DebugMode::Bounces { max_depth: 4 });
+    assert_color(
+        bounces.radiance(&miss, &world(), &mut sampler),
+        heatmap(0.0),
+    );
+    let normal = DebugIntegrator::new(DebugMode::ShadingNormal);
+    assert_color(normal.radiance(&miss, &world(), &mut sampler), color![]);
+}
+
+/// The material id color of a unit sphere made of `material`.
+fn material_color(material: Lambertian) -> Color {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        &point3![0, 0, 0],
+        1.0,
+        Some(Arc::new(material)),
+    )));
+    let ray = Ray::new(&point3![0, 0, 5], &vec3![0, 0, -1]);
+    let mut sampler = RandomSampler::new(7);
+    DebugIntegrator::new(DebugMode::MaterialId).radiance(&ray, &world, &mut sampler)
+}
+
+#[test]
+fn test_material_id() {
+    // Equal materials get the same color wherever they were allocated.
+    let gray = material_color(Lambertian::new(&color![0.5, 0.5, 0.5]));
+    assert_eq!(
+        gray,
+        material_color(Lambertian::new(&color![0.5, 0.5, 0.5]))
+    );
+    assert_ne!(
+        gray,
+        material_color(Lambertian::new(&color![0.5, 0.5, 0.6]))
+    );
+    assert_ne!(gray, color![]);
+}
+
+#[test]
+fn test_parse_debug_mode() {
+    assert_eq!("normal".parse(), Ok(DebugMode::ShadingNormal));
+    assert_eq!("depth".parse(), Ok(DebugMode::Depth { max_distance: 20.0 }));
+    assert_eq!(
+        "depth:2.5".parse(),
+        Ok(DebugMode::Depth { max_distance: 2.5 })
+    );
+    for spec in [
+        "depth:",
+        "depth:0",
+        "depth:-1",
+        "depth:NaN",
+        "depth:x",
+        "normal:1",
+        "foo",
+    ] {
+        assert!(spec.parse::<DebugMode>().is_err(), "{}", spec);
+    }
+    assert!(integrator::from_name("depth:8", 5).is_ok());
+    assert!(integrator::from_name("depth:1:2", 5).is_err());
+}
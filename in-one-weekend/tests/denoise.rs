@@ -0,0 +1,57 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use common::denoise::{denoise_film, DenoiseSettings};
+use common::{color, point3, Film, Image, Vector3};
+use fixture::{ground, renderer_with};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::{Lambertian, Metal};
+use in_one_weekend::progress::NoProgress;
+use in_one_weekend::{HittableList, RenderSettings, Sphere};
+use std::sync::Arc;
+
+fn scene() -> HittableList {
+    let mut world = ground();
+    world.add(Arc::new(Sphere::new(
+        &point3![-0.5, 0.0, -1.0],
+        0.5,
+        Some(Arc::new(Lambertian::new(&color![0.1, 0.2, 0.5]))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        &point3![0.6, 0.0, -1.2],
+        0.5,
+        Some(Arc::new(Metal::new(&color![0.8, 0.6, 0.2], 0.3))),
+    )));
+    world
+}
+
+fn render(samples_per_pixel: usize, seed: u64) -> Film {
+    let mut settings = RenderSettings::new(96, 48, samples_per_pixel);
+    settings.aovs = true;
+    let mut renderer = renderer_with(settings, seed);
+    renderer.progress = Box::new(NoProgress);
+    renderer.render(&scene(), &PathIntegrator::new(10));
+    renderer.film
+}
+
+fn beauty(film: &Film) -> Image {
+    let (components, data) = film.channel_data("beauty").unwrap();
+    Image::from_data(film.width, film.height, components, &data)
+}
+
+fn mean_squared_error(a: &Image, b: &Image) -> f64 {
+    let sum: f64 = (a.pixels.iter().zip(&b.pixels))
+        .map(|(p, q)| Vector3::from(*p - *q).length_squared())
+        .sum();
+    sum / a.pixels.len() as f64
+}
+
+#[test]
+fn test_denoised_render_is_closer_to_reference() {
+    let reference = beauty(&render(256, 1));
+    let noisy = render(4, 2);
+    let denoised = denoise_film(&noisy, &DenoiseSettings::default());
+    let before = mean_squared_error(&beauty(&noisy), &reference);
+    let after = mean_squared_error(&denoised, &reference);
+    assert!(after < 0.5 * before, "{} -> {}", before, after);
+}
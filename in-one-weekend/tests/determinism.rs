@@ -0,0 +1,54 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use common::filter;
+use common::sampler::RandomSampler;
+use common::{color, point3};
+use fixture::camera;
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::{Dielectric, Metal};
+use in_one_weekend::{HittableList, RenderSettings, Renderer, Sphere};
+use std::sync::Arc;
+
+fn scene() -> HittableList {
+    let mut world = fixture::scene(Arc::new(Dielectric::new(1.5)));
+    world.add(Arc::new(Sphere::new(
+        &point3![1.0, 0.0, -1.0],
+        0.5,
+        Some(Arc::new(Metal::new(&color![0.8, 0.6, 0.2], 0.3))),
+    )));
+    world
+}
+
+fn render(seed: u64, threads: usize) -> Vec<f32> {
+    render_filtered(seed, threads, "box")
+}
+
+fn render_filtered(seed: u64, threads: usize, filter: &str) -> Vec<f32> {
+    let mut settings = RenderSettings::new(40, 20, 4);
+    settings.threads = threads;
+    settings.filter = filter::from_name(filter).unwrap();
+    let mut renderer = Renderer::new(camera(0.1), Box::new(RandomSampler::new(seed)), settings);
+    renderer.render(&scene(), &PathIntegrator::new(10));
+    renderer.film.channel_data("beauty").unwrap().1
+}
+
+#[test]
+fn test_same_seed_same_image() {
+    let single = render(1, 1);
+    assert_eq!(single, render(1, 1));
+    assert_eq!(single, render(1, 3));
+    assert_eq!(single, render(1, 8));
+}
+
+#[test]
+fn test_different_seed_different_image() {
+    assert_ne!(render(1, 2), render(2, 2));
+}
+
+#[test]
+fn test_wide_filter_same_image() {
+    let single = render_filtered(1, 1, "mitchell:3");
+    assert_eq!(single, render_filtered(1, 4, "mitchell:3"));
+    assert_ne!(single, render(1, 1));
+}
@@ -0,0 +1,94 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use fixture::{diffuse_scene, renderer};
+use in_one_weekend::checkpoint::scene_hash;
+use in_one_weekend::distributed::{run_worker, Coordinator};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::Renderer;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+/// A misbehaving peer of the coordinator.
+#[derive(PartialEq)]
+enum BadPeer {
+    None,
+    /// Takes a job and disconnects without an answer.
+    Lost,
+    /// Takes a job and never answers, and another peer never handshakes.
+    Hung,
+}
+
+/// Render on a worker, which connects only after the bad peer took a job.
+fn render_distributed(bad_peer: BadPeer) -> Renderer {
+    let world = diffuse_scene();
+    let integrator = PathIntegrator::new(10);
+    let mut coordinator_renderer = renderer(1);
+    let mut coordinator = Coordinator::bind("127.0.0.1:0", scene_hash(&world), 7).unwrap();
+    coordinator.timeout = Duration::from_millis(300);
+    let addr = coordinator.local_addr().unwrap().to_string();
+    // Held open, silent, until the render is done.
+    let _silent = (bad_peer == BadPeer::Hung).then(|| TcpStream::connect(&addr).unwrap());
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut _hung = None;
+            if bad_peer != BadPeer::None {
+                let mut stream = TcpStream::connect(&addr).unwrap();
+                let mut handshake = [0; 24];
+                stream.read_exact(&mut handshake).unwrap();
+                stream.write_all(&1u64.to_le_bytes()).unwrap();
+                let mut job = [0; 8];
+                stream.read_exact(&mut job).unwrap();
+                if bad_peer == BadPeer::Hung {
+                    _hung = Some(stream);
+                }
+            }
+            let mut worker_renderer = renderer(2);
+            run_worker(
+                &addr,
+                &mut worker_renderer,
+                &world,
+                &integrator,
+                scene_hash(&world),
+                7,
+            )
+            .unwrap();
+        });
+        coordinator.render(&mut coordinator_renderer, &integrator);
+    });
+    coordinator_renderer
+}
+
+fn assert_same_image(a: &Renderer, b: &Renderer) {
+    for j in 0..a.film.height {
+        for i in 0..a.film.width {
+            assert_eq!(a.film.pixel_color(i, j), b.film.pixel_color(i, j));
+        }
+    }
+}
+
+#[test]
+fn test_distributed_matches_local() {
+    let mut local = renderer(3);
+    local.render(&diffuse_scene(), &PathIntegrator::new(10));
+    let distributed = render_distributed(BadPeer::None);
+    assert_same_image(&distributed, &local);
+    assert_eq!(distributed.stats, local.stats);
+}
+
+#[test]
+fn test_tile_of_lost_worker_is_reassigned() {
+    let mut local = renderer(1);
+    local.render(&diffuse_scene(), &PathIntegrator::new(10));
+    assert_same_image(&render_distributed(BadPeer::Lost), &local);
+}
+
+#[test]
+fn test_tile_of_hung_worker_is_reassigned() {
+    let mut local = renderer(1);
+    local.render(&diffuse_scene(), &PathIntegrator::new(10));
+    assert_same_image(&render_distributed(BadPeer::Hung), &local);
+}
@@ -0,0 +1,147 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use common::Transform;
+use fixture::{diffuse_scene, renderer};
+use in_one_weekend::ambient_occlusion::AmbientOcclusionIntegrator;
+use in_one_weekend::checkpoint::{scene_hash, Checkpointer};
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::progress::{ProgressReporter, RenderStats};
+use in_one_weekend::renderer::ProgressiveSettings;
+use in_one_weekend::{HittableList, Instance};
+use std::env;
+use std::fs;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+/// Everything the reporter was told, shared with the test.
+#[derive(Default)]
+struct Log {
+    tiles: usize,
+    tiles_done: Vec<RenderStats>,
+    finished: Option<(RenderStats, bool)>,
+    messages: Vec<String>,
+}
+
+struct LogProgress(Arc<Mutex<Log>>);
+
+impl ProgressReporter for LogProgress {
+    fn start(&mut self, tiles: usize) {
+        self.0.lock().unwrap().tiles = tiles;
+    }
+
+    fn tile_done(&mut self, stats: &RenderStats) {
+        self.0.lock().unwrap().tiles_done.push(*stats);
+    }
+
+    fn finish(&mut self, stats: &RenderStats, finished: bool) {
+        self.0.lock().unwrap().finished = Some((*stats, finished));
+    }
+
+    fn message(&mut self, message: &str) {
+        self.0.lock().unwrap().messages.push(message.to_string());
+    }
+}
+
+#[test]
+fn test_reporter_sees_every_tile() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut renderer = renderer(3);
+    renderer.progress = Box::new(LogProgress(log.clone()));
+    renderer.render(&diffuse_scene(), &PathIntegrator::new(10));
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.tiles, 6);
+    assert_eq!(log.tiles_done.len(), 6);
+    assert!(log
+        .tiles_done
+        .windows(2)
+        .all(|w| w[0].primary_rays < w[1].primary_rays));
+    assert_eq!(log.finished, Some((renderer.stats, true)));
+    assert!(log.messages.is_empty());
+}
+
+#[test]
+fn test_progressive_reporter_counts_all_passes() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut renderer = renderer(2);
+    renderer.progress = Box::new(LogProgress(log.clone()));
+    renderer.render_progressive(
+        &diffuse_scene(),
+        &PathIntegrator::new(10),
+        &ProgressiveSettings::new(1),
+        &mut |_| (),
+        None,
+    );
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.tiles, 6 * 4);
+    assert_eq!(log.tiles_done.len(), 6 * 4);
+    assert_eq!(renderer.stats.primary_rays, 40 * 20 * 4);
+}
+
+#[test]
+fn test_stopped_render_is_reported() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut renderer = renderer(2);
+    renderer.progress = Box::new(LogProgress(log.clone()));
+    let world = diffuse_scene();
+    let path = env::temp_dir().join(format!("progress-{}.bin", std::process::id()));
+    let mut checkpointer = Checkpointer::new(&path, scene_hash(&world), "test");
+    checkpointer.interrupt_flag().store(true, Ordering::SeqCst);
+    let finished = renderer.render_progressive(
+        &world,
+        &PathIntegrator::new(10),
+        &ProgressiveSettings::new(1),
+        &mut |_| (),
+        Some(&mut checkpointer),
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert!(!finished);
+    let log = log.lock().unwrap();
+    assert_eq!(log.tiles_done.len(), 6);
+    assert_eq!(log.finished, Some((renderer.stats, false)));
+    assert_eq!(
+        log.messages,
+        [format!(
+            "Interrupted, checkpoint written to {}",
+            path.display()
+        )]
+    );
+}
+
+#[test]
+fn test_ray_counts() {
+    let mut path = renderer(3);
+    path.render(&diffuse_scene(), &PathIntegrator::new(10));
+    let stats = path.stats;
+    assert_eq!(stats.primary_rays, 40 * 20 * 4);
+    assert!(stats.secondary_rays > 0);
+    assert_eq!(stats.shadow_rays, 0);
+    assert!(stats.average_path_length() > 1.0 && stats.average_path_length() <= 10.0);
+    // The list and both spheres are tested for every ray.
+    assert_eq!(stats.intersection_tests, 3 * stats.rays());
+
+    // An instance is tested as well as the sphere in it.
+    let mut instanced = HittableList::new();
+    for object in diffuse_scene().objects {
+        instanced.add(Arc::new(Instance::new(object, Transform::default())));
+    }
+    let mut single = renderer(1);
+    single.render(&instanced, &PathIntegrator::new(10));
+    assert_eq!(single.stats.rays(), stats.rays());
+    assert_eq!(single.stats.intersection_tests, 5 * stats.rays());
+
+    // The counts do not depend on the threads.
+    let mut single = renderer(1);
+    single.render(&diffuse_scene(), &PathIntegrator::new(10));
+    assert_eq!(single.stats, stats);
+
+    let mut ao = renderer(3);
+    ao.render(&diffuse_scene(), &AmbientOcclusionIntegrator::new(8, 1.0));
+    assert_eq!(ao.stats.secondary_rays, 0);
+    assert_eq!(ao.stats.shadow_rays % 8, 0);
+    assert!(ao.stats.shadow_rays > 0);
+    assert_eq!(ao.stats.average_path_length(), 1.0);
+}
@@ -0,0 +1,84 @@
+#[path = "common/mod.rs"]
+mod fixture;
+
+use common::color;
+use fixture::renderer_with;
+use in_one_weekend::integrator::PathIntegrator;
+use in_one_weekend::material::Metal;
+use in_one_weekend::renderer::{AdaptiveSettings, ProgressiveSettings};
+use in_one_weekend::{HittableList, RenderSettings, Renderer};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn scene() -> HittableList {
+    fixture::scene(Arc::new(Metal::new(&color![0.8, 0.6, 0.2], 0.3)))
+}
+
+fn renderer(settings: RenderSettings) -> Renderer {
+    renderer_with(settings, 9)
+}
+
+#[test]
+fn test_passes_accumulate_to_full_render() {
+    let mut full = renderer(RenderSettings::new(24, 12, 8));
+    full.render(&scene(), &PathIntegrator::new(10));
+
+    let mut progressive = renderer(RenderSettings::new(24, 12, 8));
+    let mut snapshots = Vec::new();
+    progressive.render_progressive(
+        &scene(),
+        &PathIntegrator::new(10),
+        &ProgressiveSettings::new(3),
+        &mut |film| snapshots.push(film.samples(5, 5)),
+        None,
+    );
+    assert_eq!(snapshots, vec![3, 6, 8]);
+
+    let a = full.film.channel_data("beauty").unwrap().1;
+    let b = progressive.film.channel_data("beauty").unwrap().1;
+    for (a, b) in a.iter().zip(&b) {
+        assert!((a - b).abs() < 1e-5, "{} {}", a, b);
+    }
+}
+
+#[test]
+fn test_snapshot_interval() {
+    let mut progressive = renderer(RenderSettings::new(8, 4, 8));
+    let mut settings = ProgressiveSettings::new(1);
+    settings.interval = Some(Duration::from_secs(3600));
+    let mut snapshots = 0;
+    progressive.render_progressive(
+        &scene(),
+        &PathIntegrator::new(10),
+        &settings,
+        &mut |_| snapshots += 1,
+        None,
+    );
+    assert_eq!(snapshots, 1);
+}
+
+#[test]
+fn test_adaptive_passes_stop_converged_pixels() {
+    let mut settings = RenderSettings::new(24, 12, 1);
+    settings.adaptive = Some(AdaptiveSettings {
+        threshold: 0.02,
+        min_samples: 4,
+        max_samples: 64,
+    });
+    let mut progressive = renderer(settings);
+    progressive.render_progressive(
+        &scene(),
+        &PathIntegrator::new(10),
+        &ProgressiveSettings::new(4),
+        &mut |_| (),
+        None,
+    );
+
+    let film = &progressive.film;
+    let counts: Vec<usize> = (0..12)
+        .flat_map(|j| (0..24).map(move |i| film.samples(i, j)))
+        .collect();
+    assert!(counts.iter().all(|&n| n % 4 == 0 && (4..=64).contains(&n)));
+    assert!(counts.contains(&4));
+    assert!(counts.iter().any(|&n| n > 4));
+}
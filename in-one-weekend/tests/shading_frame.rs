@@ -0,0 +1,80 @@
+use common::ray::Ray;
+use common::sampler::{RandomSampler, Sampler};
+use common::{color, point3, vec3, Point3, Transform, Vector3};
+use in_one_weekend::material::{Lambertian, Material};
+use in_one_weekend::{HitRecord, Hittable, Instance, Sphere};
+use std::sync::Arc;
+
+fn assert_frame(rec: &HitRecord) {
+    let frame = rec.shading_frame();
+    for (a, b) in [(frame.u, frame.v), (frame.v, frame.w), (frame.w, frame.u)] {
+        assert!(a.dot(&b).abs() < 1e-9);
+        assert!((a.length() - 1.0).abs() < 1e-9);
+    }
+    assert!((frame.u.cross(&frame.v) - frame.w).length() < 1e-9);
+}
+
+fn hit(object: &dyn Hittable, origin: Point3, direction: Vector3) -> HitRecord {
+    let mut rec = HitRecord::new();
+    assert!(object.hit(
+        &Ray::new(&origin, &direction),
+        0.001,
+        f64::INFINITY,
+        &mut rec
+    ));
+    rec
+}
+
+#[test]
+fn test_sphere_frame() {
+    let sphere = Sphere::new(&point3![0, 0, 0], 1.0, None);
+    // The tangent follows increasing u, along the lines of latitude.
+    let rec = hit(&sphere, point3![0, 0, 5], vec3![0, 0, -1]);
+    assert_frame(&rec);
+    assert!((rec.tangent - vec3![1, 0, 0]).length() < 1e-9);
+
+    // From inside the normal is flipped and the frame stays right-handed.
+    let rec = hit(&sphere, point3![0, 0, 0], vec3![0.3, 0.2, -1]);
+    assert!(!rec.front_face);
+    assert_frame(&rec);
+
+    // At a pole, where dp/du vanishes, some frame is still built.
+    let rec = hit(&sphere, point3![0, 5, 0], vec3![0, -1, 0]);
+    assert_frame(&rec);
+}
+
+#[test]
+fn test_instance_frame() {
+    let sphere = Arc::new(Sphere::new(&point3![0, 0, 0], 1.0, None));
+    let turned = Instance::new(
+        sphere.clone(),
+        Transform::rotate_axis(&vec3![0, 0, 1], 90.0),
+    );
+    let rec = hit(&turned, point3![0, 0, 5], vec3![0, 0, -1]);
+    assert_frame(&rec);
+    assert!((rec.tangent - vec3![0, 1, 0]).length() < 1e-9);
+
+    let sheared = Transform::rotate_axis(&vec3![1, 1, 0], 30.0) * Transform::scale(1.0, 3.0, 0.5);
+    let stretched = Instance::new(sphere, sheared);
+    assert_frame(&hit(&stretched, point3![0.2, 0.1, 5], vec3![0, 0, -1]));
+}
+
+#[test]
+fn test_lambertian_scatters_around_normal() {
+    let sphere = Sphere::new(&point3![0, 0, 0], 1.0, None);
+    let rec = hit(&sphere, point3![0, 0, 0], vec3![1, 1, 1]);
+    let material = Lambertian::new(&color![0.5, 0.5, 0.5]);
+    let mut sampler = RandomSampler::new(7);
+    let (mut attenuation, mut scattered) = (color![], Ray::new(&point3![], &vec3![]));
+    let mut mean_cos = 0.0;
+    let n = 4000;
+    for i in 0..n {
+        sampler.start_pixel_sample(0, 0, i);
+        let r_in = Ray::new(&point3![0, 0, 0], &vec3![1, 1, 1]);
+        assert!(material.scatter(&r_in, &rec, &mut attenuation, &mut scattered, &mut sampler));
+        let cos = rec.normal.dot(&scattered.direction);
+        assert!(cos >= 0.0);
+        mean_cos += cos / n as f64;
+    }
+    assert!((mean_cos - 2.0 / 3.0).abs() < 0.03, "{}", mean_cos);
+}
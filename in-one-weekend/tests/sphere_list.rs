@@ -0,0 +1,72 @@
+use common::ray::Ray;
+use common::rng::Rng;
+use common::{color, point3, vec3, Point3};
+use in_one_weekend::material::Lambertian;
+use in_one_weekend::{HitRecord, Hittable, HittableList, Sphere, SphereList};
+use std::sync::Arc;
+
+/// A point with every coordinate random in [-size, size).
+fn random_point(rng: &mut Rng, size: f64) -> Point3 {
+    let mut random = || size * (2.0 * rng.next_f64() - 1.0);
+    point3![random(), random(), random()]
+}
+
+/// 61 overlapping spheres, so that the last group of four is not full, some
+/// of them turned inside out by a negative radius like hollow glass.
+fn spheres() -> Vec<Arc<Sphere>> {
+    let mut rng = Rng::new(3);
+    (0..61)
+        .map(|i| {
+            let center = random_point(&mut rng, 4.0);
+            let radius = (0.2 + rng.next_f64()) * if i % 7 == 0 { -1.0 } else { 1.0 };
+            let material = Lambertian::new(&color![0.5, 0.5, rng.next_f64()]);
+            Arc::new(Sphere::new(&center, radius, Some(Arc::new(material))))
+        })
+        .collect()
+}
+
+#[test]
+fn test_sphere_list_matches_scalar() {
+    let mut packed = SphereList::new();
+    let mut scalar = HittableList::new();
+    for sphere in spheres() {
+        packed.add(sphere.clone());
+        scalar.add(sphere);
+    }
+
+    let mut rng = Rng::new(5);
+    let mut hits = 0;
+    for k in 0..4000 {
+        // From inside and outside the spheres, some rays cut short.
+        let origin = random_point(&mut rng, 6.0);
+        let direction = random_point(&mut rng, 4.0) - origin;
+        let ray = Ray::new(&origin, &(direction * (0.5 + rng.next_f64())));
+        let t_max = if k % 3 == 0 {
+            rng.next_f64()
+        } else {
+            f64::INFINITY
+        };
+
+        let (mut a, mut b) = (HitRecord::new(), HitRecord::new());
+        let hit = packed.hit(&ray, 0.001, t_max, &mut a);
+        assert_eq!(hit, scalar.hit(&ray, 0.001, t_max, &mut b), "{}", k);
+        if hit {
+            hits += 1;
+            assert_eq!((a.t, a.p, a.normal), (b.t, b.p, b.normal), "{}", k);
+            assert_eq!((a.u, a.v, a.front_face), (b.u, b.v, b.front_face));
+            assert_eq!((a.tangent, a.bitangent), (b.tangent, b.bitangent));
+            assert!(Arc::ptr_eq(
+                a.mat_ptr.as_ref().unwrap(),
+                b.mat_ptr.as_ref().unwrap()
+            ));
+        }
+    }
+    assert!(hits > 1000, "{}", hits);
+}
+
+#[test]
+fn test_empty_sphere_list() {
+    let ray = Ray::new(&point3![0, 0, 0], &vec3![0, 0, -1]);
+    let mut rec = HitRecord::new();
+    assert!(!SphereList::new().hit(&ray, 0.001, f64::INFINITY, &mut rec));
+}